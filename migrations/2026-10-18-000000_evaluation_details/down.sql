@@ -0,0 +1,28 @@
+DROP TABLE IF EXISTS pair_cursors;
+DROP INDEX IF EXISTS pairs_factory_block_idx;
+DROP TABLE IF EXISTS pairs;
+
+DROP INDEX IF EXISTS liquidations_pair_idx;
+DROP TABLE IF EXISTS liquidations;
+
+DROP INDEX IF EXISTS arbitrage_legs_pool_idx;
+DROP TABLE IF EXISTS arbitrage_legs;
+
+-- values can not be dropped from an enum, `flashloan` goes with the type in the initial setup
+
+DROP INDEX IF EXISTS mev_inspections_chain_block_idx;
+
+ALTER TABLE IF EXISTS mev_inspections
+    DROP COLUMN IF EXISTS gas_cost,
+    DROP COLUMN IF EXISTS coinbase_transfers,
+    DROP COLUMN IF EXISTS net_profit,
+    DROP COLUMN IF EXISTS base_fee,
+    DROP COLUMN IF EXISTS max_fee,
+    DROP COLUMN IF EXISTS max_priority_fee,
+    DROP COLUMN IF EXISTS burned_fee,
+    DROP COLUMN IF EXISTS miner_tip,
+    DROP COLUMN IF EXISTS backrun_of,
+    DROP COLUMN IF EXISTS bundle_index,
+    DROP COLUMN IF EXISTS bundle_type,
+    DROP COLUMN IF EXISTS warnings,
+    DROP COLUMN IF EXISTS chain_id;
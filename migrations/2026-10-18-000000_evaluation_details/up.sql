@@ -0,0 +1,114 @@
+ALTER TABLE mev_inspections
+    -- gas_used * gas_price
+    ADD COLUMN IF NOT EXISTS gas_cost           NUMERIC,
+    -- ETH sent directly to the block's miner
+    ADD COLUMN IF NOT EXISTS coinbase_transfers NUMERIC,
+    -- revenue - gas_cost - coinbase_transfers, negative for a loss
+    ADD COLUMN IF NOT EXISTS net_profit         NUMERIC,
+    -- EIP-1559 fields, NULL before London or for legacy transactions
+    ADD COLUMN IF NOT EXISTS base_fee           NUMERIC,
+    ADD COLUMN IF NOT EXISTS max_fee            NUMERIC,
+    ADD COLUMN IF NOT EXISTS max_priority_fee   NUMERIC,
+    -- gas_used * base_fee
+    ADD COLUMN IF NOT EXISTS burned_fee         NUMERIC,
+    -- gas_used * (gas_price - base_fee)
+    ADD COLUMN IF NOT EXISTS miner_tip          NUMERIC,
+    -- the transaction of the same block this one back-ran
+    ADD COLUMN IF NOT EXISTS backrun_of         TEXT,
+    -- the transaction's bundle within the block, if it was part of one
+    ADD COLUMN IF NOT EXISTS bundle_index       NUMERIC,
+    -- flashbots, rogue, miner_payout or inferred
+    ADD COLUMN IF NOT EXISTS bundle_type        TEXT,
+    -- what was left out of the revenue, e.g. amounts that could not be priced
+    ADD COLUMN IF NOT EXISTS warnings           TEXT[],
+    -- the chain the transaction was included in
+    ADD COLUMN IF NOT EXISTS chain_id           NUMERIC NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS mev_inspections_chain_block_idx ON mev_inspections (chain_id, block_number);
+
+ALTER TYPE call_classification ADD VALUE IF NOT EXISTS 'flashloan';
+
+-- the hops of the arbitrages within a transaction
+CREATE TABLE IF NOT EXISTS arbitrage_legs
+(
+    -- hash of the transaction of the arbitrage
+    transaction_hash TEXT    NOT NULL REFERENCES mev_inspections (hash) ON UPDATE CASCADE ON DELETE CASCADE,
+    -- the position of the arbitrage within the transaction
+    arbitrage_index  NUMERIC NOT NULL,
+    -- the position of the leg within the arbitrage
+    leg_index        NUMERIC NOT NULL,
+    -- the pool that was traded against
+    pool             TEXT    NOT NULL,
+    -- the protocol of the pool, if known
+    protocol         TEXT,
+    token_in         TEXT    NOT NULL,
+    amount_in        NUMERIC NOT NULL,
+    token_out        TEXT    NOT NULL,
+    amount_out       NUMERIC NOT NULL,
+
+    PRIMARY KEY (transaction_hash, arbitrage_index, leg_index)
+);
+
+CREATE INDEX IF NOT EXISTS arbitrage_legs_pool_idx ON arbitrage_legs (pool);
+
+-- the liquidations within a transaction
+CREATE TABLE IF NOT EXISTS liquidations
+(
+    -- hash of the transaction of the liquidation
+    transaction_hash  TEXT    NOT NULL REFERENCES mev_inspections (hash) ON UPDATE CASCADE ON DELETE CASCADE,
+    -- the position of the liquidation within the transaction
+    liquidation_index NUMERIC NOT NULL,
+    -- the lending protocol, if known
+    protocol          TEXT,
+    -- the repaid debt
+    debt_token        TEXT    NOT NULL,
+    debt_amount       NUMERIC NOT NULL,
+    -- the seized collateral
+    collateral_token  TEXT    NOT NULL,
+    collateral_amount NUMERIC NOT NULL,
+    liquidated_user   TEXT    NOT NULL,
+    -- the values in ETH, NULL if they could not be priced
+    debt_value        NUMERIC,
+    collateral_value  NUMERIC,
+    -- collateral_value - debt_value
+    bonus             NUMERIC,
+    -- uniswap or oracle
+    price_source      TEXT    NOT NULL,
+
+    PRIMARY KEY (transaction_hash, liquidation_index)
+);
+
+CREATE INDEX IF NOT EXISTS liquidations_pair_idx ON liquidations (collateral_token, debt_token);
+
+-- AMM pairs created by the known factories
+CREATE TABLE IF NOT EXISTS pairs
+(
+    -- the chain the factory is deployed on
+    chain_id     NUMERIC NOT NULL,
+    -- the pair's (or pool's) address
+    address      TEXT    NOT NULL,
+    -- the protocol of the factory
+    protocol     TEXT    NOT NULL,
+    -- the factory which created the pair
+    factory      TEXT    NOT NULL,
+    token0       TEXT    NOT NULL,
+    token1       TEXT    NOT NULL,
+    -- the swap fee in hundredths of a bip
+    fee          NUMERIC NOT NULL,
+    -- the block the pair was created at
+    block_number NUMERIC NOT NULL,
+
+    PRIMARY KEY (chain_id, address)
+);
+
+CREATE INDEX IF NOT EXISTS pairs_factory_block_idx ON pairs (chain_id, factory, block_number);
+
+-- The last block whose logs were scanned for new pairs of a factory
+CREATE TABLE IF NOT EXISTS pair_cursors
+(
+    chain_id     NUMERIC NOT NULL,
+    factory      TEXT    NOT NULL,
+    block_number NUMERIC NOT NULL,
+
+    PRIMARY KEY (chain_id, factory)
+);
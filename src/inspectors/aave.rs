@@ -7,7 +7,7 @@ use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::actions::{SpecificAction, TokenDeposit};
 use crate::types::{Action, TransactionData};
 use crate::{
-    addresses::AAVE_LENDING_POOL,
+    network::network,
     types::{actions::Liquidation, Classification, Inspection, Protocol},
     DefiProtocol, Inspector, ProtocolContracts,
 };
@@ -47,7 +47,7 @@ impl DefiProtocol for Aave {
     }
 
     fn is_protocol(&self, call: &InternalCall) -> Option<Option<Protocol>> {
-        if call.to == *AAVE_LENDING_POOL {
+        if Some(call.to) == network().aave_lending_pool {
             Some(Some(self.protocol()))
         } else {
             None
@@ -74,6 +74,7 @@ impl DefiProtocol for Aave {
                         received_amount: liquidation.liquidated_collateral_amount,
                         from: call.from,
                         liquidated_user: liquidation.user,
+                        collateral_type: None,
                     };
                     return Some(Action::with_logs(
                         action.into(),
@@ -148,7 +149,7 @@ impl Inspector for Aave {
             match action {
                 Classification::Unknown(ref mut calltrace) => {
                     let call = calltrace.as_ref();
-                    if call.to == *AAVE_LENDING_POOL {
+                    if Some(call.to) == network().aave_lending_pool {
                         inspection.protocols.insert(Protocol::Aave);
 
                         // https://github.com/aave/aave-protocol/blob/master/contracts/lendingpool/LendingPool.sol#L805
@@ -166,6 +167,7 @@ impl Inspector for Aave {
                                     received_amount: U256::zero(),
                                     from: call.from,
                                     liquidated_user: user,
+                                    collateral_type: None,
                                 },
                                 calltrace.trace_address.clone(),
                             );
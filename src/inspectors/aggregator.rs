@@ -0,0 +1,244 @@
+//! Inspector for DEX aggregators like 1inch and Paraswap
+//!
+//! The routers of all versions split a swap over many AMMs whose trades are
+//! decoded by their own inspectors. The aggregator's action is the swap of
+//! the user, i.e. the tokens the router's caller paid and received, which is
+//! taken from the token transfers instead of the router specific calldata.
+use crate::{
+    addresses::{registry, ETH},
+    inspectors::erc20::TransferFilter,
+    model::{CallClassification, InternalCall},
+    types::{
+        actions::{SpecificAction, Trade, Transfer},
+        Action, Protocol, TransactionData,
+    },
+    DefiProtocol, ProtocolContracts,
+};
+use ethers::types::CallType;
+
+#[derive(Debug, Clone)]
+/// An inspector for the routers of a DEX aggregator
+pub struct Aggregator {
+    /// The protocol of the routers in the address registry
+    protocol: Protocol,
+}
+
+impl Aggregator {
+    /// The 1inch routers (v1 - v3)
+    pub fn one_inch() -> Self {
+        Self::new(Protocol::OneInch)
+    }
+
+    /// The Paraswap routers (Augustus v1 - v3)
+    pub fn paraswap() -> Self {
+        Self::new(Protocol::Paraswap)
+    }
+
+    /// Inspects the calls to all routers registered for the protocol
+    pub fn new(protocol: Protocol) -> Self {
+        Self { protocol }
+    }
+
+    fn is_router(&self, address: &ethers::types::Address) -> bool {
+        registry().protocol(address) == Some(self.protocol)
+    }
+}
+
+impl DefiProtocol for Aggregator {
+    fn base_contracts(&self) -> ProtocolContracts {
+        ProtocolContracts::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    fn is_protocol(&self, call: &InternalCall) -> Option<Option<Protocol>> {
+        if self.is_router(&call.to) {
+            Some(None)
+        } else {
+            None
+        }
+    }
+
+    fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        if call.classification != CallClassification::Swap {
+            return None;
+        }
+        let user = call.from;
+        let transfers = tx
+            .call_logs_decoded::<TransferFilter>(&call.trace_address)
+            .map(|(_, log, transfer)| Transfer {
+                from: transfer.from,
+                to: transfer.to,
+                amount: transfer.value,
+                token: log.address,
+            })
+            .collect::<Vec<_>>();
+
+        // the user pays either with ETH or with the first token it sends
+        let paid = if call.value.is_zero() {
+            transfers.iter().find(|t| t.from == user)?.clone()
+        } else {
+            Transfer {
+                from: user,
+                to: call.to,
+                amount: call.value,
+                token: *ETH,
+            }
+        };
+        // and receives the last token or ETH which is sent back to it
+        let received = transfers
+            .iter()
+            .rev()
+            .find(|t| t.to == user && t.token != paid.token)
+            .cloned()
+            .or_else(|| {
+                tx.subcalls(&call.trace_address)
+                    .filter(|c| c.to == user && !c.value.is_zero())
+                    .last()
+                    .map(|c| Transfer {
+                        from: c.from,
+                        to: user,
+                        amount: c.value,
+                        token: *ETH,
+                    })
+            })?;
+
+        // the transfers are left to the AMM inspectors
+        Some(Action::with_logs_and_protocols(
+            Trade {
+                t1: paid,
+                t2: received,
+            }
+            .into(),
+            call.trace_address.clone(),
+            Vec::new(),
+            vec![self.protocol],
+        ))
+    }
+
+    fn classify(
+        &self,
+        call: &InternalCall,
+    ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        // only the user's call into the router, not the router's calls into
+        // its own proxies or views
+        if matches!(
+            call.call_type,
+            CallType::DelegateCall | CallType::StaticCall
+        ) || call.input.is_empty()
+            || self.is_router(&call.from)
+        {
+            return None;
+        }
+        Some((CallClassification::Swap, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::{parse_address, ETH},
+        inspectors::ERC20,
+        test_helpers::*,
+    };
+    use ethers::abi::Token;
+
+    #[test]
+    fn one_inch_swap() {
+        let addrs = addrs();
+        let [user, executor, dai, usdc] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        // 1inch v3
+        let router = parse_address("0x11111112542d85b3ef69ae05771c2dccff4faa26");
+        let traces = vec![
+            mk_trace(user, router, vec![0x7c, 0x02, 0x52, 0x00], vec![], 3),
+            mk_trace(
+                router,
+                dai,
+                calldata(
+                    "transferFrom(address,address,uint256)",
+                    &[
+                        Token::Address(user),
+                        Token::Address(executor),
+                        Token::Uint(100.into()),
+                    ],
+                ),
+                vec![0],
+                0,
+            ),
+            mk_trace(router, executor, vec![0xde, 0xad, 0xbe, 0xef], vec![1], 0),
+            mk_trace(
+                router,
+                usdc,
+                calldata(
+                    "transfer(address,uint256)",
+                    &[Token::Address(user), Token::Uint(99.into())],
+                ),
+                vec![2],
+                0,
+            ),
+        ];
+        let logs = vec![
+            transfer_log(dai, user, executor, 100, 0),
+            transfer_log(usdc, router, user, 99, 1),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Aggregator::one_inch().inspect_tx(&mut tx);
+        ERC20::new().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(
+            (trade.t1.from, trade.t1.token, trade.t1.amount),
+            (user, dai, 100.into())
+        );
+        assert_eq!(
+            (trade.t2.to, trade.t2.token, trade.t2.amount),
+            (user, usdc, 99.into())
+        );
+        assert!(tx.protocols().contains(&Protocol::OneInch));
+        // the user's transfers are part of the swap
+        assert_eq!(tx.actions().transfers().count(), 0);
+    }
+
+    #[test]
+    fn paraswap_eth_out() {
+        let addrs = addrs();
+        let [user, dai] = [addrs[0], addrs[1]];
+        // Paraswap v3
+        let router = parse_address("0xf90e98f3d8dce44632e5020abf2e122e0f99dfab");
+        let traces = vec![
+            mk_trace(user, router, vec![0xde, 0xad, 0xbe, 0xef], vec![], 2),
+            mk_trace(
+                router,
+                dai,
+                calldata(
+                    "transferFrom(address,address,uint256)",
+                    &[
+                        Token::Address(user),
+                        Token::Address(router),
+                        Token::Uint(100.into()),
+                    ],
+                ),
+                vec![0],
+                0,
+            ),
+            {
+                let mut trace = mk_trace(router, user, vec![], vec![1], 0);
+                if let ethers::types::Action::Call(ref mut call) = trace.action {
+                    call.value = 5.into();
+                }
+                trace
+            },
+        ];
+        let logs = vec![transfer_log(dai, user, router, 100, 0)];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Aggregator::paraswap().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (dai, 100.into()));
+        assert_eq!((trade.t2.token, trade.t2.amount), (*ETH, 5.into()));
+        assert!(tx.protocols().contains(&Protocol::Paraswap));
+    }
+}
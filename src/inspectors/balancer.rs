@@ -1,5 +1,6 @@
 use crate::{
-    addresses::BALANCER_PROXY,
+    addresses::{BALANCER_PROXY, BALANCER_VAULT},
+    inspect_tx,
     inspectors::find_matching,
     traits::Inspector,
     types::{actions::Trade, Classification, Inspection, Protocol},
@@ -7,16 +8,33 @@ use crate::{
 };
 
 use crate::model::{CallClassification, EventLog, InternalCall};
-use crate::types::actions::{SpecificAction, Transfer};
+use crate::types::actions::{AddLiquidity, FlashLoan, RemoveLiquidity, SpecificAction, Transfer};
 use crate::types::{Action, TransactionData};
 use ethers::{
     contract::{abigen, BaseContract, EthEvent, EthLogDecode},
     types::{Address, U256},
+    utils::id,
 };
 
 abigen!(BalancerPool, "abi/bpool.json");
 abigen!(BalancerProxy, "abi/bproxy.json");
 
+// The V2 Vault holds the tokens of all pools and emits their events
+abigen!(
+    BalancerVault,
+    r#"[
+        event Swap(bytes32 indexed poolId, address indexed tokenIn, address indexed tokenOut, uint256 amountIn, uint256 amountOut)
+        event FlashLoan(address indexed recipient, address indexed token, uint256 amount, uint256 feeAmount)
+    ]"#
+);
+
+const VAULT_SWAP: &str =
+    "swap((bytes32,uint8,address,address,uint256,bytes),(address,bool,address,bool),uint256,uint256)";
+const VAULT_BATCH_SWAP: &str = "batchSwap(uint8,(bytes32,uint256,uint256,uint256,bytes)[],address[],(address,bool,address,bool),int256[],uint256)";
+const VAULT_JOIN_POOL: &str = "joinPool(bytes32,address,address,(address[],uint256[],bytes,bool))";
+const VAULT_EXIT_POOL: &str = "exitPool(bytes32,address,address,(address[],uint256[],bytes,bool))";
+const VAULT_FLASH_LOAN: &str = "flashLoan(address,address[],uint256[],bytes)";
+
 #[derive(Debug, Clone)]
 /// An inspector for Balancer
 pub struct Balancer {
@@ -48,6 +66,223 @@ impl Balancer {
             .decode::<Swap, _>("swapExactAmountIn", &call.input)
             .is_ok()
     }
+
+    /// Whether this is one of the `BPool`'s `join*` calls
+    pub fn is_join(&self, call: &InternalCall) -> bool {
+        [
+            "joinPool",
+            "joinswapExternAmountIn",
+            "joinswapPoolAmountOut",
+        ]
+        .iter()
+        .any(|name| self.is_pool_call(call, name))
+    }
+
+    /// Whether this is one of the `BPool`'s `exit*` calls
+    pub fn is_exit(&self, call: &InternalCall) -> bool {
+        [
+            "exitPool",
+            "exitswapExternAmountOut",
+            "exitswapPoolAmountIn",
+        ]
+        .iter()
+        .any(|name| self.is_pool_call(call, name))
+    }
+
+    /// Whether the call's selector is the `BPool` function's.
+    ///
+    /// The single asset joins and exits share their argument types with
+    /// other calls, so they can only be told apart by the selector.
+    fn is_pool_call(&self, call: &InternalCall, name: &str) -> bool {
+        self.bpool
+            .as_ref()
+            .function(name)
+            .map(|function| call.input.starts_with(&function.short_signature()))
+            .unwrap_or_default()
+    }
+
+    /// The `(token, amount)` of the `LOG_JOIN`/`LOG_EXIT` events the pool
+    /// emitted during the call.
+    ///
+    /// `joinPool` and `exitPool` emit one event per token of the pool, all
+    /// other joins and exits a single one.
+    fn decode_pool_liquidity(
+        &self,
+        call: &InternalCall,
+        tx: &TransactionData,
+    ) -> Vec<(U256, Address, U256)> {
+        let is_join = call.classification == CallClassification::AddLiquidity;
+        let max_events =
+            if self.is_pool_call(call, "joinPool") || self.is_pool_call(call, "exitPool") {
+                usize::MAX
+            } else {
+                1
+            };
+        tx.call_logs(&call.trace_address)
+            .filter(|(c, _)| c.to == call.to)
+            .filter_map(|(_, log)| {
+                if is_join {
+                    LogJoinFilter::decode_log(&log.raw_log)
+                        .map(OldLogJoinFilter::from)
+                        .or_else(|_| OldLogJoinFilter::decode_log(&log.raw_log))
+                        .map(|join| (log.log_index, join.token_in, join.token_amount_in))
+                        .ok()
+                } else {
+                    LogExitFilter::decode_log(&log.raw_log)
+                        .map(OldLogExitFilter::from)
+                        .or_else(|_| OldLogExitFilter::decode_log(&log.raw_log))
+                        .map(|exit| (log.log_index, exit.token_out, exit.token_amount_out))
+                        .ok()
+                }
+            })
+            .take(max_events)
+            .collect()
+    }
+
+    /// Classifies the calls to the V2 Vault.
+    ///
+    /// A single Vault call may emit events of several pools (`batchSwap`), so
+    /// this can not go through `decode_call_action`.
+    fn inspect_vault(&self, tx: &mut TransactionData) {
+        let mut vault_calls = Vec::new();
+        for call in tx.calls_mut() {
+            if call.to != *BALANCER_VAULT {
+                continue;
+            }
+            let classification = if call.input.starts_with(&id(VAULT_SWAP))
+                || call.input.starts_with(&id(VAULT_BATCH_SWAP))
+            {
+                CallClassification::Swap
+            } else if call.input.starts_with(&id(VAULT_JOIN_POOL)) {
+                CallClassification::AddLiquidity
+            } else if call.input.starts_with(&id(VAULT_EXIT_POOL)) {
+                CallClassification::RemoveLiquidity
+            } else if call.input.starts_with(&id(VAULT_FLASH_LOAN)) {
+                CallClassification::FlashLoan
+            } else {
+                continue;
+            };
+            call.protocol = Some(Protocol::BalancerV2);
+            call.classification = classification;
+            vault_calls.push(call.trace_address.clone());
+        }
+
+        for trace_address in vault_calls {
+            let actions = match tx.get_call(&trace_address) {
+                Some(call) => self.decode_vault_actions(call, tx),
+                None => continue,
+            };
+            tx.extend_actions(actions.into_iter());
+        }
+    }
+
+    /// Decodes the actions of the Vault call from the events the Vault emitted
+    /// during it, attributed to the pool of their pool id
+    fn decode_vault_actions(&self, call: &InternalCall, tx: &TransactionData) -> Vec<Action> {
+        let vault_logs = tx
+            .call_logs(&call.trace_address)
+            .filter(|(c, _)| c.to == *BALANCER_VAULT)
+            .map(|(_, log)| log);
+        let mk_action = |action: SpecificAction, log: &EventLog| {
+            Action::with_logs(action, call.trace_address.clone(), vec![log.log_index])
+        };
+
+        match call.classification {
+            CallClassification::Swap => {
+                // `FundManagement` follows the offset of the `SingleSwap` in
+                // `swap` and `kind`, `swaps` and `assets` in `batchSwap`
+                let funds = if call.input.starts_with(&id(VAULT_SWAP)) {
+                    1
+                } else {
+                    3
+                };
+                let sender = address_arg(call, funds).unwrap_or(call.from);
+                let recipient = address_arg(call, funds + 2).unwrap_or(call.from);
+                vault_logs
+                    .filter_map(|log| {
+                        let swap = SwapFilter::decode_log(&log.raw_log).ok()?;
+                        let pool = pool_address(&swap.pool_id);
+                        let trade = Trade {
+                            t1: Transfer {
+                                from: sender,
+                                to: pool,
+                                amount: swap.amount_in,
+                                token: swap.token_in,
+                            },
+                            t2: Transfer {
+                                from: pool,
+                                to: recipient,
+                                amount: swap.amount_out,
+                                token: swap.token_out,
+                            },
+                        };
+                        Some(mk_action(trade.into(), log))
+                    })
+                    .collect()
+            }
+            CallClassification::AddLiquidity | CallClassification::RemoveLiquidity => vault_logs
+                .filter_map(|log| {
+                    let change = PoolBalanceChangedFilter::decode_log(&log.raw_log).ok()?;
+                    let pool = pool_address(&change.pool_id);
+                    let tokens = change.tokens;
+                    // joins have positive deltas, exits negative ones
+                    let amounts = change.deltas.into_iter().map(abs_delta).collect();
+                    let liquidity = if call.classification == CallClassification::AddLiquidity {
+                        AddLiquidity {
+                            pool,
+                            tokens,
+                            amounts,
+                        }
+                        .into()
+                    } else {
+                        RemoveLiquidity {
+                            pool,
+                            tokens,
+                            amounts,
+                        }
+                        .into()
+                    };
+                    Some(mk_action(liquidity, log))
+                })
+                .collect(),
+            CallClassification::FlashLoan => vault_logs
+                .filter_map(|log| {
+                    let loan = FlashLoanFilter::decode_log(&log.raw_log).ok()?;
+                    let loan = FlashLoan {
+                        lender: call.to,
+                        recipient: loan.recipient,
+                        token: loan.token,
+                        amount: loan.amount,
+                        fee: loan.fee_amount,
+                    };
+                    Some(mk_action(loan.into(), log))
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The address of a V2 pool is the first 20 bytes of its id
+fn pool_address(pool_id: &[u8; 32]) -> Address {
+    Address::from_slice(&pool_id[..20])
+}
+
+/// The absolute value of a two's complement `int256`
+fn abs_delta(delta: U256) -> U256 {
+    if delta.bit(255) {
+        !delta + U256::one()
+    } else {
+        delta
+    }
+}
+
+/// The address in the `idx`th static argument word of the call's input
+fn address_arg(call: &InternalCall, idx: usize) -> Option<Address> {
+    let start = 4 + idx * 32;
+    call.input
+        .get(start + 12..start + 32)
+        .map(Address::from_slice)
 }
 
 // this is a hack to overcome the `ethers` generated paths
@@ -70,6 +305,64 @@ pub struct OldLogSwapFilter {
     pub token_amount_out: U256,
 }
 
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(
+    name = "PoolBalanceChanged",
+    abi = "PoolBalanceChanged(bytes32,address,address[],int256[],uint256[])"
+)]
+/// Emitted by the V2 Vault on joins and exits, `deltas` are `int256`
+pub struct PoolBalanceChangedFilter {
+    #[ethevent(indexed)]
+    pub pool_id: [u8; 32],
+    #[ethevent(indexed)]
+    pub liquidity_provider: Address,
+    pub tokens: Vec<Address>,
+    pub deltas: Vec<U256>,
+    pub protocol_fee_amounts: Vec<U256>,
+}
+
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(name = "LOG_JOIN", abi = "LOG_JOIN(address,address,uint256)")]
+/// The deployed `LOG_JOIN` event, without the `reservesAmount` field
+pub struct OldLogJoinFilter {
+    #[ethevent(indexed)]
+    pub caller: Address,
+    #[ethevent(indexed)]
+    pub token_in: Address,
+    pub token_amount_in: U256,
+}
+
+impl From<LogJoinFilter> for OldLogJoinFilter {
+    fn from(filter: LogJoinFilter) -> Self {
+        OldLogJoinFilter {
+            caller: filter.caller,
+            token_in: filter.token_in,
+            token_amount_in: filter.token_amount_in,
+        }
+    }
+}
+
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(name = "LOG_EXIT", abi = "LOG_EXIT(address,address,uint256)")]
+/// The deployed `LOG_EXIT` event, without the `reservesAmount` field
+pub struct OldLogExitFilter {
+    #[ethevent(indexed)]
+    pub caller: Address,
+    #[ethevent(indexed)]
+    pub token_out: Address,
+    pub token_amount_out: U256,
+}
+
+impl From<LogExitFilter> for OldLogExitFilter {
+    fn from(filter: LogExitFilter) -> Self {
+        OldLogExitFilter {
+            caller: filter.caller,
+            token_out: filter.token_out,
+            token_amount_out: filter.token_amount_out,
+        }
+    }
+}
+
 impl From<LogSwapFilter> for OldLogSwapFilter {
     fn from(filter: LogSwapFilter) -> Self {
         let LogSwapFilter {
@@ -110,6 +403,9 @@ impl DefiProtocol for Balancer {
 
     fn is_protocol_event(&self, log: &EventLog) -> bool {
         BalancerPoolEvents::decode_log(&log.raw_log).is_ok()
+            || (log.address == *BALANCER_VAULT
+                && (BalancerVaultEvents::decode_log(&log.raw_log).is_ok()
+                    || PoolBalanceChangedFilter::decode_log(&log.raw_log).is_ok()))
     }
 
     fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
@@ -153,6 +449,38 @@ impl DefiProtocol for Balancer {
                     println!("failed to decode");
                 }
             }
+            CallClassification::AddLiquidity | CallClassification::RemoveLiquidity => {
+                // `LOG_JOIN` and `LOG_EXIT` are emitted by the pool for every
+                // token that is pulled or pushed
+                let events = self.decode_pool_liquidity(call, tx);
+                if events.is_empty() {
+                    return None;
+                }
+                let mut logs = Vec::with_capacity(events.len());
+                let mut tokens = Vec::with_capacity(events.len());
+                let mut amounts = Vec::with_capacity(events.len());
+                for (log_index, token, amount) in events {
+                    logs.push(log_index);
+                    tokens.push(token);
+                    amounts.push(amount);
+                }
+                let action = if call.classification == CallClassification::AddLiquidity {
+                    AddLiquidity {
+                        pool: call.to,
+                        tokens,
+                        amounts,
+                    }
+                    .into()
+                } else {
+                    RemoveLiquidity {
+                        pool: call.to,
+                        tokens,
+                        amounts,
+                    }
+                    .into()
+                };
+                return Some(Action::with_logs(action, call.trace_address.clone(), logs));
+            }
             _ => {}
         }
         None
@@ -165,10 +493,19 @@ impl DefiProtocol for Balancer {
         // https://github.com/balancer-labs/balancer-core/blob/master/contracts/BPool.sol#L28
         if self.is_swap_in(call) || self.is_swap_out(&call) {
             Some((CallClassification::Swap, None))
+        } else if self.is_join(call) {
+            Some((CallClassification::AddLiquidity, None))
+        } else if self.is_exit(call) {
+            Some((CallClassification::RemoveLiquidity, None))
         } else {
             None
         }
     }
+
+    fn inspect_tx(&self, tx: &mut TransactionData) {
+        inspect_tx(self, tx);
+        self.inspect_vault(tx);
+    }
 }
 
 impl Inspector for Balancer {
@@ -240,12 +577,13 @@ mod tests {
     use super::*;
     use crate::test_helpers::*;
     use crate::{
-        addresses::ADDRESSBOOK,
+        addresses::lookup,
         inspectors::ERC20,
         reducers::{ArbitrageReducer, TradeReducer},
         types::Inspection,
         Inspector, Reducer, TxReducer,
     };
+    use ethers::{abi::Token, types::H256};
 
     struct MyInspector {
         erc20: ERC20,
@@ -280,6 +618,231 @@ mod tests {
         }
     }
 
+    /// The id of the pool, which starts with the pool's address
+    fn pool_id(pool: Address) -> H256 {
+        let mut id = H256::from(pool).to_fixed_bytes();
+        id.rotate_left(12);
+        H256::from(id)
+    }
+
+    #[test]
+    fn vault_swap() {
+        let addrs = addrs();
+        let [trader, pool, token_in, token_out] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let vault = *BALANCER_VAULT;
+        let amount_in = U256::from(100);
+        let amount_out = U256::from(90);
+
+        // the trader is both the sender and recipient of the swap's funds
+        let swap = calldata(
+            VAULT_SWAP,
+            &[
+                Token::Uint(0xe0.into()),
+                Token::Address(trader),
+                Token::Bool(false),
+                Token::Address(trader),
+                Token::Bool(false),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ],
+        );
+        let traces = vec![
+            mk_trace(trader, vault, swap, vec![], 2),
+            mk_trace(
+                vault,
+                token_in,
+                calldata(
+                    "transferFrom(address,address,uint256)",
+                    &[
+                        Token::Address(trader),
+                        Token::Address(vault),
+                        Token::Uint(amount_in),
+                    ],
+                ),
+                vec![0],
+                0,
+            ),
+            mk_trace(
+                vault,
+                token_out,
+                calldata(
+                    "transfer(address,uint256)",
+                    &[Token::Address(trader), Token::Uint(amount_out)],
+                ),
+                vec![1],
+                0,
+            ),
+        ];
+        let logs = vec![
+            mk_log(
+                vault,
+                vec![
+                    SwapFilter::signature(),
+                    pool_id(pool),
+                    token_in.into(),
+                    token_out.into(),
+                ],
+                vec![Token::Uint(amount_in), Token::Uint(amount_out)],
+                0,
+            ),
+            transfer_log(token_in, trader, vault, amount_in, 1),
+            transfer_log(token_out, vault, trader, amount_out, 2),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        MyInspector::new().inspect_tx(&mut tx);
+
+        // the vault's transfers are part of the trade
+        assert_eq!(tx.actions().count(), 1);
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(trade.t1.from, trader);
+        assert_eq!(trade.t1.to, pool);
+        assert_eq!(trade.t1.token, token_in);
+        assert_eq!(trade.t1.amount, amount_in);
+        assert_eq!(trade.t2.from, pool);
+        assert_eq!(trade.t2.to, trader);
+        assert_eq!(trade.t2.token, token_out);
+        assert_eq!(trade.t2.amount, amount_out);
+
+        let call = tx.get_call(&[]).unwrap();
+        assert_eq!(call.protocol, Some(Protocol::BalancerV2));
+        assert_eq!(call.classification, CallClassification::Swap);
+    }
+
+    #[test]
+    fn vault_flash_loan_and_exit() {
+        let addrs = addrs();
+        let [bot, pool, token0, token1] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let vault = *BALANCER_VAULT;
+        let loan = calldata(
+            VAULT_FLASH_LOAN,
+            &[
+                Token::Address(bot),
+                Token::Array(vec![Token::Address(token0)]),
+                Token::Array(vec![Token::Uint(1000.into())]),
+                Token::Bytes(vec![]),
+            ],
+        );
+        let exit = calldata(
+            VAULT_EXIT_POOL,
+            &[
+                Token::FixedBytes(pool_id(pool).as_bytes().to_vec()),
+                Token::Address(bot),
+                Token::Address(bot),
+            ],
+        );
+        let traces = vec![
+            mk_trace(bot, vault, loan, vec![], 1),
+            // `receiveFlashLoan` callback
+            mk_trace(vault, bot, vec![0xf0, 0x4f, 0x27, 0x07], vec![0], 1),
+            mk_trace(bot, vault, exit, vec![0, 0], 0),
+        ];
+        // exits are negative deltas
+        let negative = |amount: u64| Token::Int(!U256::from(amount) + U256::one());
+        let logs = vec![
+            mk_log(
+                vault,
+                vec![
+                    PoolBalanceChangedFilter::signature(),
+                    pool_id(pool),
+                    bot.into(),
+                ],
+                vec![
+                    Token::Array(vec![Token::Address(token0), Token::Address(token1)]),
+                    Token::Array(vec![negative(10), negative(20)]),
+                    Token::Array(vec![Token::Uint(0.into()), Token::Uint(0.into())]),
+                ],
+                0,
+            ),
+            mk_log(
+                vault,
+                vec![FlashLoanFilter::signature(), bot.into(), token0.into()],
+                vec![Token::Uint(1000.into()), Token::Uint(0.into())],
+                1,
+            ),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        MyInspector::new().inspect_tx(&mut tx);
+
+        let loan = tx.actions().flash_loans().next().unwrap();
+        assert_eq!(loan.lender, vault);
+        assert_eq!(loan.recipient, bot);
+        assert_eq!(loan.token, token0);
+        assert_eq!(loan.amount, 1000.into());
+        assert_eq!(loan.fee, U256::zero());
+
+        let exit = tx.actions().remove_liquidity().next().unwrap();
+        assert_eq!(exit.pool, pool);
+        assert_eq!(exit.tokens, vec![token0, token1]);
+        assert_eq!(exit.amounts, vec![10.into(), 20.into()]);
+        assert_eq!(tx.actions().add_liquidity().count(), 0);
+        assert_eq!(
+            tx.get_call(&[0, 0]).unwrap().classification,
+            CallClassification::RemoveLiquidity
+        );
+    }
+
+    #[test]
+    fn pool_join_and_exit() {
+        let addrs = addrs();
+        let [user, router, pool, token0, token1] =
+            [addrs[0], addrs[1], addrs[2], addrs[3], addrs[4]];
+        let max = Token::Uint(U256::max_value());
+        let join = calldata(
+            "joinPool(uint256,uint256[])",
+            &[Token::Uint(1.into()), Token::Array(vec![max.clone(), max])],
+        );
+        let exit = calldata(
+            "exitswapPoolAmountIn(address,uint256,uint256)",
+            &[
+                Token::Address(token1),
+                Token::Uint(1.into()),
+                Token::Uint(0.into()),
+            ],
+        );
+        let traces = vec![
+            mk_trace(user, router, vec![0xde, 0xad, 0xbe, 0xef], vec![], 2),
+            mk_trace(router, pool, join, vec![0], 0),
+            mk_trace(router, pool, exit, vec![1], 0),
+        ];
+        let logs = vec![
+            // the deployed events lack the `reservesAmount`
+            mk_log(
+                pool,
+                vec![OldLogJoinFilter::signature(), router.into(), token0.into()],
+                vec![Token::Uint(10.into())],
+                0,
+            ),
+            mk_log(
+                pool,
+                vec![OldLogJoinFilter::signature(), router.into(), token1.into()],
+                vec![Token::Uint(20.into())],
+                1,
+            ),
+            mk_log(
+                pool,
+                vec![LogExitFilter::signature(), router.into(), token1.into()],
+                vec![Token::Uint(5.into()), Token::Uint(15.into())],
+                2,
+            ),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        MyInspector::new().inspect_tx(&mut tx);
+
+        let join = tx.actions().add_liquidity().next().unwrap();
+        assert_eq!(join.pool, pool);
+        assert_eq!(join.tokens, vec![token0, token1]);
+        assert_eq!(join.amounts, vec![10.into(), 20.into()]);
+
+        let exit = tx.actions().remove_liquidity().next().unwrap();
+        assert_eq!(exit.pool, pool);
+        assert_eq!(exit.tokens, vec![token1]);
+        assert_eq!(exit.amounts, vec![5.into()]);
+        assert_eq!(
+            tx.get_call(&[1]).unwrap().protocol,
+            Some(Protocol::Balancer)
+        );
+    }
+
     #[test]
     fn bot_trade2() {
         let mut tx = read_tx("balancer_trade.data.json");
@@ -337,18 +900,18 @@ mod tests {
             trade.t1.amount,
             U256::from_dec_str("1882725882636").unwrap()
         );
-        assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "cDAI",);
+        assert_eq!(lookup(trade.t1.token), "cDAI",);
         assert_eq!(
             trade.t2.amount,
             U256::from_dec_str("2048034448010009909").unwrap()
         );
-        assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "COMP",);
+        assert_eq!(lookup(trade.t2.token), "COMP",);
 
         // 2 comp payouts
         let t1 = known[1].as_transfer().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&t1.token).unwrap(), "COMP",);
+        assert_eq!(lookup(t1.token), "COMP",);
         let t2 = known[2].as_transfer().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&t2.token).unwrap(), "COMP",);
+        assert_eq!(lookup(t2.token), "COMP",);
     }
 
     #[test]
@@ -365,17 +928,17 @@ mod tests {
             trade.t1.amount,
             U256::from_dec_str("1882725882636").unwrap()
         );
-        assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "cDAI",);
+        assert_eq!(lookup(trade.t1.token), "cDAI",);
         assert_eq!(
             trade.t2.amount,
             U256::from_dec_str("2048034448010009909").unwrap()
         );
-        assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "COMP",);
+        assert_eq!(lookup(trade.t2.token), "COMP",);
 
         // 2 comp payouts
         let t1 = known[1].as_ref().as_transfer().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&t1.token).unwrap(), "COMP",);
+        assert_eq!(lookup(t1.token), "COMP",);
         let t2 = known[2].as_ref().as_transfer().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&t2.token).unwrap(), "COMP",);
+        assert_eq!(lookup(t2.token), "COMP",);
     }
 }
@@ -0,0 +1,226 @@
+use crate::{
+    model::{CallClassification, EventLog, InternalCall},
+    types::{
+        actions::{SpecificAction, Trade, Transfer},
+        Action, Protocol, TransactionData,
+    },
+    DefiProtocol, ProtocolContracts,
+};
+use ethers::{
+    abi::{self, ParamType, Token},
+    contract::{EthEvent, EthLogDecode},
+    types::{Address, CallType, U256},
+    utils::id,
+};
+
+/// `convertByPath(path, amount, minReturn, beneficiary, affiliateAccount, affiliateFee)`
+const CONVERT_BY_PATH: &str = "convertByPath(address[],uint256,uint256,address,address,uint256)";
+
+#[derive(Debug, Clone)]
+/// An inspector for conversions via the Bancor Network
+pub struct Bancor {
+    convert_by_path: [u8; 4],
+}
+
+impl Default for Bancor {
+    fn default() -> Self {
+        Self {
+            convert_by_path: id(CONVERT_BY_PATH),
+        }
+    }
+}
+
+impl DefiProtocol for Bancor {
+    fn base_contracts(&self) -> ProtocolContracts {
+        ProtocolContracts::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Bancor
+    }
+
+    fn is_protocol_event(&self, log: &EventLog) -> bool {
+        ConversionFilter::decode_log(&log.raw_log).is_ok()
+    }
+
+    fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        if call.classification != CallClassification::Swap {
+            return None;
+        }
+        let mut params = abi::decode(
+            &[
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+            ],
+            call.input.get(4..)?,
+        )
+        .ok()?
+        .into_iter();
+        let path = params
+            .next()?
+            .into_array()?
+            .into_iter()
+            .filter_map(Token::into_address)
+            .collect::<Vec<_>>();
+        let beneficiary = params.nth(2)?.into_address()?;
+        let beneficiary = if beneficiary.is_zero() {
+            call.from
+        } else {
+            beneficiary
+        };
+
+        // every converter along the path emits a `Conversion`
+        let conversions = tx
+            .call_logs_decoded::<ConversionFilter>(&call.trace_address)
+            .map(|(_, log, conversion)| (log.log_index, conversion))
+            .collect::<Vec<_>>();
+        let (_, first) = conversions.first()?;
+        let (_, last) = conversions.last()?;
+
+        let action = Trade {
+            t1: Transfer {
+                from: call.from,
+                to: call.to,
+                amount: first.amount,
+                token: *path.first()?,
+            },
+            t2: Transfer {
+                from: call.to,
+                to: beneficiary,
+                amount: last.return_amount,
+                token: *path.last()?,
+            },
+        };
+        Some(Action::with_logs(
+            action.into(),
+            call.trace_address.clone(),
+            conversions.iter().map(|(idx, _)| *idx).collect(),
+        ))
+    }
+
+    fn classify(
+        &self,
+        call: &InternalCall,
+    ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        if call.call_type == CallType::DelegateCall {
+            return None;
+        }
+        if call.input.get(..4)? == self.convert_by_path {
+            Some((CallClassification::Swap, None))
+        } else {
+            None
+        }
+    }
+}
+
+// this is a hack to overcome the `ethers` generated paths
+use ethers::contract as ethers_contract;
+use ethers::core as ethers_core;
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(
+    name = "Conversion",
+    abi = "Conversion(address,address,address,uint256,uint256,int256)"
+)]
+/// Emitted by a converter for each hop of a conversion
+pub struct ConversionFilter {
+    #[ethevent(indexed)]
+    pub from_token: Address,
+    #[ethevent(indexed)]
+    pub to_token: Address,
+    #[ethevent(indexed)]
+    pub trader: Address,
+    pub amount: U256,
+    pub return_amount: U256,
+    // int256
+    pub conversion_fee: U256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::{parse_address, ETH},
+        test_helpers::*,
+    };
+    use ethers::types::H256;
+
+    fn conversion_log(
+        converter: Address,
+        from: Address,
+        to: Address,
+        trader: Address,
+        amount: u64,
+        ret: u64,
+        log_index: u64,
+    ) -> EventLog {
+        mk_log(
+            converter,
+            vec![
+                ConversionFilter::signature(),
+                H256::from(from),
+                H256::from(to),
+                H256::from(trader),
+            ],
+            vec![
+                Token::Uint(amount.into()),
+                Token::Uint(ret.into()),
+                Token::Int(0.into()),
+            ],
+            log_index,
+        )
+    }
+
+    #[test]
+    fn convert_by_path() {
+        let addrs = addrs();
+        let [user, bnt, dai, eth_anchor, dai_anchor, eth_converter, dai_converter] = [
+            addrs[0], addrs[1], addrs[2], addrs[3], addrs[4], addrs[5], addrs[6],
+        ];
+        let network = parse_address("0x2f9ec37d6ccfff1cab21733bdadede11c823ccb0");
+        // ETH -> BNT -> DAI
+        let input = calldata(
+            CONVERT_BY_PATH,
+            &[
+                Token::Array(vec![
+                    Token::Address(*ETH),
+                    Token::Address(eth_anchor),
+                    Token::Address(bnt),
+                    Token::Address(dai_anchor),
+                    Token::Address(dai),
+                ]),
+                Token::Uint(10.into()),
+                Token::Uint(1.into()),
+                Token::Address(Address::zero()),
+                Token::Address(Address::zero()),
+                Token::Uint(0.into()),
+            ],
+        );
+        let traces = vec![
+            mk_trace(user, network, input, vec![], 2),
+            mk_trace(network, eth_converter, vec![0xde, 0xad], vec![0], 0),
+            mk_trace(network, dai_converter, vec![0xbe, 0xef], vec![1], 0),
+        ];
+        let logs = vec![
+            conversion_log(eth_converter, *ETH, bnt, network, 10, 50, 0),
+            conversion_log(dai_converter, bnt, dai, network, 50, 3000, 1),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Bancor::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(
+            (trade.t1.from, trade.t1.token, trade.t1.amount),
+            (user, *ETH, 10.into())
+        );
+        // no beneficiary, so the caller receives the tokens
+        assert_eq!(
+            (trade.t2.to, trade.t2.token, trade.t2.amount),
+            (user, dai, 3000.into())
+        );
+        assert!(tx.protocols().contains(&Protocol::Bancor));
+    }
+}
@@ -4,9 +4,9 @@ use std::ops::Range;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use ethers::prelude::Middleware;
+use ethers::prelude::{Middleware, ProviderError};
 use ethers::types::{
-    Block, BlockNumber, Filter, Log, Trace, Transaction, TransactionReceipt, TxHash, U256,
+    Address, Block, BlockNumber, Filter, Log, Trace, Transaction, TransactionReceipt, TxHash, U256,
 };
 use futures::{
     stream::{self, FuturesUnordered},
@@ -17,15 +17,24 @@ use thiserror::Error;
 
 use crate::mevdb::BatchInserts;
 use crate::model::EventLog;
-use crate::types::{EvalError, Evaluation, TransactionData};
-use crate::{DefiProtocol, HistoricalPrice, MevDB, TxReducer};
+use crate::types::{fees::block_with_fees, EvalError, Evaluation, GasFees, TransactionData};
+use crate::{BlockReducer, DefiProtocol, HistoricalPrice, MevDB, TxReducer};
 use std::convert::TryFrom;
 use std::sync::Arc;
 
 /// Classifies traces according to the provided inspectors
+///
+/// The pipeline for every block is:
+/// 1. `DefiProtocol`s inspect each transaction
+/// 2. `TxReducer`s reduce each transaction
+/// 3. `BlockReducer`s reduce all the block's transactions
+/// 4. each transaction is evaluated
+/// 5. `BlockReducer`s reduce all the block's `Evaluation`s
+#[derive(Default)]
 pub struct BatchInspector {
     inspectors: Vec<Box<dyn DefiProtocol + Send + Sync>>,
     reducers: Vec<Box<dyn TxReducer + Send + Sync>>,
+    block_reducers: Vec<Box<dyn BlockReducer + Send + Sync>>,
 }
 
 impl BatchInspector {
@@ -37,9 +46,31 @@ impl BatchInspector {
         Self {
             inspectors,
             reducers,
+            block_reducers: Vec::new(),
         }
     }
 
+    /// Adds an inspector that runs after all the already registered ones
+    pub fn with_inspector(mut self, inspector: impl DefiProtocol + Send + Sync + 'static) -> Self {
+        self.inspectors.push(Box::new(inspector));
+        self
+    }
+
+    /// Adds a reducer that runs after all the already registered ones
+    pub fn with_reducer(mut self, reducer: impl TxReducer + Send + Sync + 'static) -> Self {
+        self.reducers.push(Box::new(reducer));
+        self
+    }
+
+    /// Adds a block reducer that runs after all the already registered ones
+    pub fn with_block_reducer(
+        mut self,
+        reducer: impl BlockReducer + Send + Sync + 'static,
+    ) -> Self {
+        self.block_reducers.push(Box::new(reducer));
+        self
+    }
+
     /// Decodes the inspection's actions
     pub fn inspect_tx(&self, tx: &mut TransactionData) {
         for inspector in self.inspectors.iter() {
@@ -53,10 +84,33 @@ impl BatchInspector {
         }
     }
 
+    /// Inspects and reduces all the transactions of a block and sorts them by
+    /// their `transaction_position`
+    pub fn inspect_block(&self, block: &Block<Transaction>, txs: &mut [TransactionData]) {
+        for tx in txs.iter_mut() {
+            tx.coinbase = Some(block.author);
+            self.inspect_tx(tx);
+            self.reduce_tx(tx);
+        }
+        txs.sort_by_key(|tx| tx.transaction_position);
+        for reducer in self.block_reducers.iter() {
+            reducer.reduce_block_txs(block, txs);
+        }
+    }
+
+    /// Reduces the `Evaluation`s of a block, which must be sorted by their
+    /// `transaction_position`
+    pub fn reduce_block(&self, block: &Block<Transaction>, evaluations: &mut [Evaluation]) {
+        for reducer in self.block_reducers.iter() {
+            reducer.reduce_block(block, evaluations);
+        }
+    }
+
     /// Evaluates all the blocks and evaluate them.
     ///
     /// This will return the `Evaluation`s of all the `Inspection`s for all the
-    /// blocks in any order.
+    /// blocks in any order. The `Evaluation`s of a block are only returned once
+    /// all of them are done and the block reducers ran.
     ///
     /// No more than `max` evaluations will be buffered at
     /// any point in time.
@@ -81,6 +135,7 @@ async fn get_block_info<M: Middleware + Unpin + 'static>(
         Block<Transaction>,
         Vec<TransactionReceipt>,
         Vec<Log>,
+        HashMap<TxHash, GasFees>,
     ),
     BatchEvaluationError<M>,
 > {
@@ -91,9 +146,9 @@ async fn get_block_info<M: Middleware + Unpin + 'static>(
             error,
         });
 
-    let block = provider
-        .get_block_with_txs(block_number)
-        .map_err(|error| BatchEvaluationError::Block {
+    // the block and its receipts are requested once and also decoded into the fees
+    let block = block_with_fees(provider.as_ref(), block_number)
+        .map_err(|error| BatchEvaluationError::Fees {
             block_number,
             error,
         })
@@ -101,13 +156,6 @@ async fn get_block_info<M: Middleware + Unpin + 'static>(
             futures::future::ready(block.ok_or(BatchEvaluationError::NotFound(block_number)))
         });
 
-    let receipts = provider
-        .parity_block_receipts(block_number)
-        .map_err(|error| BatchEvaluationError::Block {
-            block_number,
-            error,
-        });
-
     let filter = Filter::new()
         .from_block(block_number)
         .to_block(block_number);
@@ -119,7 +167,8 @@ async fn get_block_info<M: Middleware + Unpin + 'static>(
             error,
         });
 
-    futures::try_join!(traces, block, receipts, logs)
+    let (traces, (block, receipts, fees), logs) = futures::try_join!(traces, block, logs)?;
+    Ok((traces, block, receipts, logs, fees))
 }
 
 type BlockStream<T> = Pin<
@@ -131,6 +180,7 @@ type BlockStream<T> = Pin<
                         Block<Transaction>,
                         Vec<TransactionReceipt>,
                         Vec<Log>,
+                        HashMap<TxHash, GasFees>,
                     ),
                     BatchEvaluationError<T>,
                 >,
@@ -141,14 +191,26 @@ type BlockStream<T> = Pin<
 type EvaluationResult<T> =
     Pin<Box<dyn Future<Output = Result<Evaluation, BatchEvaluationError<T>>> + Send>>;
 
+/// A block whose transactions are still being evaluated
+struct PendingBlock {
+    block: Block<Transaction>,
+    /// How many of the block's transactions are not evaluated yet
+    remaining: usize,
+    evaluations: Vec<Evaluation>,
+}
+
 pub struct BatchEvaluator<M: Middleware + 'static> {
     prices: Arc<HistoricalPrice<M>>,
     inspector: Arc<BatchInspector>,
     block_infos: BlockStream<M>,
     /// Evaluations that currently ongoing
     evaluations_queue: FuturesUnordered<EvaluationResult<M>>,
-    /// `(TransactionData, gas_used, gas_price)` waiting to be evaluated
-    waiting_inspections: VecDeque<(TransactionData, U256, U256)>,
+    /// `(TransactionData, gas_used, fees, coinbase)` waiting to be evaluated
+    waiting_inspections: VecDeque<(TransactionData, U256, GasFees, Address)>,
+    /// block number -> the block's finished evaluations
+    pending_blocks: HashMap<u64, PendingBlock>,
+    /// Evaluations of blocks that are fully evaluated and reduced
+    ready: VecDeque<Evaluation>,
     /// maximum allowed buffered futures
     max: usize,
     /// whether all block requests are done
@@ -177,6 +239,8 @@ impl<M: Middleware + Unpin + 'static> BatchEvaluator<M> {
             block_infos: Box::pin(block_infos),
             evaluations_queue: FuturesUnordered::new(),
             waiting_inspections: VecDeque::new(),
+            pending_blocks: HashMap::new(),
+            ready: VecDeque::new(),
             max,
             blocks_done: false,
         }
@@ -187,12 +251,18 @@ impl<M: Middleware + Unpin + 'static> BatchEvaluator<M> {
         BatchInserts::new(mev_db, self)
     }
 
-    fn queue_in_evaluation(&mut self, tx: TransactionData, gas_used: U256, gas_price: U256) {
+    fn queue_in_evaluation(
+        &mut self,
+        tx: TransactionData,
+        gas_used: U256,
+        fees: GasFees,
+        coinbase: Address,
+    ) {
         let block_number = tx.block_number;
         let hash = tx.hash;
         let prices = Arc::clone(&self.prices);
         let eval = Box::pin(async move {
-            Evaluation::new(tx, prices.as_ref(), gas_used, gas_price)
+            Evaluation::new(tx, prices.as_ref(), gas_used, fees, coinbase)
                 .map_err(move |error| BatchEvaluationError::Evaluation {
                     block_number,
                     hash,
@@ -202,6 +272,35 @@ impl<M: Middleware + Unpin + 'static> BatchEvaluator<M> {
         });
         self.evaluations_queue.push(eval);
     }
+
+    /// Counts a finished evaluation of the block, either the `Evaluation` or
+    /// `None` if it failed
+    fn finish_evaluation(&mut self, block_number: u64, evaluation: Option<Evaluation>) {
+        let pending = if let Some(pending) = self.pending_blocks.get_mut(&block_number) {
+            pending
+        } else {
+            self.ready.extend(evaluation);
+            return;
+        };
+        pending.evaluations.extend(evaluation);
+        pending.remaining -= 1;
+        if pending.remaining > 0 {
+            return;
+        }
+
+        let PendingBlock {
+            block,
+            mut evaluations,
+            ..
+        } = self
+            .pending_blocks
+            .remove(&block_number)
+            .expect("pending block exists");
+        evaluations.sort_by_key(|evaluation| evaluation.tx.transaction_position);
+        self.inspector.reduce_block(&block, &mut evaluations);
+        log::trace!("reduced block {}", block_number);
+        self.ready.extend(evaluations);
+    }
 }
 
 impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
@@ -210,104 +309,139 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
 
-        // queue in buffered evaluation jobs
-        while this.evaluations_queue.len() < this.max {
-            if let Some((inspection, gas_used, gas_price)) = this.waiting_inspections.pop_front() {
-                this.queue_in_evaluation(inspection, gas_used, gas_price);
-                log::trace!(
-                    "queued new evaluation job, active: {}, waiting: {}",
-                    this.evaluations_queue.len(),
-                    this.waiting_inspections.len()
-                );
-            } else {
-                break;
+        loop {
+            // queue in buffered evaluation jobs
+            while this.evaluations_queue.len() < this.max {
+                if let Some((inspection, gas_used, fees, coinbase)) =
+                    this.waiting_inspections.pop_front()
+                {
+                    this.queue_in_evaluation(inspection, gas_used, fees, coinbase);
+                    log::trace!(
+                        "queued new evaluation job, active: {}, waiting: {}",
+                        this.evaluations_queue.len(),
+                        this.waiting_inspections.len()
+                    );
+                } else {
+                    break;
+                }
             }
-        }
 
-        while this.evaluations_queue.len() < this.max {
-            match this.block_infos.as_mut().poll_next(cx) {
-                Poll::Ready(Some(Ok((traces, block, receipts, logs)))) => {
-                    log::trace!("fetched block infos for block {:?}", block.number);
-                    let gas_price_txs = block
-                        .transactions
-                        .iter()
-                        .map(|tx| (tx.hash, tx.gas_price))
-                        .collect::<HashMap<TxHash, U256>>();
-
-                    // tx -> logs
-                    let mut all_tx_logs = logs
-                        .into_iter()
-                        .filter_map(|log| EventLog::try_from(log).ok())
-                        .into_group_map_by(|log| log.transaction_hash);
-
-                    let gas_used_txs = receipts
-                        .into_iter()
-                        .map(|receipt| {
-                            (
-                                receipt.transaction_hash,
-                                receipt.gas_used.unwrap_or_default(),
-                            )
-                        })
-                        .collect::<HashMap<TxHash, U256>>();
-
-                    for mut tx in traces
-                        .clone()
-                        .into_iter()
-                        .group_by(|t| t.transaction_hash.expect("tx hash exists"))
-                        .into_iter()
-                        .filter_map(|(tx, tx_traces)| {
-                            let tx_logs = all_tx_logs.remove(&tx).unwrap_or_default();
-                            TransactionData::create(tx_traces, tx_logs).ok()
-                        })
-                    {
-                        this.inspector.inspect_tx(&mut tx);
-                        this.inspector.reduce_tx(&mut tx);
-
-                        let gas_used = gas_used_txs.get(&tx.hash).cloned().unwrap_or_default();
-
-                        let gas_price = gas_price_txs.get(&tx.hash).cloned().unwrap_or_default();
-
-                        if this.evaluations_queue.len() < this.max {
-                            this.queue_in_evaluation(tx, gas_used, gas_price)
-                        } else {
-                            this.waiting_inspections
-                                .push_back((tx, gas_used, gas_price));
+            while this.evaluations_queue.len() < this.max {
+                match this.block_infos.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(Ok((traces, block, receipts, logs, mut fees_txs)))) => {
+                        log::trace!("fetched block infos for block {:?}", block.number);
+                        let coinbase = block.author;
+                        let gas_price_txs = block
+                            .transactions
+                            .iter()
+                            .map(|tx| (tx.hash, tx.gas_price))
+                            .collect::<HashMap<TxHash, U256>>();
+
+                        // tx -> logs
+                        let mut all_tx_logs = logs
+                            .into_iter()
+                            .filter_map(|log| EventLog::try_from(log).ok())
+                            .into_group_map_by(|log| log.transaction_hash);
+
+                        let gas_used_txs = receipts
+                            .into_iter()
+                            .map(|receipt| {
+                                (
+                                    receipt.transaction_hash,
+                                    receipt.gas_used.unwrap_or_default(),
+                                )
+                            })
+                            .collect::<HashMap<TxHash, U256>>();
+
+                        let mut txs = traces
+                            .into_iter()
+                            .group_by(|t| t.transaction_hash.expect("tx hash exists"))
+                            .into_iter()
+                            .filter_map(|(tx, tx_traces)| {
+                                let tx_logs = all_tx_logs.remove(&tx).unwrap_or_default();
+                                TransactionData::create(tx_traces, tx_logs).ok()
+                            })
+                            .collect::<Vec<_>>();
+
+                        if txs.is_empty() {
+                            continue;
+                        }
+                        this.inspector.inspect_block(&block, &mut txs);
+
+                        let block_number = txs[0].block_number;
+                        this.pending_blocks.insert(
+                            block_number,
+                            PendingBlock {
+                                remaining: txs.len(),
+                                evaluations: Vec::with_capacity(txs.len()),
+                                block,
+                            },
+                        );
+
+                        for tx in txs {
+                            let gas_used = gas_used_txs.get(&tx.hash).cloned().unwrap_or_default();
+
+                            let fees = fees_txs.remove(&tx.hash).unwrap_or_else(|| {
+                                GasFees::legacy(
+                                    gas_price_txs.get(&tx.hash).cloned().unwrap_or_default(),
+                                )
+                            });
+
+                            if this.evaluations_queue.len() < this.max {
+                                this.queue_in_evaluation(tx, gas_used, fees, coinbase)
+                            } else {
+                                this.waiting_inspections
+                                    .push_back((tx, gas_used, fees, coinbase));
+                            }
                         }
                     }
-                }
-                Poll::Ready(Some(Err(err))) => {
-                    return {
-                        log::error!("failed to fetch block: {:?}", err);
-                        Poll::Ready(Some(Err(err)))
+                    Poll::Ready(Some(Err(err))) => {
+                        return {
+                            log::error!("failed to fetch block: {:?}", err);
+                            Poll::Ready(Some(Err(err)))
+                        }
+                    }
+                    Poll::Pending => break,
+                    Poll::Ready(None) => {
+                        log::trace!("all blocks fetched");
+                        this.blocks_done = true;
+                        break;
                     }
-                }
-                Poll::Pending => break,
-                Poll::Ready(None) => {
-                    log::trace!("all blocks fetched");
-                    this.blocks_done = true;
-                    break;
                 }
             }
-        }
 
-        // pull the next value from the evaluations_queue
-        match this.evaluations_queue.poll_next_unpin(cx) {
-            x @ Poll::Pending | x @ Poll::Ready(Some(_)) => {
-                log::trace!("finished evaluation");
-                return x;
+            // return the evaluations of blocks that are already reduced
+            if let Some(evaluation) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(evaluation)));
             }
-            Poll::Ready(None) => {}
-        }
 
-        // If more values are still coming from the stream, we're not done yet
-        if this.blocks_done
-            && this.evaluations_queue.is_empty()
-            && this.waiting_inspections.is_empty()
-        {
-            log::info!("batch done");
-            Poll::Ready(None)
-        } else {
-            Poll::Pending
+            // pull the next value from the evaluations_queue
+            match this.evaluations_queue.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(evaluation))) => {
+                    log::trace!("finished evaluation");
+                    this.finish_evaluation(evaluation.tx.block_number, Some(evaluation));
+                    continue;
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    if let BatchEvaluationError::Evaluation { block_number, .. } = &err {
+                        this.finish_evaluation(*block_number, None);
+                    }
+                    return Poll::Ready(Some(Err(err)));
+                }
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {}
+            }
+
+            // If more values are still coming from the stream, we're not done yet
+            return if this.blocks_done
+                && this.evaluations_queue.is_empty()
+                && this.waiting_inspections.is_empty()
+            {
+                log::info!("batch done");
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
         }
     }
 
@@ -315,7 +449,12 @@ impl<M: Middleware + Unpin + 'static> Stream for BatchEvaluator<M> {
         let (blocks, _) = self.block_infos.size_hint();
         let evals = self.evaluations_queue.len();
         let waiting = self.waiting_inspections.len();
-        (blocks + evals + waiting, None)
+        let pending = self
+            .pending_blocks
+            .values()
+            .map(|pending| pending.evaluations.len())
+            .sum::<usize>();
+        (blocks + evals + waiting + pending + self.ready.len(), None)
     }
 }
 
@@ -338,6 +477,17 @@ pub enum BatchEvaluationError<M: Middleware + 'static> {
         /// The reason why it failed
         error: EvalError<M>,
     },
+    #[error(
+        "Failed to get block {} with its receipts and fees: {:?}",
+        block_number,
+        error
+    )]
+    Fees {
+        /// The block number of the inspection
+        block_number: u64,
+        /// The reason why it failed
+        error: ProviderError,
+    },
     #[error("Failed to get block {}: {:?}", block_number, error)]
     Block {
         /// The block number of the inspection
@@ -351,7 +501,7 @@ pub enum BatchEvaluationError<M: Middleware + 'static> {
 mod tests {
     use super::*;
     use crate::{
-        addresses::{ADDRESSBOOK, WETH},
+        addresses::{lookup, parse_address, WETH},
         inspectors::*,
         reducers::*,
         set,
@@ -387,11 +537,8 @@ mod tests {
             set![Protocol::UniswapV2, Protocol::Sushiswap, Protocol::Aave]
         );
 
-        assert_eq!(ADDRESSBOOK.get(&liquidation.token).unwrap(), "ETH");
-        assert_eq!(
-            ADDRESSBOOK.get(&liquidation.as_ref().sent_token).unwrap(),
-            "YFI"
-        );
+        assert_eq!(lookup(liquidation.token), "ETH");
+        assert_eq!(lookup(liquidation.as_ref().sent_token), "YFI");
     }
 
     #[test]
@@ -446,7 +593,9 @@ mod tests {
         inspector.reduce_tx(&mut tx);
 
         let arb = tx.actions().arbitrage().next().unwrap();
-        assert_eq!(arb.profit, U256::from_dec_str("14397525374450478").unwrap());
+        // 0.0143975 WETH made by the trades minus 0.0012845 ETH the bot sent to
+        // 0x61935cbdd02287b511119ddb11aeb42f1593b7ef
+        assert_eq!(arb.profit, U256::from_dec_str("13113025374450478").unwrap());
         assert_eq!(arb.token, *WETH);
         assert_eq!(
             tx.protocols(),
@@ -504,8 +653,8 @@ mod tests {
         assert_eq!(tx.status, Status::Success);
         assert_eq!(tx.protocols(), set![Protocol::UniswapV2, Protocol::Aave]);
         let liquidation = tx.actions().liquidations().next().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&liquidation.sent_token).unwrap(), "BAT");
-        assert_eq!(ADDRESSBOOK.get(&liquidation.received_token).unwrap(), "DAI");
+        assert_eq!(lookup(liquidation.sent_token), "BAT");
+        assert_eq!(lookup(liquidation.received_token), "DAI");
     }
 
     // #[test]
@@ -556,6 +705,10 @@ mod tests {
         dbg!(tx.actions().add_liquidity().collect::<Vec<_>>());
         // then the addliquidity call gets classified
         let add_liquidity = tx.actions().add_liquidity().next().unwrap();
+        assert_eq!(
+            add_liquidity.pool,
+            parse_address("0xbb2b8038a1640196fbe3e38816f3e67cba72d940")
+        );
         assert_eq!(
             add_liquidity.amounts,
             vec![
@@ -564,4 +717,27 @@ mod tests {
             ]
         );
     }
+
+    /// Records the positions of the transactions it was given
+    struct PositionRecorder(Arc<std::sync::Mutex<Vec<usize>>>);
+
+    impl BlockReducer for PositionRecorder {
+        fn reduce_block_txs(&self, _: &Block<Transaction>, txs: &mut [TransactionData]) {
+            *self.0.lock().unwrap() = txs.iter().map(|tx| tx.transaction_position).collect();
+        }
+    }
+
+    #[test]
+    fn block_reducers_see_sorted_txs() {
+        let positions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let inspector = test_inspector().with_block_reducer(PositionRecorder(positions.clone()));
+
+        let mut txs = block_txs();
+        txs.reverse();
+        inspector.inspect_block(&Block::default(), &mut txs);
+
+        let positions = positions.lock().unwrap();
+        assert_eq!(positions.len(), txs.len());
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
 }
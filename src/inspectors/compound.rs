@@ -1,17 +1,24 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::{Arc, RwLock},
+};
 
 use ethers::{
     abi::FunctionExt,
     contract::{abigen, BaseContract, ContractError, EthLogDecode},
     providers::Middleware,
     types::{Address, Call, CallType, U256},
+    utils::id,
 };
 
 use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::{Action, TransactionData};
 use crate::{
-    actions_after,
-    addresses::{CETH, COMPTROLLER, COMP_ORACLE, WETH},
+    actions_after, inspect_tx,
+    network::network,
     traits::Inspector,
     types::{
         actions::{Liquidation, SpecificAction},
@@ -44,7 +51,11 @@ pub struct Compound {
     ctoken: BaseContract,
     cether: BaseContract,
     comptroller: BaseContract,
-    ctoken_to_token: HashMap<Address, Address>,
+    /// cToken -> underlying, shared by all clones.
+    ///
+    /// Markets whose `MarketListed` event was seen while inspecting are `None`
+    /// until their underlying is pulled from a liquidator.
+    markets: Arc<RwLock<HashMap<Address, Option<Address>>>>,
 }
 
 impl DefiProtocol for Compound {
@@ -76,7 +87,7 @@ impl DefiProtocol for Compound {
                     .next()
                 {
                     let action = Liquidation {
-                        sent_token: *self.underlying(&call.to),
+                        sent_token: self.liquidated_underlying(call, tx),
                         sent_amount: liquidation.repay_amount,
 
                         received_token: liquidation.c_token_collateral,
@@ -84,6 +95,7 @@ impl DefiProtocol for Compound {
 
                         from: call.from,
                         liquidated_user: liquidation.borrower,
+                        collateral_type: None,
                     };
                     return Some(Action::with_logs(
                         action.into(),
@@ -115,6 +127,11 @@ impl DefiProtocol for Compound {
             .map(|c| (c, None))
             .ok()
     }
+
+    fn inspect_tx(&self, tx: &mut TransactionData) {
+        self.list_markets(tx);
+        inspect_tx(self, tx)
+    }
 }
 
 impl Inspector for Compound {
@@ -163,19 +180,46 @@ impl Compound {
             ctoken: BaseContract::from(CTOKEN_ABI.clone()),
             cether: BaseContract::from(CETHER_ABI.clone()),
             comptroller: BaseContract::from(COMPTROLLER_ABI.clone()),
-            ctoken_to_token: ctoken_to_token.into_iter().collect(),
+            markets: Arc::new(RwLock::new(
+                ctoken_to_token
+                    .into_iter()
+                    .map(|(ctoken, token)| (ctoken, Some(token)))
+                    .collect(),
+            )),
         }
     }
 
+    /// Instantiates Compound with the markets of the json snapshot at the
+    /// given path, which may include the markets of forks like Cream
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let markets: HashMap<Address, Option<Address>> = serde_json::from_reader(reader)?;
+        let this = Self::new(vec![]);
+        *this.markets.write().unwrap() = markets;
+        Ok(this)
+    }
+
+    /// Writes all known markets, including the ones listed while inspecting,
+    /// to the json file at the given path
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        Ok(serde_json::to_writer(file, &*self.markets.read().unwrap())?)
+    }
+
     /// Instantiates Compound with all live markets
     ///
     /// # Panics
     ///
     /// - If the `Ctoken.underlying` call fails
+    /// - If there is no Compound deployment on the network in use
     pub async fn create<M: Middleware>(
         provider: std::sync::Arc<M>,
     ) -> Result<Self, ContractError<M>> {
-        let comptroller = Comptroller::new(*COMPTROLLER, provider.clone());
+        let network = network();
+        let config = network
+            .compound
+            .expect("no compound deployment on the network");
+        let comptroller = Comptroller::new(config.comptroller, provider.clone());
 
         let markets = comptroller.get_all_markets().call().await?;
         let futs = markets
@@ -183,7 +227,7 @@ impl Compound {
             .map(|market| {
                 let provider = provider.clone();
                 async move {
-                    if market != *CETH {
+                    if market != config.cether {
                         (
                             market,
                             CToken::new(market, provider)
@@ -193,7 +237,7 @@ impl Compound {
                                 .expect("could not get underlying"),
                         )
                     } else {
-                        (market, *WETH)
+                        (market, network.wrapped_native)
                     }
                 }
             })
@@ -214,7 +258,7 @@ impl Compound {
                 {
                     Some((
                         Liquidation {
-                            sent_token: *self.underlying(&call.to),
+                            sent_token: self.underlying(&call.to),
                             sent_amount: repaid_amount,
 
                             received_token: ctoken_collateral,
@@ -222,6 +266,7 @@ impl Compound {
 
                             from: call.from,
                             liquidated_user,
+                            collateral_type: None,
                         },
                         calltrace.trace_address.clone(),
                     ))
@@ -231,7 +276,7 @@ impl Compound {
                 {
                     Some((
                         Liquidation {
-                            sent_token: *self.underlying(&call.to),
+                            sent_token: self.underlying(&call.to),
                             sent_amount: call.value,
 
                             received_token: ctoken_collateral,
@@ -239,6 +284,7 @@ impl Compound {
 
                             from: call.from,
                             liquidated_user,
+                            collateral_type: None,
                         },
                         calltrace.trace_address.clone(),
                     ))
@@ -272,9 +318,13 @@ impl Compound {
             Classification::Unknown(ref calltrace) => {
                 let call = calltrace.as_ref();
                 // checks if liquidation is allowed
-                call.to == *COMPTROLLER && call.input.as_ref().starts_with(&self.comptroller.as_ref().function("liquidateBorrowAllowed").unwrap().selector()) ||
+                let config = match network().compound {
+                    Some(config) => config,
+                    None => return false,
+                };
+                call.to == config.comptroller && call.input.as_ref().starts_with(&self.comptroller.as_ref().function("liquidateBorrowAllowed").unwrap().selector()) ||
                     // checks oracle price
-                    call.to == *COMP_ORACLE && call.input.as_ref().starts_with(&ethers::utils::id("getUnderlyingPrice(address)"))
+                    call.to == config.oracle && call.input.as_ref().starts_with(&ethers::utils::id("getUnderlyingPrice(address)"))
             }
             _ => false,
         }
@@ -283,11 +333,60 @@ impl Compound {
     // helper for converting cToken to Token address.
     // TODO: Should this also include decimals? Or should we assume that
     // cTokens always use 8 decimals
-    fn underlying<'a>(&'a self, address: &'a Address) -> &'a Address {
-        if let Some(inner) = self.ctoken_to_token.get(address) {
-            inner
+    fn underlying(&self, address: &Address) -> Address {
+        self.markets
+            .read()
+            .unwrap()
+            .get(address)
+            .cloned()
+            .flatten()
+            .unwrap_or(*address)
+    }
+
+    /// Adds the markets listed by any comptroller in the transaction, so that
+    /// the markets of forks are covered as well
+    fn list_markets(&self, tx: &TransactionData) {
+        let listed = tx
+            .all_logs()
+            .filter_map(|log| comptroller_mod::MarketListedFilter::decode_log(&log.raw_log).ok())
+            .collect::<Vec<_>>();
+        if listed.is_empty() {
+            return;
+        }
+        let mut markets = self.markets.write().unwrap();
+        for event in listed {
+            markets.entry(event.c_token).or_insert(None);
+        }
+    }
+
+    /// The underlying of the liquidation's borrowed market.
+    ///
+    /// The underlying of a market which was listed while inspecting is
+    /// resolved from the `transferFrom` the cToken repays the borrow with, or
+    /// is the native currency if the borrow is repaid with ETH.
+    fn liquidated_underlying(&self, call: &InternalCall, tx: &TransactionData) -> Address {
+        if self.markets.read().unwrap().get(&call.to) != Some(&None) {
+            return self.underlying(&call.to);
+        }
+        let underlying = if !call.value.is_zero() {
+            Some(network().wrapped_native)
         } else {
-            &address
+            let transfer_from = id("transferFrom(address,address,uint256)");
+            tx.subcalls(&call.trace_address)
+                .find(|subcall| {
+                    subcall.from == call.to && subcall.input.starts_with(&transfer_from)
+                })
+                .map(|subcall| subcall.to)
+        };
+        match underlying {
+            Some(underlying) => {
+                self.markets
+                    .write()
+                    .unwrap()
+                    .insert(call.to, Some(underlying));
+                underlying
+            }
+            None => call.to,
         }
     }
 }
@@ -299,7 +398,7 @@ mod tests {
     use ethers::providers::Provider;
 
     use crate::{
-        addresses::{parse_address, ADDRESSBOOK},
+        addresses::{lookup, parse_address},
         test_helpers::*,
         types::Status,
         Inspector,
@@ -320,7 +419,7 @@ mod tests {
 
         let liquidation = tx.actions().liquidations().next().unwrap();
 
-        assert_eq!(ADDRESSBOOK.get(&liquidation.sent_token).unwrap(), "ZRX");
+        assert_eq!(lookup(liquidation.sent_token), "ZRX");
         // cETH has 8 decimals
         assert_eq!(liquidation.received_amount, 5250648.into());
         // ZRX has 18 decimals
@@ -348,7 +447,7 @@ mod tests {
             .cloned()
             .unwrap();
 
-        assert_eq!(ADDRESSBOOK.get(&liquidation.sent_token).unwrap(), "ZRX");
+        assert_eq!(lookup(liquidation.sent_token), "ZRX");
         // cETH has 8 decimals
         assert_eq!(liquidation.received_amount, 5250648.into());
         // ZRX has 18 decimals
@@ -358,6 +457,106 @@ mod tests {
         assert_eq!(inspection.status, Status::Success);
     }
 
+    #[test]
+    fn snapshot() {
+        let compound = Compound::load("res/compound_markets.json").unwrap();
+        let mut tx = read_tx("compound_liquidation.data.json");
+        compound.inspect_tx(&mut tx);
+
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!(lookup(liquidation.sent_token), "ZRX");
+
+        let path = std::env::temp_dir().join("mev-inspect-compound-markets.json");
+        compound.save(&path).unwrap();
+        let loaded = Compound::load(&path).unwrap();
+        assert_eq!(
+            *loaded.markets.read().unwrap(),
+            *compound.markets.read().unwrap()
+        );
+    }
+
+    #[test]
+    fn listed_market() {
+        use ethers::{abi::Token, contract::EthEvent};
+
+        let addrs = addrs();
+        let [comptroller, crtoken, underlying, liquidator, borrower, collateral] =
+            [addrs[0], addrs[1], addrs[2], addrs[3], addrs[4], addrs[5]];
+        let compound = Compound::new(vec![]);
+
+        // a fork's comptroller lists a new market
+        let listing = mk_trace(
+            borrower,
+            comptroller,
+            calldata("_supportMarket(address)", &[Token::Address(crtoken)]),
+            vec![],
+            0,
+        );
+        let listed = mk_log(
+            comptroller,
+            vec![comptroller_mod::MarketListedFilter::signature()],
+            vec![Token::Address(crtoken)],
+            0,
+        );
+        let mut tx = TransactionData::create(vec![listing], vec![listed]).unwrap();
+        compound.inspect_tx(&mut tx);
+        assert_eq!(compound.markets.read().unwrap().get(&crtoken), Some(&None));
+
+        let traces = vec![
+            mk_trace(
+                liquidator,
+                crtoken,
+                calldata(
+                    "liquidateBorrow(address,uint256,address)",
+                    &[
+                        Token::Address(borrower),
+                        Token::Uint(100.into()),
+                        Token::Address(collateral),
+                    ],
+                ),
+                vec![],
+                1,
+            ),
+            mk_trace(
+                crtoken,
+                underlying,
+                calldata(
+                    "transferFrom(address,address,uint256)",
+                    &[
+                        Token::Address(liquidator),
+                        Token::Address(crtoken),
+                        Token::Uint(100.into()),
+                    ],
+                ),
+                vec![0],
+                0,
+            ),
+        ];
+        let logs = vec![mk_log(
+            crtoken,
+            vec![ctoken_mod::LiquidateBorrowFilter::signature()],
+            vec![
+                Token::Address(liquidator),
+                Token::Address(borrower),
+                Token::Uint(100.into()),
+                Token::Address(collateral),
+                Token::Uint(5.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        compound.inspect_tx(&mut tx);
+
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!(liquidation.sent_token, underlying);
+        assert_eq!(liquidation.sent_amount, 100.into());
+        assert_eq!(
+            compound.underlying(&crtoken),
+            underlying,
+            "the underlying is remembered"
+        );
+    }
+
     #[tokio::test]
     async fn instantiate() {
         let provider =
@@ -369,7 +568,7 @@ mod tests {
 
         // cZRX -> ZRX
         assert_eq!(
-            *compound.underlying(&parse_address("0xb3319f5d18bc0d84dd1b4825dcde5d5f7266d407")),
+            compound.underlying(&parse_address("0xb3319f5d18bc0d84dd1b4825dcde5d5f7266d407")),
             parse_address("0xe41d2489571d322189246dafa5ebde1f4699f498"),
         );
     }
@@ -1,34 +1,327 @@
 #![allow(clippy::too_many_arguments)]
 use crate::{
-    addresses::CURVE_REGISTRY,
+    network::network,
     traits::Inspector,
     types::{actions::AddLiquidity, Classification, Inspection, Protocol},
     DefiProtocol, ProtocolContracts,
 };
 
 use crate::model::{CallClassification, EventLog, InternalCall};
-use crate::types::actions::{SpecificAction, Trade, Transfer};
+use crate::types::actions::{RemoveLiquidity, SpecificAction, Trade, Transfer};
 use crate::types::{decode_token_transfers_prior, Action, TransactionData};
 use ethers::{
-    abi::parse_abi,
+    abi::{self, parse_abi, ParamType, RawLog, Token},
     contract::{abigen, ContractError},
     contract::{decode_function_data, BaseContract, EthLogDecode},
     providers::Middleware,
-    types::{Address, Call as TraceCall, U256},
+    types::{Address, Call as TraceCall, Filter, H256, U256},
+    utils::{id, keccak256},
 };
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path, sync::Arc};
+use thiserror::Error;
 
 /// Type aliases for Curve
 /// i: int128, j: int128, _dx: uint256, _min_dy: uint256
 /// https://github.com/curvefi/curve-contract/blob/c6df0cf14b557b11661a474d8d278affd849d3fe/contracts/pool-templates/base/SwapTemplateBase.vy#L447
 type Exchange = (u128, u128, U256, U256);
 
+/// Curve pools have at most 4 coins
+const MAX_COINS: usize = 4;
+
+/// How many blocks to request the registry's logs for at once
+const REGISTRY_BLOCK_STEP: u64 = 10_000;
+
 #[derive(Debug, Clone)]
 /// An inspector for Curve
 pub struct Curve {
     pool: BaseContract,
     pool4: BaseContract,
-    pools: HashMap<Address, Vec<Address>>,
+    pools: HashMap<Address, CurveCoins>,
+    liquidity_functions: Vec<LiquidityFunction>,
+}
+
+/// The coins of a Curve pool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurveCoins {
+    /// The coins the pool holds, e.g. the cTokens of a lending pool or the
+    /// base pool's LP token of a metapool
+    pub coins: Vec<Address>,
+    /// The coins `exchange_underlying` trades, the same as `coins` for plain
+    /// pools
+    pub underlying_coins: Vec<Address>,
+}
+
+impl CurveCoins {
+    /// Drops the zero addresses the registry pads its coin arrays with
+    pub fn new(
+        coins: impl IntoIterator<Item = Address>,
+        underlying_coins: impl IntoIterator<Item = Address>,
+    ) -> Self {
+        Self {
+            coins: coins.into_iter().filter(|coin| !coin.is_zero()).collect(),
+            underlying_coins: underlying_coins
+                .into_iter()
+                .filter(|coin| !coin.is_zero())
+                .collect(),
+        }
+    }
+}
+
+/// The Curve pools known up to a block.
+///
+/// The registry's pools are collected from its `PoolAdded` and `PoolRemoved`
+/// events and the factory's pools by their index, so a snapshot can be
+/// refreshed incrementally and saved to a JSON file for runs without a node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurvePools {
+    /// The last block whose registry events are included
+    pub block: u64,
+    /// The number of the factory's pools that are included
+    pub factory_pools: u64,
+    /// pool -> coins of the pool
+    pub pools: HashMap<Address, CurveCoins>,
+}
+
+impl CurvePools {
+    /// Reads a snapshot from the json file at the given path
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the snapshot to the json file at the given path
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        Ok(serde_json::to_writer(file, self)?)
+    }
+
+    /// Adds the pools registered since the snapshot's block and the factory
+    /// pools deployed since its last refresh
+    pub async fn refresh<M: Middleware + 'static>(
+        &mut self,
+        provider: Arc<M>,
+    ) -> Result<(), CurvePoolsError<M>> {
+        let latest = provider
+            .get_block_number()
+            .await
+            .map_err(CurvePoolsError::Provider)?
+            .as_u64();
+
+        if let Some(address) = network().curve_registry {
+            if self.block < latest {
+                self.refresh_registry(address, provider.clone(), latest)
+                    .await?;
+            }
+        }
+        if let Some(address) = network().curve_factory {
+            self.refresh_factory(address, provider).await?;
+        }
+        self.block = self.block.max(latest);
+        log::info!(
+            "{} curve pools known at block {}",
+            self.pools.len(),
+            self.block
+        );
+        Ok(())
+    }
+
+    async fn refresh_registry<M: Middleware + 'static>(
+        &mut self,
+        address: Address,
+        provider: Arc<M>,
+        latest: u64,
+    ) -> Result<(), CurvePoolsError<M>> {
+        // the logs are requested in windows to stay below the provider's limits
+        let mut logs = Vec::new();
+        let mut start = self.block + 1;
+        while start <= latest {
+            let end = latest.min(start + REGISTRY_BLOCK_STEP - 1);
+            let filter = Filter::new()
+                .address(address)
+                .from_block(start)
+                .to_block(end);
+            logs.extend(
+                provider
+                    .get_logs(&filter)
+                    .await
+                    .map_err(CurvePoolsError::Provider)?,
+            );
+            start = end + 1;
+        }
+
+        let registry = CurveRegistry::new(address, provider);
+        for log in logs {
+            let raw_log = RawLog {
+                topics: log.topics,
+                data: log.data.to_vec(),
+            };
+            match CurveRegistryEvents::decode_log(&raw_log) {
+                Ok(CurveRegistryEvents::PoolAddedFilter(event)) => {
+                    // the registry resolves the underlying coins of lending
+                    // pools and metapools
+                    let coins = registry.get_coins(event.pool).call().await?;
+                    let underlying_coins = registry.get_underlying_coins(event.pool).call().await?;
+                    self.pools.insert(
+                        event.pool,
+                        CurveCoins::new(coins.to_vec(), underlying_coins.to_vec()),
+                    );
+                }
+                Ok(CurveRegistryEvents::PoolRemovedFilter(event)) => {
+                    self.pools.remove(&event.pool);
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn refresh_factory<M: Middleware + 'static>(
+        &mut self,
+        address: Address,
+        provider: Arc<M>,
+    ) -> Result<(), CurvePoolsError<M>> {
+        // the factory does not emit the address of a new pool, but its pools
+        // are never removed from `pool_list`
+        let factory = CurveFactory::new(address, provider);
+        let pool_count = factory.pool_count().call().await?.as_u64();
+        for i in self.factory_pools..pool_count {
+            let pool = factory.pool_list(i.into()).call().await?;
+            let coins = factory.get_coins(pool).call().await?;
+            let underlying_coins = factory.get_underlying_coins(pool).call().await?;
+            self.pools
+                .entry(pool)
+                .or_insert_with(|| CurveCoins::new(coins.to_vec(), underlying_coins.to_vec()));
+        }
+        self.factory_pools = self.factory_pools.max(pool_count);
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CurvePoolsError<M: Middleware + 'static> {
+    #[error(transparent)]
+    Provider(<M as Middleware>::Error),
+
+    #[error(transparent)]
+    Contract(#[from] ContractError<M>),
+}
+
+/// The events of the pool functions that add or remove liquidity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LiquidityEvent {
+    AddLiquidity,
+    RemoveLiquidity,
+    RemoveLiquidityImbalance,
+    RemoveLiquidityOne,
+}
+
+/// A liquidity function of pools with `n_coins` coins, whose arrays' sizes are
+/// part of their selectors and event signatures.
+#[derive(Debug, Clone, Copy)]
+struct LiquidityFunction {
+    selector: [u8; 4],
+    event: LiquidityEvent,
+    /// `0` for `remove_liquidity_one_coin`, which takes no array
+    n_coins: usize,
+}
+
+impl LiquidityFunction {
+    fn new(signature: &str, event: LiquidityEvent, n_coins: usize) -> Self {
+        Self {
+            selector: id(signature),
+            event,
+            n_coins,
+        }
+    }
+
+    /// All liquidity functions of pools with up to `MAX_COINS` coins
+    fn all() -> Vec<Self> {
+        let mut functions = vec![Self::new(
+            "remove_liquidity_one_coin(uint256,int128,uint256)",
+            LiquidityEvent::RemoveLiquidityOne,
+            0,
+        )];
+        for n in 2..=MAX_COINS {
+            functions.push(Self::new(
+                &format!("add_liquidity(uint256[{}],uint256)", n),
+                LiquidityEvent::AddLiquidity,
+                n,
+            ));
+            functions.push(Self::new(
+                &format!("remove_liquidity(uint256,uint256[{}])", n),
+                LiquidityEvent::RemoveLiquidity,
+                n,
+            ));
+            functions.push(Self::new(
+                &format!("remove_liquidity_imbalance(uint256[{}],uint256)", n),
+                LiquidityEvent::RemoveLiquidityImbalance,
+                n,
+            ));
+        }
+        functions
+    }
+
+    fn classification(&self) -> CallClassification {
+        if self.event == LiquidityEvent::AddLiquidity {
+            CallClassification::AddLiquidity
+        } else {
+            CallClassification::RemoveLiquidity
+        }
+    }
+
+    /// The non-indexed fields of the function's event, all of which start
+    /// with the amounts of the coins that were added or removed
+    fn event_params(&self) -> (String, Vec<ParamType>) {
+        let n = self.n_coins;
+        let amounts = || ParamType::FixedArray(Box::new(ParamType::Uint(256)), n);
+        match self.event {
+            LiquidityEvent::AddLiquidity | LiquidityEvent::RemoveLiquidityImbalance => (
+                format!(
+                    "{:?}(address,uint256[{1}],uint256[{1}],uint256,uint256)",
+                    self.event, n
+                ),
+                vec![
+                    amounts(),
+                    amounts(),
+                    ParamType::Uint(256),
+                    ParamType::Uint(256),
+                ],
+            ),
+            LiquidityEvent::RemoveLiquidity => (
+                format!(
+                    "RemoveLiquidity(address,uint256[{0}],uint256[{0}],uint256)",
+                    n
+                ),
+                vec![amounts(), amounts(), ParamType::Uint(256)],
+            ),
+            // token_amount, coin_amount
+            LiquidityEvent::RemoveLiquidityOne => (
+                "RemoveLiquidityOne(address,uint256,uint256)".to_string(),
+                vec![ParamType::Uint(256), ParamType::Uint(256)],
+            ),
+        }
+    }
+
+    /// Decodes the amounts of the coins from the function's event
+    fn decode_amounts(&self, log: &RawLog) -> Option<Vec<U256>> {
+        let (signature, params) = self.event_params();
+        if log.topics.first() != Some(&H256::from(keccak256(signature))) {
+            return None;
+        }
+        let mut tokens = abi::decode(&params, &log.data).ok()?;
+        if self.event == LiquidityEvent::RemoveLiquidityOne {
+            Some(vec![tokens.pop()?.into_uint()?])
+        } else {
+            tokens
+                .swap_remove(0)
+                .into_fixed_array()?
+                .into_iter()
+                .map(Token::into_uint)
+                .collect()
+        }
+    }
 }
 
 abigen!(
@@ -39,6 +332,15 @@ abigen!(
     }
 );
 abigen!(CurvePool, "abi/curvepool.json");
+abigen!(
+    CurveFactory,
+    r#"[
+        function pool_count() external view returns (uint256)
+        function pool_list(uint256 arg0) external view returns (address)
+        function get_coins(address pool) external view returns (address[2])
+        function get_underlying_coins(address pool) external view returns (address[8])
+    ]"#
+);
 
 impl DefiProtocol for Curve {
     fn base_contracts(&self) -> ProtocolContracts {
@@ -56,24 +358,11 @@ impl DefiProtocol for Curve {
 
     fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
         match call.classification {
-            CallClassification::AddLiquidity => {
-                // https://github.com/curvefi/curve-contract/blob/c6df0cf14b557b11661a474d8d278affd849d3fe/contracts/pool-templates/base/SwapTemplateBase.vy#L27
-                if let Some((_, log, add_liquidity)) = tx
-                    .call_logs_decoded::<AddLiquidityFilter>(&call.trace_address)
-                    .next()
-                {
-                    let tokens = self.pools.get(&call.to).cloned().unwrap_or_default();
-                    let action = AddLiquidity {
-                        tokens,
-                        amounts: add_liquidity.token_amounts.to_vec(),
-                    };
-
-                    return Some(Action::with_logs(
-                        action.into(),
-                        call.trace_address.clone(),
-                        vec![log.log_index],
-                    ));
-                }
+            CallClassification::AddLiquidity | CallClassification::RemoveLiquidity => {
+                return self.decode_liquidity(call, tx);
+            }
+            CallClassification::Swap if self.is_exchange_underlying(call) => {
+                return self.decode_exchange_underlying(call, tx);
             }
             CallClassification::Swap => {
                 // find the swap log
@@ -122,13 +411,18 @@ impl DefiProtocol for Curve {
         &self,
         call: &InternalCall,
     ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        if self.pools.contains_key(&call.from) {
+            // a metapool's calls into its base pool are part of its own action
+            return None;
+        }
         // https://github.com/curvefi/curve-contract/blob/c6df0cf14b557b11661a474d8d278affd849d3fe/contracts/pool-templates/base/SwapTemplateBase.vy#L372
-        if self.as_add_liquidity(&call.to, &call.input).is_some() {
-            Some((CallClassification::AddLiquidity, None))
-        } else if self
-            .pool
-            .decode::<Exchange, _>("exchange", &call.input)
-            .is_ok()
+        if let Some(function) = self.liquidity_function(call) {
+            Some((function.classification(), None))
+        } else if self.is_exchange_underlying(call)
+            || self
+                .pool
+                .decode::<Exchange, _>("exchange", &call.input)
+                .is_ok()
         {
             Some((CallClassification::Swap, None))
         } else {
@@ -166,7 +460,7 @@ impl Inspector for Curve {
 
 impl Curve {
     /// Constructor
-    pub fn new<T: IntoIterator<Item = (Address, Vec<Address>)>>(pools: T) -> Self {
+    pub fn new<T: IntoIterator<Item = (Address, CurveCoins)>>(pools: T) -> Self {
         Self {
             pool: BaseContract::from(CURVEPOOL_ABI.clone()),
             pool4: parse_abi(&[
@@ -175,11 +469,117 @@ impl Curve {
             .expect("could not parse curve 4-pool abi")
             .into(),
             pools: pools.into_iter().collect(),
+            liquidity_functions: LiquidityFunction::all(),
         }
     }
 
+    fn liquidity_function(&self, call: &InternalCall) -> Option<&LiquidityFunction> {
+        self.liquidity_functions
+            .iter()
+            .find(|function| call.input.starts_with(&function.selector))
+    }
+
+    fn is_exchange_underlying(&self, call: &InternalCall) -> bool {
+        self.pool
+            .as_ref()
+            .function("exchange_underlying")
+            .map(|function| call.input.starts_with(&function.short_signature()))
+            .unwrap_or_default()
+    }
+
+    /// Decodes the coin amounts of a liquidity call from the event the pool
+    /// emitted
+    fn decode_liquidity(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let function = self.liquidity_function(call)?;
+        let (log_index, amounts) = tx
+            .call_logs(&call.trace_address)
+            .filter(|(c, _)| c.to == call.to)
+            .find_map(|(_, log)| {
+                function
+                    .decode_amounts(&log.raw_log)
+                    .map(|amounts| (log.log_index, amounts))
+            })?;
+
+        let coins = self
+            .pools
+            .get(&call.to)
+            .map(|pool| pool.coins.as_slice())
+            .unwrap_or_default();
+        let tokens = if function.event == LiquidityEvent::RemoveLiquidityOne {
+            // remove_liquidity_one_coin(_token_amount, i, _min_amount)
+            let i = call
+                .input
+                .get(36..68)
+                .map(|word| U256::from_big_endian(word).low_u64() as usize);
+            i.and_then(|i| coins.get(i)).cloned().into_iter().collect()
+        } else {
+            coins.to_vec()
+        };
+
+        let action = if function.event == LiquidityEvent::AddLiquidity {
+            AddLiquidity {
+                pool: call.to,
+                tokens,
+                amounts,
+            }
+            .into()
+        } else {
+            RemoveLiquidity {
+                pool: call.to,
+                tokens,
+                amounts,
+            }
+            .into()
+        };
+        Some(Action::with_logs(
+            action,
+            call.trace_address.clone(),
+            vec![log_index],
+        ))
+    }
+
+    /// Decodes a trade of the underlying coins of a lending pool or metapool.
+    ///
+    /// The underlying coins are moved through the lending protocol or the base
+    /// pool, so the trade is made from the pool's `TokenExchangeUnderlying`
+    /// event and needs the pool's coins to be known.
+    fn decode_exchange_underlying(
+        &self,
+        call: &InternalCall,
+        tx: &TransactionData,
+    ) -> Option<Action> {
+        let coins = &self.pools.get(&call.to)?.underlying_coins;
+        let (i, j, _, _) = self
+            .pool
+            .decode::<Exchange, _>("exchange_underlying", &call.input)
+            .ok()?;
+        let (_, log, exchange) = tx
+            .call_logs_decoded::<curvepool_mod::TokenExchangeUnderlyingFilter>(&call.trace_address)
+            .find(|(c, _, _)| c.to == call.to)?;
+
+        let action = Trade {
+            t1: Transfer {
+                from: call.from,
+                to: call.to,
+                amount: exchange.tokens_sold,
+                token: *coins.get(i as usize)?,
+            },
+            t2: Transfer {
+                from: call.to,
+                to: call.from,
+                amount: exchange.tokens_bought,
+                token: *coins.get(j as usize)?,
+            },
+        };
+        Some(Action::with_logs(
+            action.into(),
+            call.trace_address.clone(),
+            vec![log.log_index],
+        ))
+    }
+
     fn as_add_liquidity(&self, to: &Address, data: impl AsRef<[u8]>) -> Option<AddLiquidity> {
-        let tokens = self.pools.get(to)?;
+        let tokens = &self.pools.get(to)?.coins;
         // adapter for Curve's pool-specific abi decoding
         // TODO: Do we need to add the tripool?
         let amounts = match tokens.len() {
@@ -196,26 +596,22 @@ impl Curve {
         .ok()?;
 
         Some(AddLiquidity {
+            pool: *to,
             tokens: tokens.clone(),
             amounts,
         })
     }
 
-    pub async fn create<M: Middleware>(
-        provider: std::sync::Arc<M>,
-    ) -> Result<Self, ContractError<M>> {
-        let mut this = Self::new(vec![]);
-        let registry = CurveRegistry::new(*CURVE_REGISTRY, provider);
-
-        let pool_count = registry.pool_count().call().await?;
-        // TODO: Cache these locally.
-        for i in 0..pool_count.as_u64() {
-            let pool = registry.pool_list(i.into()).call().await?;
-            let tokens = registry.get_underlying_coins(pool).call().await?;
-            this.pools.insert(pool, tokens.to_vec());
-        }
-
-        Ok(this)
+    /// Instantiates Curve with all pools of the registry and the factory
+    ///
+    /// Prefer refreshing a saved `CurvePools` snapshot, this queries the
+    /// registry's whole history.
+    pub async fn create<M: Middleware + 'static>(
+        provider: Arc<M>,
+    ) -> Result<Self, CurvePoolsError<M>> {
+        let mut pools = CurvePools::default();
+        pools.refresh(provider).await?;
+        Ok(Self::new(pools.pools))
     }
 
     fn check(&self, call: &TraceCall) -> bool {
@@ -237,14 +633,15 @@ impl Curve {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::read_tx;
+    use crate::test_helpers::{addrs, calldata, mk_log, mk_trace, read_tx, transfer_log};
     use crate::{
+        addresses::parse_address,
         inspectors::ERC20,
         reducers::{ArbitrageReducer, TradeReducer},
         test_helpers::read_trace,
         Reducer, TxReducer,
     };
-    use ethers::providers::Provider;
+    use ethers::{contract::EthEvent, providers::Provider};
     use std::convert::TryFrom;
 
     #[tokio::test]
@@ -297,7 +694,8 @@ mod tests {
         inspector.inspect_tx(&mut tx);
 
         let arb = tx.actions().arbitrage().next().unwrap();
-        assert_eq!(arb.profit.to_string(), "45259140804");
+        // the USDC flashloan is repaid with a fee of 2 wei
+        assert_eq!(arb.profit.to_string(), "45259140802");
     }
 
     #[test]
@@ -314,4 +712,184 @@ mod tests {
             .unwrap();
         assert_eq!(arb.profit.to_string(), "45259140804");
     }
+
+    #[test]
+    fn snapshot() {
+        let pools = CurvePools::load("res/curve_pools.json").unwrap();
+        assert_eq!(pools.block, 11129944);
+        let json = serde_json::to_string(&pools).unwrap();
+        assert_eq!(serde_json::from_str::<CurvePools>(&json).unwrap(), pools);
+
+        let mut tx = read_tx("simple_curve_arb.data.json");
+        Curve::new(pools.pools).inspect_tx(&mut tx);
+
+        // USDC -> USDT in the BUSD pool and back in the Y pool
+        let usdc = parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let usdt = parse_address("0xdac17f958d2ee523a2206206994597c13d831ec7");
+        let trades = tx.actions().trades().collect::<Vec<_>>();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(
+            trades[0].t2.from,
+            parse_address("0x79a8c46dea5ada233abaffd40f3a0a2b1e5a4f27")
+        );
+        assert_eq!(trades[0].t1.token, usdc);
+        assert_eq!(trades[0].t1.amount, 4888682275029u64.into());
+        assert_eq!(trades[0].t2.token, usdt);
+        assert_eq!(trades[0].t2.amount, 4882040133814u64.into());
+        assert_eq!(
+            trades[1].t2.from,
+            parse_address("0x45f783cce6b7ff23b2ab2d70e416cdb7d6055f51")
+        );
+        assert_eq!(trades[1].t1.token, usdt);
+        assert_eq!(trades[1].t2.token, usdc);
+        assert_eq!(trades[1].t2.amount, 4933941415833u64.into());
+    }
+
+    #[test]
+    fn exchange_underlying() {
+        let addrs = addrs();
+        let [user, pool, dai, usdc] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let coins = CurveCoins::new(vec![addrs[4], addrs[5]], vec![dai, usdc]);
+        let mut inspector = MyInspector::new();
+        inspector.inspector = Curve::new(vec![(pool, coins)]);
+
+        let exchange = calldata(
+            "exchange_underlying(int128,int128,uint256,uint256)",
+            &[
+                Token::Int(0.into()),
+                Token::Int(1.into()),
+                Token::Uint(100.into()),
+                Token::Uint(0.into()),
+            ],
+        );
+        let traces = vec![
+            mk_trace(user, pool, exchange, vec![], 2),
+            mk_trace(
+                pool,
+                dai,
+                calldata(
+                    "transferFrom(address,address,uint256)",
+                    &[
+                        Token::Address(user),
+                        Token::Address(pool),
+                        Token::Uint(100.into()),
+                    ],
+                ),
+                vec![0],
+                0,
+            ),
+            mk_trace(
+                pool,
+                usdc,
+                calldata(
+                    "transfer(address,uint256)",
+                    &[Token::Address(user), Token::Uint(95.into())],
+                ),
+                vec![1],
+                0,
+            ),
+        ];
+        let logs = vec![
+            transfer_log(dai, user, pool, 100, 0),
+            transfer_log(usdc, pool, user, 95, 1),
+            mk_log(
+                pool,
+                vec![
+                    curvepool_mod::TokenExchangeUnderlyingFilter::signature(),
+                    user.into(),
+                ],
+                vec![
+                    Token::Int(0.into()),
+                    Token::Uint(100.into()),
+                    Token::Int(1.into()),
+                    Token::Uint(95.into()),
+                ],
+                2,
+            ),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        inspector.inspect_tx(&mut tx);
+
+        // the transfers of the underlying coins are part of the trade
+        assert_eq!(tx.actions().count(), 1);
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(trade.t1.token, dai);
+        assert_eq!(trade.t1.amount, 100.into());
+        assert_eq!(trade.t2.token, usdc);
+        assert_eq!(trade.t2.amount, 95.into());
+        assert_eq!(trade.t2.from, pool);
+    }
+
+    #[test]
+    fn liquidity() {
+        let addrs = addrs();
+        let [user, router, pool, tripool, coin0, coin1] =
+            [addrs[0], addrs[1], addrs[2], addrs[3], addrs[4], addrs[5]];
+        let mut inspector = MyInspector::new();
+        inspector.inspector = Curve::new(vec![(
+            pool,
+            CurveCoins::new(vec![coin0, coin1], vec![coin0, coin1]),
+        )]);
+
+        let amounts = |amounts: &[u64]| {
+            Token::FixedArray(amounts.iter().map(|x| Token::Uint((*x).into())).collect())
+        };
+        let add = calldata(
+            "add_liquidity(uint256[3],uint256)",
+            &[amounts(&[1, 2, 3]), Token::Uint(0.into())],
+        );
+        let remove = calldata(
+            "remove_liquidity_one_coin(uint256,int128,uint256)",
+            &[
+                Token::Uint(50.into()),
+                Token::Int(1.into()),
+                Token::Uint(0.into()),
+            ],
+        );
+        let traces = vec![
+            mk_trace(user, router, vec![0xde, 0xad, 0xbe, 0xef], vec![], 2),
+            mk_trace(router, tripool, add, vec![0], 0),
+            mk_trace(router, pool, remove, vec![1], 0),
+        ];
+        let logs = vec![
+            mk_log(
+                tripool,
+                vec![
+                    H256::from(keccak256(
+                        "AddLiquidity(address,uint256[3],uint256[3],uint256,uint256)",
+                    )),
+                    router.into(),
+                ],
+                vec![
+                    amounts(&[1, 2, 3]),
+                    amounts(&[0, 0, 0]),
+                    Token::Uint(6.into()),
+                    Token::Uint(6.into()),
+                ],
+                0,
+            ),
+            mk_log(
+                pool,
+                vec![
+                    H256::from(keccak256("RemoveLiquidityOne(address,uint256,uint256)")),
+                    router.into(),
+                ],
+                vec![Token::Uint(50.into()), Token::Uint(40.into())],
+                1,
+            ),
+        ];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        inspector.inspect_tx(&mut tx);
+
+        // the coins of pools that are not known are empty
+        let add = tx.actions().add_liquidity().next().unwrap();
+        assert_eq!(add.pool, tripool);
+        assert!(add.tokens.is_empty());
+        assert_eq!(add.amounts, vec![1.into(), 2.into(), 3.into()]);
+
+        let remove = tx.actions().remove_liquidity().next().unwrap();
+        assert_eq!(remove.pool, pool);
+        assert_eq!(remove.tokens, vec![coin1]);
+        assert_eq!(remove.amounts, vec![40.into()]);
+    }
 }
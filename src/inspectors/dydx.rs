@@ -0,0 +1,596 @@
+//! Inspector for dYdX's Solo Margin
+//!
+//! All interactions go through `operate`, which executes a list of actions
+//! on the sender's accounts. The call's actions are only used to classify the
+//! call, the amounts are taken from the events `SoloMargin` emits for each
+//! executed action, since the calldata may specify them relative to the
+//! account's balance.
+use crate::{
+    addresses::{parse_address, DYDX},
+    model::{CallClassification, EventLog, InternalCall},
+    types::{
+        actions::{FlashLoan, Liquidation, SpecificAction, Trade, Transfer},
+        Action, Protocol, TransactionData,
+    },
+    DefiProtocol, ProtocolContracts,
+};
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, CallType, H256, U256},
+    utils::{id, keccak256},
+};
+use once_cell::sync::Lazy;
+
+const OPERATE: &str =
+    "operate((address,uint256)[],(uint8,uint256,(bool,uint8,uint8,uint256),uint256,uint256,address,uint256,bytes)[])";
+
+/// `Types.BalanceUpdate`, the `(sign, value)` of the change in `Wei`
+/// followed by the `(sign, value)` of the new `Par` balance
+const BALANCE_UPDATE: &str = "((bool,uint256),(bool,uint128))";
+
+/// The tokens of the Solo Margin markets, indexed by their market id
+static MARKETS: Lazy<Vec<Address>> = Lazy::new(|| {
+    [
+        // WETH
+        "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+        // SAI
+        "0x89d24a6b4ccb1b6faa2625fe562bdd9a23260359",
+        // USDC
+        "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+        // DAI
+        "0x6b175474e89094c44da98b954eedeac495271d0f",
+    ]
+    .iter()
+    .map(|market| parse_address(market))
+    .collect()
+});
+
+/// The `Actions.ActionType`s of `operate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ActionType {
+    Deposit,
+    Withdraw,
+    Transfer,
+    Buy,
+    Sell,
+    Trade,
+    Liquidate,
+    Vaporize,
+    Call,
+}
+
+impl ActionType {
+    fn from_u8(action: u8) -> Option<Self> {
+        use ActionType::*;
+        [
+            Deposit, Withdraw, Transfer, Buy, Sell, Trade, Liquidate, Vaporize, Call,
+        ]
+        .get(action as usize)
+        .copied()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An inspector for dYdX's Solo Margin
+pub struct DyDx {
+    operate: [u8; 4],
+}
+
+impl Default for DyDx {
+    fn default() -> Self {
+        Self {
+            operate: id(OPERATE),
+        }
+    }
+}
+
+impl DyDx {
+    /// The types of the actions of an `operate` call
+    fn operate_actions(&self, call: &InternalCall) -> Option<Vec<ActionType>> {
+        if call.input.get(..4)? != self.operate {
+            return None;
+        }
+        let asset_amount = ParamType::Tuple(vec![
+            ParamType::Bool,
+            ParamType::Uint(8),
+            ParamType::Uint(8),
+            ParamType::Uint(256),
+        ]);
+        let action_args = ParamType::Tuple(vec![
+            ParamType::Uint(8),
+            ParamType::Uint(256),
+            asset_amount,
+            ParamType::Uint(256),
+            ParamType::Uint(256),
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::Bytes,
+        ]);
+        let account_info = ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(256)]);
+        let mut params = abi::decode(
+            &[
+                ParamType::Array(Box::new(account_info)),
+                ParamType::Array(Box::new(action_args)),
+            ],
+            call.input.get(4..)?,
+        )
+        .ok()?;
+        params
+            .pop()?
+            .into_array()?
+            .into_iter()
+            .map(|action| {
+                let action_type = action.into_tuple()?.into_iter().next()?.into_uint()?;
+                ActionType::from_u8(action_type.low_u32() as u8)
+            })
+            .collect()
+    }
+
+    /// Decodes all the actions of an `operate` call from the emitted events
+    fn decode_operation(&self, call: &InternalCall, tx: &TransactionData) -> Vec<Action> {
+        let logs = tx
+            .call_logs(&call.trace_address)
+            .filter(|(_, log)| log.address == call.to)
+            .map(|(_, log)| log)
+            .collect::<Vec<_>>();
+        let mk_action = |action: SpecificAction, logs: Vec<U256>| {
+            Action::with_logs(action, call.trace_address.clone(), logs)
+        };
+
+        let mut actions = Vec::new();
+        let mut deposits = logs
+            .iter()
+            .filter_map(|log| BalanceChange::decode(log, "LogDeposit"))
+            .collect::<Vec<_>>();
+        for log in &logs {
+            if let Some(withdraw) = BalanceChange::decode(log, "LogWithdraw") {
+                // a flash loan is a withdrawal that is deposited again
+                // within the same operation
+                let repayment = deposits.iter().position(|deposit| {
+                    deposit.log_index > withdraw.log_index
+                        && deposit.owner == withdraw.owner
+                        && deposit.market == withdraw.market
+                        && deposit.amount >= withdraw.amount
+                });
+                if let (Some(repayment), Some(token)) = (repayment, withdraw.token()) {
+                    let repayment = deposits.remove(repayment);
+                    let loan = FlashLoan {
+                        lender: call.to,
+                        recipient: withdraw.counterparty,
+                        token,
+                        amount: withdraw.amount,
+                        fee: repayment.amount - withdraw.amount,
+                    };
+                    actions.push(mk_action(
+                        loan.into(),
+                        vec![withdraw.log_index, repayment.log_index],
+                    ));
+                }
+            } else if let Some(trade) = LogTrade::decode(log) {
+                if let Some(trade) = trade.to_trade() {
+                    actions.push(mk_action(trade.into(), vec![log.log_index]));
+                }
+            } else if let Some(liquidation) = LogLiquidate::decode(log) {
+                if let Some(liquidation) = liquidation.to_liquidation() {
+                    actions.push(mk_action(liquidation.into(), vec![log.log_index]));
+                }
+            }
+        }
+        actions
+    }
+}
+
+impl DefiProtocol for DyDx {
+    fn base_contracts(&self) -> ProtocolContracts {
+        ProtocolContracts::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::DyDx
+    }
+
+    fn is_protocol(&self, call: &InternalCall) -> Option<Option<Protocol>> {
+        if call.to == *DYDX {
+            Some(Some(Protocol::DyDx))
+        } else {
+            None
+        }
+    }
+
+    fn is_protocol_event(&self, log: &EventLog) -> bool {
+        log.address == *DYDX
+    }
+
+    fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        self.decode_operation(call, tx).into_iter().next()
+    }
+
+    fn classify(
+        &self,
+        call: &InternalCall,
+    ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        if call.call_type == CallType::DelegateCall {
+            return None;
+        }
+        let actions = self.operate_actions(call)?;
+        let classification = if actions.contains(&ActionType::Liquidate) {
+            CallClassification::Liquidation
+        } else if actions.contains(&ActionType::Trade) {
+            CallClassification::Swap
+        } else if actions
+            .iter()
+            .skip_while(|action| **action != ActionType::Withdraw)
+            .any(|action| *action == ActionType::Deposit)
+        {
+            CallClassification::FlashLoan
+        } else {
+            return None;
+        };
+        Some((classification, None))
+    }
+
+    /// A single `operate` call may consist of several actions, so this can
+    /// not go through `decode_call_action`.
+    fn inspect_tx(&self, tx: &mut TransactionData) {
+        let mut operations = Vec::new();
+        for call in tx.calls_mut() {
+            if call.to != *DYDX {
+                continue;
+            }
+            if let Some((classification, _)) = self.classify(call) {
+                call.protocol = Some(Protocol::DyDx);
+                call.classification = classification;
+                operations.push(call.trace_address.clone());
+            }
+        }
+
+        for trace_address in operations {
+            let actions = match tx.get_call(&trace_address) {
+                Some(call) => self.decode_operation(call, tx),
+                None => continue,
+            };
+            tx.extend_actions(actions.into_iter());
+        }
+    }
+}
+
+/// Verifies the event's signature and decodes its data
+fn decode_event(log: &EventLog, signature: &str, data: &[ParamType]) -> Option<Vec<Token>> {
+    if log.signature != H256::from(keccak256(signature)) {
+        return None;
+    }
+    abi::decode(data, &log.raw_log.data).ok()
+}
+
+/// The params of a `Types.BalanceUpdate`, flattened
+fn balance_update() -> Vec<ParamType> {
+    vec![
+        ParamType::Bool,
+        ParamType::Uint(256),
+        ParamType::Bool,
+        ParamType::Uint(128),
+    ]
+}
+
+/// The `(positive, value)` of the `Wei` change of the next balance update
+fn next_wei(tokens: &mut impl Iterator<Item = Token>) -> Option<(bool, U256)> {
+    let positive = tokens.next()?.into_bool()?;
+    let value = tokens.next()?.into_uint()?;
+    // skip the new `Par` balance
+    tokens.nth(1)?;
+    Some((positive, value))
+}
+
+fn market_token(market: U256) -> Option<Address> {
+    if market >= MARKETS.len().into() {
+        return None;
+    }
+    MARKETS.get(market.as_usize()).copied()
+}
+
+fn topic_address(log: &EventLog, idx: usize) -> Option<Address> {
+    log.raw_log
+        .topics
+        .get(idx)
+        .map(|topic| Address::from(*topic))
+}
+
+/// A `LogDeposit` or `LogWithdraw` of an account
+#[derive(Debug, Clone)]
+struct BalanceChange {
+    log_index: U256,
+    owner: Address,
+    market: U256,
+    amount: U256,
+    /// The `from` of a deposit, the `to` of a withdrawal
+    counterparty: Address,
+}
+
+impl BalanceChange {
+    fn decode(log: &EventLog, name: &str) -> Option<Self> {
+        let signature = format!(
+            "{}(address,uint256,uint256,{},address)",
+            name, BALANCE_UPDATE
+        );
+        let mut data = vec![ParamType::Uint(256), ParamType::Uint(256)];
+        data.extend(balance_update());
+        data.push(ParamType::Address);
+        let mut tokens = decode_event(log, &signature, &data)?.into_iter().skip(1);
+
+        let market = tokens.next()?.into_uint()?;
+        let (_, amount) = next_wei(&mut tokens)?;
+        Some(Self {
+            log_index: log.log_index,
+            owner: topic_address(log, 1)?,
+            market,
+            amount,
+            counterparty: tokens.next()?.into_address()?,
+        })
+    }
+
+    fn token(&self) -> Option<Address> {
+        market_token(self.market)
+    }
+}
+
+/// A `LogTrade` between two accounts, matched by the maker's `autoTrader`
+#[derive(Debug, Clone)]
+struct LogTrade {
+    taker: Address,
+    maker: Address,
+    input_market: U256,
+    output_market: U256,
+    taker_input: (bool, U256),
+    taker_output: (bool, U256),
+}
+
+impl LogTrade {
+    fn decode(log: &EventLog) -> Option<Self> {
+        let signature = format!(
+            "LogTrade(address,uint256,address,uint256,uint256,uint256,{0},{0},{0},{0},address)",
+            BALANCE_UPDATE
+        );
+        let mut data = vec![ParamType::Uint(256); 4];
+        for _ in 0..4 {
+            data.extend(balance_update());
+        }
+        data.push(ParamType::Address);
+        // skip the account numbers
+        let mut tokens = decode_event(log, &signature, &data)?.into_iter().skip(2);
+
+        Some(Self {
+            taker: topic_address(log, 1)?,
+            maker: topic_address(log, 2)?,
+            input_market: tokens.next()?.into_uint()?,
+            output_market: tokens.next()?.into_uint()?,
+            taker_input: next_wei(&mut tokens)?,
+            taker_output: next_wei(&mut tokens)?,
+        })
+    }
+
+    /// The trade from the taker's perspective, who pays with the market of
+    /// its negative balance update
+    fn to_trade(&self) -> Option<Trade> {
+        let input = (market_token(self.input_market)?, self.taker_input);
+        let output = (market_token(self.output_market)?, self.taker_output);
+        let ((paid_token, (_, paid)), (received_token, (_, received))) =
+            match (self.taker_input.0, self.taker_output.0) {
+                (false, true) => (input, output),
+                (true, false) => (output, input),
+                _ => return None,
+            };
+        Some(Trade {
+            t1: Transfer {
+                from: self.taker,
+                to: self.maker,
+                amount: paid,
+                token: paid_token,
+            },
+            t2: Transfer {
+                from: self.maker,
+                to: self.taker,
+                amount: received,
+                token: received_token,
+            },
+        })
+    }
+}
+
+/// A `LogLiquidate` of the liquid account by the solid account
+#[derive(Debug, Clone)]
+struct LogLiquidate {
+    solid: Address,
+    liquid: Address,
+    held_market: U256,
+    owed_market: U256,
+    /// The collateral the solid account received
+    solid_held: U256,
+    /// The debt the solid account repaid
+    solid_owed: U256,
+}
+
+impl LogLiquidate {
+    fn decode(log: &EventLog) -> Option<Self> {
+        let signature = format!(
+            "LogLiquidate(address,uint256,address,uint256,uint256,uint256,{0},{0},{0},{0})",
+            BALANCE_UPDATE
+        );
+        let mut data = vec![ParamType::Uint(256); 4];
+        for _ in 0..4 {
+            data.extend(balance_update());
+        }
+        // skip the account numbers
+        let mut tokens = decode_event(log, &signature, &data)?.into_iter().skip(2);
+
+        Some(Self {
+            solid: topic_address(log, 1)?,
+            liquid: topic_address(log, 2)?,
+            held_market: tokens.next()?.into_uint()?,
+            owed_market: tokens.next()?.into_uint()?,
+            solid_held: next_wei(&mut tokens)?.1,
+            solid_owed: next_wei(&mut tokens)?.1,
+        })
+    }
+
+    fn to_liquidation(&self) -> Option<Liquidation> {
+        Some(Liquidation {
+            sent_token: market_token(self.owed_market)?,
+            sent_amount: self.solid_owed,
+            received_token: market_token(self.held_market)?,
+            received_amount: self.solid_held,
+            from: self.solid,
+            liquidated_user: self.liquid,
+            collateral_type: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::lookup,
+        inspectors::{Aave, Uniswap, ERC20},
+        reducers::{ArbitrageReducer, LiquidationReducer, TradeReducer},
+        test_helpers::*,
+        BatchInspector,
+    };
+
+    /// The inspectors of the loan's liquidation with dYdX added
+    fn inspector() -> BatchInspector {
+        BatchInspector::new(
+            vec![
+                Box::new(DyDx::default()),
+                Box::new(Aave::new()),
+                Box::new(Uniswap::default()),
+                Box::new(ERC20::new()),
+            ],
+            vec![
+                Box::new(TradeReducer),
+                Box::new(LiquidationReducer),
+                Box::new(ArbitrageReducer),
+            ],
+        )
+    }
+
+    /// A `Types.BalanceUpdate` with a `Wei` change of `amount`
+    fn update(positive: bool, amount: u64) -> Vec<Token> {
+        vec![
+            Token::Bool(positive),
+            Token::Uint(amount.into()),
+            Token::Bool(true),
+            Token::Uint(0.into()),
+        ]
+    }
+
+    /// The `ActionArgs` of `operate` with the given action type
+    fn action_args(action: ActionType) -> Token {
+        Token::Tuple(vec![
+            Token::Uint((action as u8).into()),
+            Token::Uint(0.into()),
+            Token::Tuple(vec![
+                Token::Bool(true),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ]),
+            Token::Uint(0.into()),
+            Token::Uint(0.into()),
+            Token::Address(Address::zero()),
+            Token::Uint(0.into()),
+            Token::Bytes(vec![]),
+        ])
+    }
+
+    #[test]
+    // http://etherscan.io/tx/0x0e0e7c690589d9b94c3fbc4bae8abb4c5cac5c965abbb5bf1533e9f546b10b92
+    fn flash_loan() {
+        let mut tx = read_tx("dydx_loan.data.json");
+        DyDx::default().inspect_tx(&mut tx);
+
+        let call = tx.get_call(&[7]).unwrap();
+        assert_eq!(call.classification, CallClassification::FlashLoan);
+        let loan = tx.actions().flash_loans().next().unwrap();
+        assert_eq!(loan.lender, *DYDX);
+        assert_eq!(
+            loan.recipient,
+            parse_address("0xe0478bb78c8fe80ee74ca2155509e7927cc812ec")
+        );
+        assert_eq!(loan.token, MARKETS[3]);
+        assert_eq!(
+            loan.amount,
+            U256::from_dec_str("437823029928112300000").unwrap()
+        );
+        assert_eq!(loan.fee, 2.into());
+    }
+
+    #[test]
+    // http://etherscan.io/tx/0x0e0e7c690589d9b94c3fbc4bae8abb4c5cac5c965abbb5bf1533e9f546b10b92
+    fn liquidation_with_flash_loan() {
+        let mut tx = read_tx("dydx_loan.data.json");
+        let inspector = inspector();
+        inspector.inspect_tx(&mut tx);
+        inspector.reduce_tx(&mut tx);
+
+        let liquidation = tx.actions().profitable_liquidations().next().unwrap();
+        assert_eq!(
+            liquidation.profit,
+            U256::from_dec_str("18789801420638046861").unwrap()
+        );
+        // the DAI used for the liquidation is borrowed from dYdX
+        let loan = tx.actions().flash_loans().next().unwrap();
+        assert_eq!(lookup(loan.token), "DAI");
+        assert_eq!(loan.fee, 2.into());
+    }
+
+    #[test]
+    fn liquidate() {
+        let addrs = addrs();
+        let [bot, user] = [addrs[0], addrs[1]];
+        let input = calldata(
+            OPERATE,
+            &[
+                Token::Array(vec![Token::Tuple(vec![
+                    Token::Address(bot),
+                    Token::Uint(0.into()),
+                ])]),
+                Token::Array(vec![action_args(ActionType::Liquidate)]),
+            ],
+        );
+        let traces = vec![mk_trace(bot, *DYDX, input, vec![], 0)];
+        let mut data = vec![Token::Uint(0.into()); 2];
+        // held: WETH, owed: USDC
+        data.extend(vec![Token::Uint(0.into()), Token::Uint(2.into())]);
+        data.extend(update(true, 10));
+        data.extend(update(false, 5000));
+        data.extend(update(false, 10));
+        data.extend(update(true, 5000));
+        let logs = vec![mk_log(
+            *DYDX,
+            vec![
+                H256::from(keccak256(format!(
+                    "LogLiquidate(address,uint256,address,uint256,uint256,uint256,{0},{0},{0},{0})",
+                    BALANCE_UPDATE
+                ))),
+                H256::from(bot),
+                H256::from(user),
+            ],
+            data,
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        DyDx::default().inspect_tx(&mut tx);
+
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!((liquidation.from, liquidation.liquidated_user), (bot, user));
+        assert_eq!(
+            (liquidation.sent_token, liquidation.sent_amount),
+            (MARKETS[2], 5000.into())
+        );
+        assert_eq!(
+            (liquidation.received_token, liquidation.received_amount),
+            (MARKETS[0], 10.into())
+        );
+        assert!(tx.protocols().contains(&Protocol::DyDx));
+    }
+}
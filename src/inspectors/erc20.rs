@@ -1,8 +1,9 @@
 use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::{Action, Protocol, TransactionData};
 use crate::{
-    addresses::{ETH, WETH},
+    addresses::ETH,
     inspect_tx,
+    network::network,
     types::{
         actions::{Deposit, SpecificAction, Transfer, Withdrawal},
         Classification, Inspection,
@@ -130,7 +131,7 @@ impl DefiProtocol for ERC20 {
                     amount: call.value,
                 })),
             ))
-        } else if call.value > 0.into() && call.from != *WETH {
+        } else if call.value > 0.into() && call.from != network().wrapped_native {
             // ETH transfer
             Some((
                 CallClassification::Transfer,
@@ -226,7 +227,7 @@ impl ERC20 {
                 from: trace_call.from,
                 amount: trace_call.value,
             }))
-        } else if trace_call.value > 0.into() && trace_call.from != *WETH {
+        } else if trace_call.value > 0.into() && trace_call.from != network().wrapped_native {
             // ETH transfer
             Some(SpecificAction::Transfer(Transfer {
                 from: trace_call.from,
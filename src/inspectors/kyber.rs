@@ -0,0 +1,297 @@
+use crate::{
+    model::{CallClassification, EventLog, InternalCall},
+    types::{
+        actions::{SpecificAction, Trade, Transfer},
+        Action, Protocol, TransactionData,
+    },
+    DefiProtocol, ProtocolContracts,
+};
+use ethers::{
+    abi::{self, ParamType},
+    contract::{abigen, EthEvent, EthLogDecode},
+    types::{Address, CallType, U256},
+    utils::id,
+};
+
+abigen!(
+    KyberNetworkProxy,
+    r#"[
+        event ExecuteTrade(address indexed trader, address src, address dest, uint256 actualSrcAmount, uint256 actualDestAmount)
+    ]"#
+);
+
+/// The trading functions of the proxy, all of which start with
+/// `(src, srcAmount, dest, destAddress, ..)`
+const TRADE_FUNCTIONS: [&str; 3] = [
+    "trade(address,uint256,address,address,uint256,uint256,address)",
+    "tradeWithHint(address,uint256,address,address,uint256,uint256,address,bytes)",
+    "tradeWithHintAndFee(address,uint256,address,address,uint256,uint256,address,uint256,bytes)",
+];
+
+#[derive(Debug, Clone)]
+/// An inspector for trades via the Kyber Network Proxy
+pub struct Kyber {
+    selectors: Vec<[u8; 4]>,
+}
+
+impl Default for Kyber {
+    fn default() -> Self {
+        Self {
+            selectors: TRADE_FUNCTIONS.iter().map(|f| id(f)).collect(),
+        }
+    }
+}
+
+impl DefiProtocol for Kyber {
+    fn base_contracts(&self) -> ProtocolContracts {
+        ProtocolContracts::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Kyber
+    }
+
+    fn is_protocol_event(&self, log: &EventLog) -> bool {
+        ExecuteTradeFilter::decode_log(&log.raw_log).is_ok()
+            || KatalystExecuteTradeFilter::decode_log(&log.raw_log).is_ok()
+    }
+
+    fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        if call.classification != CallClassification::Swap {
+            return None;
+        }
+        // (src, srcAmount, dest, destAddress)
+        let dest_address = abi::decode(
+            &[
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Address,
+            ],
+            call.input.get(4..)?,
+        )
+        .ok()?
+        .pop()?
+        .into_address()?;
+
+        let (log, trader, src, dest, src_amount, dest_amount) =
+            tx.call_logs(&call.trace_address).find_map(|(_, log)| {
+                if let Ok(trade) = KatalystExecuteTradeFilter::decode_log(&log.raw_log) {
+                    Some((
+                        log,
+                        trade.trader,
+                        trade.src,
+                        trade.dest,
+                        trade.actual_src_amount,
+                        trade.actual_dest_amount,
+                    ))
+                } else {
+                    ExecuteTradeFilter::decode_log(&log.raw_log)
+                        .ok()
+                        .map(|trade| {
+                            (
+                                log,
+                                trade.trader,
+                                trade.src,
+                                trade.dest,
+                                trade.actual_src_amount,
+                                trade.actual_dest_amount,
+                            )
+                        })
+                }
+            })?;
+
+        let action = Trade {
+            t1: Transfer {
+                from: trader,
+                to: call.to,
+                amount: src_amount,
+                token: src,
+            },
+            t2: Transfer {
+                from: call.to,
+                to: dest_address,
+                amount: dest_amount,
+                token: dest,
+            },
+        };
+        Some(Action::with_logs(
+            action.into(),
+            call.trace_address.clone(),
+            vec![log.log_index],
+        ))
+    }
+
+    fn classify(
+        &self,
+        call: &InternalCall,
+    ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        if call.call_type == CallType::DelegateCall {
+            return None;
+        }
+        let selector = call.input.get(..4)?;
+        if self.selectors.iter().any(|s| selector == s) {
+            Some((CallClassification::Swap, None))
+        } else {
+            None
+        }
+    }
+}
+
+// this is a hack to overcome the `ethers` generated paths
+use ethers::contract as ethers_contract;
+use ethers::core as ethers_core;
+#[derive(Debug, Clone, EthEvent)]
+#[ethevent(
+    name = "ExecuteTrade",
+    abi = "ExecuteTrade(address,address,address,address,uint256,uint256,address,uint256)"
+)]
+/// The `ExecuteTrade` event of the Katalyst proxy, which includes the
+/// receiver and the platform fee
+pub struct KatalystExecuteTradeFilter {
+    #[ethevent(indexed)]
+    pub trader: Address,
+    pub src: Address,
+    pub dest: Address,
+    pub dest_address: Address,
+    pub actual_src_amount: U256,
+    pub actual_dest_amount: U256,
+    pub platform_wallet: Address,
+    pub platform_fee_bps: U256,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addresses::{parse_address, ETH},
+        test_helpers::*,
+    };
+    use ethers::{abi::Token, types::H256};
+
+    #[test]
+    // https://etherscan.io/tx/0x93690c02fc4d58734225d898ea4091df104040450c0f204b6bf6f6850ac4602f
+    // An Aave liquidation bot swaps the seized USDC for YFI on the Katalyst proxy
+    fn liquidator_trade() {
+        let mut tx = read_tx("kyber_trade.data.json");
+        Kyber::default().inspect_tx(&mut tx);
+
+        let bot = parse_address("0x80119949f52cb9bf18ecf259e3c3b59f0e5e5a5b");
+        let proxy = parse_address("0x9aab3f75489902f3a48495025729a0af77d4b11e");
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(
+            trade.t1,
+            Transfer {
+                from: bot,
+                to: proxy,
+                amount: 99139976158u64.into(),
+                token: parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            }
+        );
+        assert_eq!(
+            trade.t2,
+            Transfer {
+                from: proxy,
+                to: bot,
+                amount: 5722594594602333796u64.into(),
+                token: parse_address("0x0bc529c00c6401aef6d220be8c6ea1667f6ad93e"),
+            }
+        );
+
+        let call = tx.get_call(&[8]).unwrap();
+        assert_eq!(call.classification, CallClassification::Swap);
+        assert_eq!(call.protocol, Some(Protocol::Kyber));
+    }
+
+    #[test]
+    fn trade_with_hint() {
+        let addrs = addrs();
+        let [trader, receiver, dai] = [addrs[0], addrs[1], addrs[2]];
+        let proxy = parse_address("0x818e6fecd516ecc3849daf6845e3ec868087b755");
+        let input = calldata(
+            TRADE_FUNCTIONS[1],
+            &[
+                Token::Address(dai),
+                Token::Uint(100.into()),
+                Token::Address(*ETH),
+                Token::Address(receiver),
+                Token::Uint(U256::MAX),
+                Token::Uint(0.into()),
+                Token::Address(Address::zero()),
+                Token::Bytes(vec![]),
+            ],
+        );
+        let traces = vec![
+            mk_trace(trader, proxy, input, vec![], 1),
+            // the proxy's network contract
+            mk_trace(proxy, addrs[3], vec![0xde, 0xad, 0xbe, 0xef], vec![0], 0),
+        ];
+        let logs = vec![mk_log(
+            proxy,
+            vec![ExecuteTradeFilter::signature(), H256::from(trader)],
+            vec![
+                Token::Address(dai),
+                Token::Address(*ETH),
+                Token::Uint(100.into()),
+                Token::Uint(2.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Kyber::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(
+            (trade.t1.from, trade.t1.token, trade.t1.amount),
+            (trader, dai, 100.into())
+        );
+        assert_eq!(
+            (trade.t2.to, trade.t2.token, trade.t2.amount),
+            (receiver, *ETH, 2.into())
+        );
+        assert!(tx.protocols().contains(&Protocol::Kyber));
+    }
+
+    #[test]
+    fn katalyst_trade() {
+        let addrs = addrs();
+        let [trader, dai, usdc] = [addrs[0], addrs[1], addrs[2]];
+        let proxy = parse_address("0x9aab3f75489902f3a48495025729a0af77d4b11e");
+        let input = calldata(
+            TRADE_FUNCTIONS[0],
+            &[
+                Token::Address(dai),
+                Token::Uint(100.into()),
+                Token::Address(usdc),
+                Token::Address(trader),
+                Token::Uint(U256::MAX),
+                Token::Uint(0.into()),
+                Token::Address(Address::zero()),
+            ],
+        );
+        let traces = vec![mk_trace(trader, proxy, input, vec![], 0)];
+        let logs = vec![mk_log(
+            proxy,
+            vec![KatalystExecuteTradeFilter::signature(), H256::from(trader)],
+            vec![
+                Token::Address(dai),
+                Token::Address(usdc),
+                Token::Address(trader),
+                Token::Uint(100.into()),
+                Token::Uint(99.into()),
+                Token::Address(Address::zero()),
+                Token::Uint(0.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Kyber::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (dai, 100.into()));
+        assert_eq!(
+            (trade.t2.to, trade.t2.token, trade.t2.amount),
+            (trader, usdc, 99.into())
+        );
+    }
+}
@@ -0,0 +1,701 @@
+//! Inspector for MakerDAO liquidations
+//!
+//! Vaults (`urns`) are liquidated with `Cat.bite` or `Dog.bark`, which move
+//! the urn's collateral and debt into an auction of its collateral type
+//! (`ilk`). Keepers bid on `Flipper` auctions with `tend` and `dent` and
+//! settle them with `deal`, `Clipper` auctions are filled with `take`. There
+//! is an auction contract per collateral type, so their calls are identified
+//! by their selectors and the events they emit or the `Vat` calls they make.
+//! The surplus (`Flapper`) and debt (`Flopper`) auctions share the selectors
+//! but not the `Vat` calls of a `Flipper`, so calls are only classified once
+//! they decode.
+//!
+//! Only the fills are `Liquidation`s, starting an auction is an
+//! `AuctionKick` that moves no funds of the keeper.
+use crate::{
+    addresses::{parse_address, DAI, MAKER_VAT},
+    model::{CallClassification, EventLog, InternalCall},
+    types::{
+        actions::{AuctionBid, AuctionKick, Liquidation, SpecificAction},
+        Action, Protocol, TransactionData,
+    },
+    DefiProtocol, ProtocolContracts,
+};
+use ethers::{
+    abi::{self, ParamType},
+    contract::{abigen, EthLogDecode},
+    types::{Address, CallType, U256},
+    utils::id,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+abigen!(
+    MakerLiquidations,
+    r#"[
+        event Bite(bytes32 indexed ilk, address indexed urn, uint256 ink, uint256 art, uint256 tab, address flip, uint256 id)
+        event Bark(bytes32 indexed ilk, address indexed urn, uint256 ink, uint256 art, uint256 due, address clip, uint256 indexed id)
+        event Take(uint256 indexed id, uint256 max, uint256 price, uint256 owe, uint256 tab, uint256 lot, address indexed usr)
+    ]"#
+);
+
+const CAT_BITE: &str = "bite(bytes32,address)";
+const DOG_BARK: &str = "bark(bytes32,address,address)";
+const FLIPPER_TEND: &str = "tend(uint256,uint256,uint256)";
+const FLIPPER_DENT: &str = "dent(uint256,uint256,uint256)";
+const FLIPPER_DEAL: &str = "deal(uint256)";
+const CLIPPER_TAKE: &str = "take(uint256,uint256,uint256,address,bytes)";
+const VAT_FLUX: &str = "flux(bytes32,address,address,uint256)";
+const VAT_MOVE: &str = "move(address,address,uint256)";
+const VAT_SLIP: &str = "slip(bytes32,address,int256)";
+const ERC20_TRANSFER: &str = "transfer(address,uint256)";
+const ERC20_TRANSFER_FROM: &str = "transferFrom(address,address,uint256)";
+
+/// DAI amounts in the `Vat` are `rad`s, which have 27 more decimals than DAI
+static RAY: Lazy<U256> = Lazy::new(|| U256::exp10(27));
+
+/// The collateral token of the mainnet collateral types, the token of any
+/// other collateral type is read from its `GemJoin`
+const ILKS: &[(&str, &str)] = &[
+    ("ETH-A", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+    ("ETH-B", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+    ("ETH-C", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+    ("BAT-A", "0x0d8775f648430679a709e98d2b0cb6250d2887ef"),
+    ("USDC-A", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+    ("USDC-B", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+    ("WBTC-A", "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"),
+    ("TUSD-A", "0x0000000000085d4780b73119b644ae5ecd22b376"),
+    ("KNC-A", "0xdd974d5c2e2928dea5f71b9825b8b646686bd200"),
+    ("ZRX-A", "0xe41d2489571d322189246dafa5ebde1f4699f498"),
+    ("MANA-A", "0x0f5d2fb29fb7d3cfee444a200298f468908cc942"),
+    ("USDT-A", "0xdac17f958d2ee523a2206206994597c13d831ec7"),
+    ("COMP-A", "0xc00e94cb662c3520282e6f5717214004a7f26888"),
+    ("LRC-A", "0xbbbbca6a901c926f240b89eacb641d8aec7aeafd"),
+    ("LINK-A", "0x514910771af9ca656af840dff83e8264ecf986ca"),
+    ("BAL-A", "0xba100000625a3754423978a60c9317c58a424e3d"),
+    ("YFI-A", "0x0bc529c00c6401aef6d220be8c6ea1667f6ad93e"),
+    ("UNI-A", "0x1f9840a85d5af5bf1d1762f925bdaddc4201f984"),
+    ("RENBTC-A", "0xeb4c2781e4eba804ce9a9803c67d0893436bb27d"),
+    ("AAVE-A", "0x7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9"),
+];
+
+/// The `bytes32` identifier of a collateral type
+fn ilk(name: &str) -> [u8; 32] {
+    let mut ilk = [0u8; 32];
+    ilk[..name.len()].copy_from_slice(name.as_bytes());
+    ilk
+}
+
+/// The name of a collateral type
+fn ilk_name(ilk: &[u8; 32]) -> String {
+    String::from_utf8_lossy(ilk)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+#[derive(Debug, Clone)]
+/// An inspector for MakerDAO's vault liquidations and collateral auctions
+pub struct Maker {
+    /// The collateral token of each collateral type
+    gems: HashMap<[u8; 32], Address>,
+    bite: [u8; 4],
+    bark: [u8; 4],
+    tend: [u8; 4],
+    dent: [u8; 4],
+    deal: [u8; 4],
+    take: [u8; 4],
+    flux: [u8; 4],
+    mv: [u8; 4],
+    slip: [u8; 4],
+    transfer: [u8; 4],
+    transfer_from: [u8; 4],
+}
+
+impl Default for Maker {
+    fn default() -> Self {
+        Self {
+            gems: ILKS
+                .iter()
+                .map(|(name, gem)| (ilk(name), parse_address(gem)))
+                .collect(),
+            bite: id(CAT_BITE),
+            bark: id(DOG_BARK),
+            tend: id(FLIPPER_TEND),
+            dent: id(FLIPPER_DENT),
+            deal: id(FLIPPER_DEAL),
+            take: id(CLIPPER_TAKE),
+            flux: id(VAT_FLUX),
+            mv: id(VAT_MOVE),
+            slip: id(VAT_SLIP),
+            transfer: id(ERC20_TRANSFER),
+            transfer_from: id(ERC20_TRANSFER_FROM),
+        }
+    }
+}
+
+impl Maker {
+    /// The collateral token of the collateral type, if known
+    pub fn gem(&self, ilk: &[u8; 32]) -> Option<Address> {
+        self.gems.get(ilk).copied()
+    }
+
+    /// The collateral token of the collateral type, read from the `GemJoin`
+    /// that moved it in or out of the `Vat` during the transaction if the
+    /// collateral type is not known
+    fn collateral(&self, ilk: &[u8; 32], tx: &TransactionData) -> Option<Address> {
+        self.gem(ilk).or_else(|| {
+            // the join `slip`s the collateral in the `Vat` and transfers the token
+            let join = tx
+                .all_calls()
+                .find(|c| {
+                    c.to == *MAKER_VAT
+                        && c.input.get(..4) == Some(&self.slip[..])
+                        && c.input.get(4..36) == Some(&ilk[..])
+                })?
+                .from;
+            tx.all_calls()
+                .find(|c| {
+                    c.from == join
+                        && c.to != *MAKER_VAT
+                        && [self.transfer, self.transfer_from]
+                            .iter()
+                            .any(|s| c.input.get(..4) == Some(&s[..]))
+                })
+                .map(|c| c.to)
+        })
+    }
+
+    /// The `(ilk, dst, wad)` of the collateral the auction moved with
+    /// `Vat.flux` during the call
+    fn vat_flux(
+        &self,
+        call: &InternalCall,
+        tx: &TransactionData,
+    ) -> Option<([u8; 32], Address, U256)> {
+        tx.subcalls(&call.trace_address)
+            .filter(|c| c.to == *MAKER_VAT && c.from == call.to)
+            .find_map(|c| {
+                if c.input.get(..4)? != self.flux {
+                    return None;
+                }
+                let mut params = abi::decode(
+                    &[
+                        ParamType::FixedBytes(32),
+                        ParamType::Address,
+                        ParamType::Address,
+                        ParamType::Uint(256),
+                    ],
+                    c.input.get(4..)?,
+                )
+                .ok()?
+                .into_iter();
+                let mut ilk = [0u8; 32];
+                ilk.copy_from_slice(&params.next()?.into_fixed_bytes()?);
+                let dst = params.nth(1)?.into_address()?;
+                let wad = params.next()?.into_uint()?;
+                Some((ilk, dst, wad))
+            })
+    }
+
+    /// The urn's collateral and debt that `bite` or `bark` put up for auction
+    fn decode_kick(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let (log, kick) = tx
+            .call_logs(&call.trace_address)
+            .filter(|(_, log)| log.address == call.to)
+            .find_map(|(_, log)| {
+                let kick = if let Ok(bite) = BiteFilter::decode_log(&log.raw_log) {
+                    AuctionKick {
+                        auction: bite.flip,
+                        id: bite.id,
+                        kicker: call.from,
+                        liquidated_user: bite.urn,
+                        collateral_type: ilk_name(&bite.ilk),
+                        lot: bite.ink,
+                        debt: bite.tab / *RAY,
+                    }
+                } else {
+                    let bark = BarkFilter::decode_log(&log.raw_log).ok()?;
+                    AuctionKick {
+                        auction: bark.clip,
+                        id: bark.id,
+                        kicker: call.from,
+                        liquidated_user: bark.urn,
+                        collateral_type: ilk_name(&bark.ilk),
+                        lot: bark.ink,
+                        debt: bark.due / *RAY,
+                    }
+                };
+                Some((log, kick))
+            })?;
+        Some(Action::with_logs(
+            kick.into(),
+            call.trace_address.clone(),
+            vec![log.log_index],
+        ))
+    }
+
+    /// The DAI the keeper paid for the collateral it took from a `Clipper`
+    fn decode_take(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let (_, log, take) = tx
+            .call_logs_decoded::<TakeFilter>(&call.trace_address)
+            .find(|(_, log, _)| log.address == call.to)?;
+        let (ilk, _, slice) = self.vat_flux(call, tx)?;
+        let liquidation = Liquidation {
+            sent_token: *DAI,
+            sent_amount: take.owe / *RAY,
+            received_token: self.collateral(&ilk, tx)?,
+            received_amount: slice,
+            from: call.from,
+            liquidated_user: take.usr,
+            collateral_type: Some(ilk_name(&ilk)),
+        };
+        Some(Action::with_logs(
+            liquidation.into(),
+            call.trace_address.clone(),
+            vec![log.log_index],
+        ))
+    }
+
+    /// The collateral the winner of a `Flipper` auction receives for its
+    /// winning bid.
+    ///
+    /// Only a winning bid made in the same transaction is known, otherwise
+    /// its DAI was paid in an earlier transaction and there is nothing to
+    /// price. The urn is only known to the auction, which stands in for it.
+    fn decode_deal(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let id = U256::from_big_endian(call.input.get(4..36)?);
+        let (ilk, winner, lot) = self.vat_flux(call, tx)?;
+        let bid = tx
+            .actions()
+            .auction_bids()
+            .filter(|bid| bid.auction == call.to && bid.id == id && bid.bidder == winner)
+            .last()?;
+        let liquidation = Liquidation {
+            sent_token: *DAI,
+            sent_amount: bid.bid,
+            received_token: self.collateral(&ilk, tx)?,
+            received_amount: lot,
+            from: winner,
+            liquidated_user: call.to,
+            collateral_type: Some(ilk_name(&ilk)),
+        };
+        Some(Action::new(liquidation.into(), call.trace_address.clone()))
+    }
+
+    /// The action of a call `classify` matched by its selector
+    fn decode(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let selector = call.input.get(..4)?;
+        if selector == self.bite || selector == self.bark {
+            self.decode_kick(call, tx)
+        } else if selector == self.take {
+            self.decode_take(call, tx)
+        } else if selector == self.deal {
+            self.decode_deal(call, tx)
+        } else {
+            self.decode_bid(call, tx)
+        }
+    }
+
+    /// A keeper's `tend` or `dent` bid on a `Flipper` auction
+    fn decode_bid(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        // a `tend` is paid in the `Vat`, unlike one on a `Flapper`, and a
+        // `dent` returns the collateral that is no longer bid for, unlike
+        // one on a `Flopper`
+        let paid = if call.input.get(..4)? == self.dent {
+            self.vat_flux(call, tx).is_some()
+        } else {
+            tx.subcalls(&call.trace_address).any(|c| {
+                c.to == *MAKER_VAT && c.from == call.to && c.input.get(..4) == Some(&self.mv[..])
+            })
+        };
+        if !paid {
+            return None;
+        }
+        // (id, lot, bid)
+        let mut params = abi::decode(
+            &[
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ],
+            call.input.get(4..)?,
+        )
+        .ok()?
+        .into_iter();
+        let bid = AuctionBid {
+            auction: call.to,
+            id: params.next()?.into_uint()?,
+            bidder: call.from,
+            lot: params.next()?.into_uint()?,
+            bid: params.next()?.into_uint()? / *RAY,
+        };
+        Some(Action::new(bid.into(), call.trace_address.clone()))
+    }
+}
+
+impl DefiProtocol for Maker {
+    fn base_contracts(&self) -> ProtocolContracts {
+        ProtocolContracts::None
+    }
+
+    fn protocol(&self) -> Protocol {
+        Protocol::Maker
+    }
+
+    fn is_protocol_event(&self, log: &EventLog) -> bool {
+        MakerLiquidationsEvents::decode_log(&log.raw_log).is_ok()
+    }
+
+    fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        if call.classification != CallClassification::Liquidation {
+            return None;
+        }
+        self.decode(call, tx)
+    }
+
+    fn classify(
+        &self,
+        call: &InternalCall,
+    ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        if matches!(
+            call.call_type,
+            CallType::DelegateCall | CallType::StaticCall
+        ) {
+            return None;
+        }
+        let selector = call.input.get(..4)?;
+        if [
+            self.bite, self.bark, self.tend, self.dent, self.deal, self.take,
+        ]
+        .iter()
+        .any(|s| selector == s)
+        {
+            Some((CallClassification::Liquidation, None))
+        } else {
+            None
+        }
+    }
+
+    /// The selectors are not unique to the liquidation contracts, so only the
+    /// calls that decode are classified.
+    fn inspect_tx(&self, tx: &mut TransactionData) {
+        let candidates = tx
+            .calls()
+            .filter(|call| self.classify(call).is_some())
+            .map(|call| call.trace_address.clone())
+            .collect::<Vec<_>>();
+
+        // in order, so that a `deal` finds the bids before it
+        for trace_address in candidates {
+            let action = match tx.get_call(&trace_address) {
+                Some(call) => self.decode(call, tx),
+                None => continue,
+            };
+            if let Some(action) = action {
+                if let Some(call) = tx
+                    .calls_mut()
+                    .find(|call| call.trace_address == trace_address)
+                {
+                    call.protocol = Some(Protocol::Maker);
+                    call.classification = CallClassification::Liquidation;
+                }
+                tx.push_action(action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use ethers::{abi::Token, contract::EthEvent, types::H256};
+
+    fn rad(amount: u64) -> U256 {
+        U256::from(amount) * *RAY
+    }
+
+    fn flux(ilk: [u8; 32], src: Address, dst: Address, wad: u64) -> Vec<u8> {
+        calldata(
+            VAT_FLUX,
+            &[
+                Token::FixedBytes(ilk.to_vec()),
+                Token::Address(src),
+                Token::Address(dst),
+                Token::Uint(wad.into()),
+            ],
+        )
+    }
+
+    #[test]
+    fn bark() {
+        let addrs = addrs();
+        let [keeper, urn, clipper] = [addrs[0], addrs[1], addrs[2]];
+        let dog = parse_address("0x135954d155898d42c90d2a57824c690e0c7bef1b");
+        let input = calldata(
+            DOG_BARK,
+            &[
+                Token::FixedBytes(ilk("ETH-A").to_vec()),
+                Token::Address(urn),
+                Token::Address(keeper),
+            ],
+        );
+        let traces = vec![mk_trace(keeper, dog, input, vec![], 0)];
+        let logs = vec![mk_log(
+            dog,
+            vec![
+                BarkFilter::signature(),
+                H256::from(ilk("ETH-A")),
+                H256::from(urn),
+                H256::from_low_u64_be(1),
+            ],
+            vec![
+                Token::Uint(10.into()),
+                Token::Uint(15000.into()),
+                Token::Uint(rad(16000)),
+                Token::Address(clipper),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+
+        let kick = tx.actions().auction_kicks().next().unwrap();
+        assert_eq!((kick.auction, kick.id), (clipper, 1.into()));
+        assert_eq!((kick.kicker, kick.liquidated_user), (keeper, urn));
+        assert_eq!(kick.collateral_type, "ETH-A");
+        assert_eq!((kick.lot, kick.debt), (10.into(), 16000.into()));
+        // the keeper neither pays the debt nor receives the collateral
+        assert_eq!(tx.actions().liquidations().count(), 0);
+        assert!(tx.protocols().contains(&Protocol::Maker));
+    }
+
+    #[test]
+    fn take() {
+        let addrs = addrs();
+        let [keeper, urn, clipper] = [addrs[0], addrs[1], addrs[2]];
+        let input = calldata(
+            CLIPPER_TAKE,
+            &[
+                Token::Uint(1.into()),
+                Token::Uint(10.into()),
+                Token::Uint(rad(2000)),
+                Token::Address(keeper),
+                Token::Bytes(vec![]),
+            ],
+        );
+        let traces = vec![
+            mk_trace(keeper, clipper, input, vec![], 1),
+            mk_trace(
+                clipper,
+                *MAKER_VAT,
+                flux(ilk("WBTC-A"), clipper, keeper, 2),
+                vec![0],
+                0,
+            ),
+        ];
+        let logs = vec![mk_log(
+            clipper,
+            vec![
+                TakeFilter::signature(),
+                H256::from_low_u64_be(1),
+                H256::from(urn),
+            ],
+            vec![
+                Token::Uint(rad(2000)),
+                // 1500 DAI per WBTC
+                Token::Uint(U256::from(1500) * *RAY),
+                Token::Uint(rad(3000)),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!(
+            (liquidation.from, liquidation.liquidated_user),
+            (keeper, urn)
+        );
+        assert_eq!(liquidation.sent_amount, 3000.into());
+        assert_eq!(
+            (liquidation.received_token, liquidation.received_amount),
+            (parse_address(ILKS[6].1), 2.into())
+        );
+        assert_eq!(liquidation.collateral_type.as_deref(), Some("WBTC-A"));
+    }
+
+    #[test]
+    fn take_of_unknown_collateral_type() {
+        let addrs = addrs();
+        let [bot, urn, clipper, join] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let wsteth = parse_address("0x7f39c581f595b53c5cb19bd0b3f8da6c935e2ca0");
+        let take = calldata(
+            CLIPPER_TAKE,
+            &[
+                Token::Uint(1.into()),
+                Token::Uint(10.into()),
+                Token::Uint(rad(2000)),
+                Token::Address(bot),
+                Token::Bytes(vec![]),
+            ],
+        );
+        let exit = calldata(
+            "exit(address,uint256)",
+            &[Token::Address(bot), Token::Uint(2.into())],
+        );
+        let slip = calldata(
+            VAT_SLIP,
+            &[
+                Token::FixedBytes(ilk("WSTETH-A").to_vec()),
+                Token::Address(join),
+                // -2
+                Token::Int(U256::MAX - 1),
+            ],
+        );
+        let transfer = calldata(
+            ERC20_TRANSFER,
+            &[Token::Address(bot), Token::Uint(2.into())],
+        );
+        let traces = vec![
+            mk_trace(bot, bot, vec![], vec![], 2),
+            mk_trace(bot, clipper, take, vec![0], 1),
+            mk_trace(
+                clipper,
+                *MAKER_VAT,
+                flux(ilk("WSTETH-A"), clipper, bot, 2),
+                vec![0, 0],
+                0,
+            ),
+            mk_trace(bot, join, exit, vec![1], 2),
+            mk_trace(join, *MAKER_VAT, slip, vec![1, 0], 0),
+            mk_trace(join, wsteth, transfer, vec![1, 1], 0),
+        ];
+        let logs = vec![mk_log(
+            clipper,
+            vec![
+                TakeFilter::signature(),
+                H256::from_low_u64_be(1),
+                H256::from(urn),
+            ],
+            vec![
+                Token::Uint(rad(2000)),
+                Token::Uint(U256::from(1500) * *RAY),
+                Token::Uint(rad(3000)),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!(
+            (liquidation.received_token, liquidation.received_amount),
+            (wsteth, 2.into())
+        );
+        assert_eq!(liquidation.collateral_type.as_deref(), Some("WSTETH-A"));
+    }
+
+    #[test]
+    fn tend_and_deal() {
+        let addrs = addrs();
+        let [keeper, bot, flipper] = [addrs[0], addrs[1], addrs[2]];
+        let tend = calldata(
+            FLIPPER_TEND,
+            &[
+                Token::Uint(7.into()),
+                Token::Uint(10.into()),
+                Token::Uint(rad(15000)),
+            ],
+        );
+        let mv = calldata(
+            "move(address,address,uint256)",
+            &[
+                Token::Address(bot),
+                Token::Address(flipper),
+                Token::Uint(rad(15000)),
+            ],
+        );
+        let deal = calldata(FLIPPER_DEAL, &[Token::Uint(7.into())]);
+        let fluxed = flux(ilk("ETH-A"), flipper, bot, 10);
+        let traces = vec![
+            mk_trace(keeper, bot, vec![], vec![], 2),
+            mk_trace(bot, flipper, tend, vec![0], 1),
+            mk_trace(flipper, *MAKER_VAT, mv, vec![0, 0], 0),
+            mk_trace(bot, flipper, deal.clone(), vec![1], 1),
+            mk_trace(flipper, *MAKER_VAT, fluxed.clone(), vec![1, 0], 0),
+        ];
+        let mut tx = TransactionData::create(traces, vec![]).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+
+        let bid = tx.actions().auction_bids().next().unwrap();
+        assert_eq!((bid.auction, bid.bidder), (flipper, bot));
+        assert_eq!(
+            (bid.id, bid.lot, bid.bid),
+            (7.into(), 10.into(), 15000.into())
+        );
+
+        // the collateral is paid with the winning bid
+        let liquidation = tx.actions().liquidations().next().unwrap();
+        assert_eq!(liquidation.from, bot);
+        assert_eq!(
+            (liquidation.sent_token, liquidation.sent_amount),
+            (*DAI, 15000.into())
+        );
+        assert_eq!(
+            (liquidation.received_token, liquidation.received_amount),
+            (parse_address(ILKS[0].1), 10.into())
+        );
+        assert_eq!(liquidation.collateral_type.as_deref(), Some("ETH-A"));
+
+        // the winning bid of an earlier transaction is unknown
+        let traces = vec![
+            mk_trace(bot, flipper, deal, vec![], 1),
+            mk_trace(flipper, *MAKER_VAT, fluxed, vec![0], 0),
+        ];
+        let mut tx = TransactionData::create(traces, vec![]).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+        assert_eq!(tx.actions().liquidations().count(), 0);
+    }
+
+    #[test]
+    fn ignores_debt_auctions_and_other_contracts() {
+        let addrs = addrs();
+        let [keeper, bot, flopper, other] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        // a `Flopper` bid is paid in the `Vat` but returns no collateral
+        let dent = calldata(
+            FLIPPER_DENT,
+            &[
+                Token::Uint(3.into()),
+                Token::Uint(100.into()),
+                Token::Uint(rad(50000)),
+            ],
+        );
+        let mv = calldata(
+            VAT_MOVE,
+            &[
+                Token::Address(bot),
+                Token::Address(flopper),
+                Token::Uint(rad(50000)),
+            ],
+        );
+        let deal = calldata(FLIPPER_DEAL, &[Token::Uint(3.into())]);
+        let traces = vec![
+            mk_trace(keeper, bot, vec![], vec![], 2),
+            mk_trace(bot, flopper, dent, vec![0], 1),
+            mk_trace(flopper, *MAKER_VAT, mv, vec![0, 0], 0),
+            mk_trace(bot, other, deal, vec![1], 0),
+        ];
+        let mut tx = TransactionData::create(traces, vec![]).unwrap();
+        Maker::default().inspect_tx(&mut tx);
+
+        assert_eq!(tx.actions().count(), 0);
+        assert!(tx
+            .all_calls()
+            .all(|call| call.classification == CallClassification::Unknown));
+    }
+}
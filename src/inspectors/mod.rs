@@ -10,12 +10,20 @@ pub use uniswap::Uniswap;
 
 mod curve;
 /// A Curve inspector
-pub use curve::Curve;
+pub use curve::{Curve, CurveCoins, CurvePools, CurvePoolsError};
 
 mod balancer;
 /// A Balancer inspector
 pub use balancer::Balancer;
 
+mod kyber;
+/// A Kyber Network inspector
+pub use kyber::Kyber;
+
+mod bancor;
+/// A Bancor inspector
+pub use bancor::Bancor;
+
 mod aave;
 /// An Aave inspector
 pub use aave::Aave;
@@ -28,12 +36,24 @@ mod batch;
 /// Takes multiple inspectors
 pub use batch::{BatchEvaluationError, BatchInspector};
 
+mod maker;
+/// A MakerDAO liquidations inspector
+pub use maker::Maker;
+
+mod dydx;
+/// A dYdX Solo Margin inspector
+pub use dydx::DyDx;
+
 mod compound;
 pub use compound::Compound;
 
 mod zeroex;
 pub use zeroex::ZeroEx;
 
+mod aggregator;
+/// An inspector for the 1inch and Paraswap routers
+pub use aggregator::Aggregator;
+
 use crate::types::{actions::SpecificAction, Classification};
 
 /// Given an iterator over index,Classification tuples, it will try to cast
@@ -8,11 +8,11 @@ use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::actions::{SpecificAction, Transfer};
 use crate::types::{decode_token_transfers_prior, Action, TransactionData};
 use crate::{
-    addresses::{AAVE_LENDING_POOL_CORE, PROTOCOLS},
+    addresses::{registry, AAVE_LENDING_POOL_CORE},
     inspectors::find_matching,
     traits::Inspector,
     types::{
-        actions::{AddLiquidity as AddLiquidityAct, Trade},
+        actions::{AddLiquidity as AddLiquidityAct, RemoveLiquidity as RemoveLiquidityAct, Trade},
         Classification, Inspection, Protocol, Status,
     },
     DefiProtocol, ProtocolContracts,
@@ -75,13 +75,12 @@ impl DefiProtocol for Uniswap {
     }
 
     fn is_protocol(&self, call: &InternalCall) -> Option<Option<Protocol>> {
-        if let Some(protocol) = PROTOCOLS.get(&call.to) {
-            Some(Some(*protocol))
-        } else if let Some(protocol) = PROTOCOLS.get(&call.from) {
-            Some(Some(*protocol))
-        } else {
-            Some(None)
-        }
+        let registry = registry();
+        Some(
+            registry
+                .protocol(&call.to)
+                .or_else(|| registry.protocol(&call.from)),
+        )
     }
 
     fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
@@ -116,10 +115,22 @@ impl DefiProtocol for Uniswap {
                     }
 
                     if let (Some(transfer_0), Some(transfer_1)) = (transfer_0, transfer_1) {
+                        // the minted amounts are ordered by the pair's tokens
+                        let tokens = if let Some(pool) = registry().pool(&mint_log.address) {
+                            if !pool.contains(&transfer_0.address)
+                                || !pool.contains(&transfer_1.address)
+                            {
+                                return None;
+                            }
+                            vec![pool.token0, pool.token1]
+                        } else {
+                            vec![transfer_0.address, transfer_1.address]
+                        };
                         logs.push(transfer_1.log_index);
                         logs.push(transfer_0.log_index);
                         let action = AddLiquidityAct {
-                            tokens: vec![transfer_0.address, transfer_1.address],
+                            pool: mint_log.address,
+                            tokens,
                             amounts: vec![mint.amount_0, mint.amount_1],
                         };
                         return Some(Action::with_logs(
@@ -130,7 +141,7 @@ impl DefiProtocol for Uniswap {
                     }
                 }
             }
-            CallClassification::Liquidation => {
+            CallClassification::RemoveLiquidity => {
                 // get the burn event from the pair
                 // burn https://github.com/Uniswap/uniswap-v2-core/blob/master/contracts/UniswapV2Pair.sol#L148-L149
                 if let Some((_, burn_log, _)) = tx
@@ -140,7 +151,8 @@ impl DefiProtocol for Uniswap {
                     if let Some((transfer_0, transfer_1)) =
                         decode_token_transfers_prior(call, tx, burn_log.log_index)
                     {
-                        let action = AddLiquidityAct {
+                        let action = RemoveLiquidityAct {
+                            pool: burn_log.address,
                             tokens: vec![transfer_0.token, transfer_1.token],
                             amounts: vec![transfer_0.value, transfer_1.value],
                         };
@@ -169,6 +181,9 @@ impl DefiProtocol for Uniswap {
                     .call_logs_decoded::<unipair_mod::SwapFilter>(&call.trace_address)
                     .next()
                 {
+                    // the pair's tokens, if known, to reject transfers of unrelated tokens
+                    let pool = registry().pool(&swap_log.address);
+
                     // swap emits at least 1 `Transfer` event before the `Swap` event
                     // https://github.com/Uniswap/uniswap-v2-core/blob/master/contracts/UniswapV2Pair.sol#L170-L171
                     if swap.amount_0_out.is_zero() || swap.amount_1_out.is_zero() {
@@ -177,6 +192,11 @@ impl DefiProtocol for Uniswap {
                             .logs_prior_decoded::<erc20::TransferFilter>(swap_log.log_index)
                             .next()
                         {
+                            if let Some(pool) = pool {
+                                if transfer_log.address != pool.out_token(swap.amount_0_out) {
+                                    return None;
+                                }
+                            }
                             let transfer = Transfer {
                                 from: swap_call.to,
                                 to: transfer.to,
@@ -195,6 +215,13 @@ impl DefiProtocol for Uniswap {
                         if let Some((transfer_0, transfer_1)) =
                             decode_token_transfers_prior(call, tx, swap_log.log_index)
                         {
+                            if let Some(pool) = pool {
+                                if !pool.contains(&transfer_0.token)
+                                    || !pool.contains(&transfer_1.token)
+                                {
+                                    return None;
+                                }
+                            }
                             let action = Trade {
                                 t1: Transfer {
                                     from: call.from,
@@ -309,13 +336,29 @@ impl Inspector for Uniswap {
                     .decode::<AddLiquidity, _>("addLiquidity", &call.input)
                 {
                     let trace_address = calltrace.trace_address.clone();
-                    *action = Classification::new(
-                        AddLiquidityAct {
-                            tokens: vec![token0, token1],
-                            amounts: vec![amount0, amount1],
-                        },
-                        trace_address,
-                    );
+                    // the router mints the liquidity tokens with the pair
+                    let pool = actions
+                        .iter()
+                        .skip(i + 1)
+                        .filter_map(|a| a.as_call())
+                        .take_while(|c| c.trace_address.starts_with(&trace_address))
+                        .find(|c| {
+                            c.as_ref()
+                                .input
+                                .as_ref()
+                                .starts_with(&ethers::utils::id("mint(address)"))
+                        })
+                        .map(|c| c.as_ref().to);
+                    if let Some(pool) = pool {
+                        *action = Classification::new(
+                            AddLiquidityAct {
+                                pool,
+                                tokens: vec![token0, token1],
+                                amounts: vec![amount0, amount1],
+                            },
+                            trace_address,
+                        );
+                    }
                 } else if let Ok((_, _, _, bytes)) =
                     self.pair.decode::<PairSwap, _>("swap", &call.input)
                 {
@@ -401,13 +444,11 @@ impl Inspector for Uniswap {
 }
 
 fn uniswappy(to: &Address, from: &Address) -> Protocol {
-    if let Some(protocol) = PROTOCOLS.get(to) {
-        *protocol
-    } else if let Some(protocol) = PROTOCOLS.get(from) {
-        *protocol
-    } else {
-        Protocol::Uniswappy
-    }
+    let registry = registry();
+    registry
+        .protocol(to)
+        .or_else(|| registry.protocol(from))
+        .unwrap_or(Protocol::Uniswappy)
 }
 
 impl Uniswap {
@@ -455,7 +496,7 @@ pub mod tests {
 
     use crate::test_helpers::*;
     use crate::{
-        addresses::ADDRESSBOOK,
+        addresses::lookup,
         reducers::{ArbitrageReducer, TradeReducer},
         types::{Protocol, Status},
         Reducer, TxReducer,
@@ -622,7 +663,64 @@ pub mod tests {
         assert_eq!(inspection.status, Status::Checked); // This is a check
         let known = inspection.known();
         let transfer = known[0].as_ref().as_transfer().unwrap();
-        assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+        assert_eq!(lookup(transfer.token), "ETH");
+    }
+
+    #[test]
+    // The transfer before a pair's `Swap` must be of the pair's token, otherwise
+    // unrelated transfers of the caller are mistaken for the swap (see `zapper_no_false_positive`)
+    fn rejects_transfers_of_other_tokens() {
+        use crate::addresses::{parse_address, WETH};
+        use ethers::{abi::Token, contract::EthEvent, types::H256};
+
+        // the registered USDC/WETH pair
+        let pair = parse_address("0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc");
+        let addrs = addrs();
+        let [user, other_token] = [addrs[0], addrs[1]];
+        let swap = |token| {
+            let input = calldata(
+                "swap(uint256,uint256,address,bytes)",
+                &[
+                    Token::Uint(0.into()),
+                    Token::Uint(10.into()),
+                    Token::Address(user),
+                    Token::Bytes(vec![]),
+                ],
+            );
+            let traces = vec![
+                mk_trace(user, pair, input, vec![], 1),
+                mk_trace(pair, token, vec![0xa9, 0x05, 0x9c, 0xbb], vec![0], 0),
+            ];
+            let logs = vec![
+                transfer_log(token, pair, user, 10, 0),
+                mk_log(
+                    pair,
+                    vec![
+                        unipair_mod::SwapFilter::signature(),
+                        H256::from(user),
+                        H256::from(user),
+                    ],
+                    vec![
+                        Token::Uint(0.into()),
+                        Token::Uint(0.into()),
+                        Token::Uint(0.into()),
+                        Token::Uint(10.into()),
+                    ],
+                    1,
+                ),
+            ];
+            let mut tx = TransactionData::create(traces, logs).unwrap();
+            Uniswap::default().inspect_tx(&mut tx);
+            tx
+        };
+
+        // token1 of the pair is paid out
+        let tx = swap(*WETH);
+        let transfer = tx.actions().transfers().next().unwrap();
+        assert_eq!((transfer.token, transfer.amount), (*WETH, 10.into()));
+
+        let tx = swap(other_token);
+        assert_eq!(tx.actions().count(), 0);
     }
 
     #[test]
@@ -739,14 +837,14 @@ pub mod tests {
             let known = inspection.known();
 
             let transfer = known[0].as_ref().as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
 
             let deposit = known[1].as_ref().as_deposit();
             assert!(deposit.is_some());
 
             // Second is the trade
             let trade = known[2].as_ref().as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t1.token), "WETH");
             assert_eq!(trade.t1.amount, 664510977762648404u64.into());
             assert_eq!(
                 trade.t2.amount,
@@ -755,7 +853,7 @@ pub mod tests {
 
             // Third is the ETH refund
             let transfer = known[3].as_ref().as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
 
             assert_eq!(inspection.status, Status::Success);
             assert_eq!(inspection.known().len(), 4);
@@ -774,14 +872,14 @@ pub mod tests {
             // The router makes the first action by transferFrom'ing the tokens we're
             // sending in
             let trade = actions[0].as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t2.token), "WETH");
 
             let withdrawal = actions[1].as_withdrawal();
             assert!(withdrawal.is_some());
 
             // send the eth to the buyer
             let transfer = actions[2].as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
         }
 
         #[test]
@@ -796,7 +894,7 @@ pub mod tests {
             // The router makes the first action by transferFrom'ing the tokens we're
             // sending in
             let trade = known[0].as_ref().as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t2.token), "WETH");
 
             assert_eq!(inspection.known().len(), 3);
             assert_eq!(inspection.unknown().len(), 2);
@@ -806,7 +904,7 @@ pub mod tests {
 
             // send the eth to the buyer
             let transfer = known[2].as_ref().as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
 
             assert_eq!(inspection.status, Status::Success);
         }
@@ -820,8 +918,8 @@ pub mod tests {
 
             let actions = tx.actions().collect::<Vec<_>>();
             let trade = actions[0].as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "YFI");
-            assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t1.token), "YFI");
+            assert_eq!(lookup(trade.t2.token), "WETH");
         }
 
         #[test]
@@ -835,8 +933,8 @@ pub mod tests {
 
             let known = inspection.known();
             let trade = known[0].as_ref().as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "YFI");
-            assert_eq!(ADDRESSBOOK.get(&trade.t2.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t1.token), "YFI");
+            assert_eq!(lookup(trade.t2.token), "WETH");
             assert_eq!(inspection.known().len(), 1);
             assert_eq!(inspection.unknown().len(), 2);
         }
@@ -851,13 +949,13 @@ pub mod tests {
             let actions = tx.actions().collect::<Vec<_>>();
 
             let transfer = actions[0].as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
 
             let deposit = actions[1].as_deposit();
             assert!(deposit.is_some());
 
             let trade = actions[2].as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t1.token), "WETH");
             assert_eq!(
                 trade.t1.amount,
                 U256::from_dec_str("4500000000000000000").unwrap()
@@ -882,13 +980,13 @@ pub mod tests {
             assert_eq!(inspection.unknown().len(), 2);
 
             let transfer = known[0].as_ref().as_transfer().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&transfer.token).unwrap(), "ETH");
+            assert_eq!(lookup(transfer.token), "ETH");
 
             let deposit = known[1].as_ref().as_deposit();
             assert!(deposit.is_some());
 
             let trade = known[2].as_ref().as_trade().unwrap();
-            assert_eq!(ADDRESSBOOK.get(&trade.t1.token).unwrap(), "WETH");
+            assert_eq!(lookup(trade.t1.token), "WETH");
             assert_eq!(
                 trade.t1.amount,
                 U256::from_dec_str("4500000000000000000").unwrap()
@@ -1,5 +1,6 @@
 use crate::{
-    addresses::PROTOCOLS,
+    addresses::{registry, ETH},
+    inspectors::erc20::TransferFilter,
     traits::Inspector,
     types::{actions::Transfer, Classification, Inspection, Protocol},
     DefiProtocol, ProtocolContracts,
@@ -9,27 +10,88 @@ use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::actions::{SpecificAction, Trade};
 use crate::types::{Action, TransactionData};
 use ethers::{
+    abi::{self, ParamType, Token},
     contract::{abigen, BaseContract, EthLogDecode},
-    types::{Address, Bytes, U256},
+    types::{Address, Bytes, CallType, U256},
+    utils::id,
 };
 
 abigen!(ZeroXUniswapBridge, "abi/0x-uniswap-bridge.json");
 abigen!(ZeroXExchange, "abi/0x-exchange-v3.json");
+abigen!(
+    ZeroXExchangeProxy,
+    r#"[
+        event TransformedERC20(address indexed taker, address inputToken, address outputToken, uint256 inputTokenAmount, uint256 outputTokenAmount)
+        event LimitOrderFilled(bytes32 orderHash, address maker, address taker, address feeRecipient, address makerToken, address takerToken, uint128 takerTokenFilledAmount, uint128 makerTokenFilledAmount, uint128 takerTokenFeeFilledAmount, uint256 protocolFeePaid, bytes32 pool)
+        event RfqOrderFilled(bytes32 orderHash, address maker, address taker, address makerToken, address takerToken, uint128 takerTokenFilledAmount, uint128 makerTokenFilledAmount, bytes32 pool)
+    ]"#
+);
+
+/// `LimitOrder` and `Signature` of the V4 native orders
+const LIMIT_ORDER: &str = "(address,address,uint128,uint128,uint128,address,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32)";
+/// `RfqOrder` and `Signature` of the V4 native orders
+const RFQ_ORDER: &str = "(address,address,uint128,uint128,address,address,address,bytes32,uint64,uint256),(uint8,uint8,bytes32,bytes32)";
 
 #[derive(Debug, Clone)]
 /// An inspector for ZeroEx Exchange Proxy transfers
 pub struct ZeroEx {
     exchange: BaseContract,
     bridge: BaseContract,
+    proxy: ProxySelectors,
 }
 
 type BridgeTransfer = (Address, Address, Address, U256, Bytes);
 
+/// The selectors of the Exchange Proxy (V4) features that trade
+#[derive(Debug, Clone)]
+struct ProxySelectors {
+    transform_erc20: [u8; 4],
+    sell_to_uniswap: [u8; 4],
+    /// `fillLimitOrder` and `fillOrKillLimitOrder`
+    limit_orders: [[u8; 4]; 2],
+    /// `fillRfqOrder` and `fillOrKillRfqOrder`
+    rfq_orders: [[u8; 4]; 2],
+}
+
+impl Default for ProxySelectors {
+    fn default() -> Self {
+        Self {
+            transform_erc20: id("transformERC20(address,address,uint256,uint256,(uint32,bytes)[])"),
+            sell_to_uniswap: id("sellToUniswap(address[],uint256,uint256,bool)"),
+            limit_orders: [
+                id(format!("fillLimitOrder({},uint128)", LIMIT_ORDER)),
+                id(format!("fillOrKillLimitOrder({},uint128)", LIMIT_ORDER)),
+            ],
+            rfq_orders: [
+                id(format!("fillRfqOrder({},uint128)", RFQ_ORDER)),
+                id(format!("fillOrKillRfqOrder({},uint128)", RFQ_ORDER)),
+            ],
+        }
+    }
+}
+
+impl ProxySelectors {
+    fn is_trade(&self, input: &[u8]) -> bool {
+        let selector = match input.get(..4) {
+            Some(selector) => selector,
+            None => return false,
+        };
+        selector == self.transform_erc20
+            || selector == self.sell_to_uniswap
+            || self.limit_orders.iter().any(|s| selector == s)
+            || self.rfq_orders.iter().any(|s| selector == s)
+    }
+}
+
 impl Default for ZeroEx {
     fn default() -> Self {
         let bridge = BaseContract::from(ZEROXUNISWAPBRIDGE_ABI.clone());
         let exchange = BaseContract::from(ZEROXEXCHANGE_ABI.clone());
-        Self { exchange, bridge }
+        Self {
+            exchange,
+            bridge,
+            proxy: ProxySelectors::default(),
+        }
     }
 }
 
@@ -45,18 +107,17 @@ impl DefiProtocol for ZeroEx {
     fn is_protocol_event(&self, log: &EventLog) -> bool {
         Erc20BridgeTransferFilter::decode_log(&log.raw_log).is_ok()
             || ZeroXExchangeEvents::decode_log(&log.raw_log).is_ok()
+            || ZeroXExchangeProxyEvents::decode_log(&log.raw_log).is_ok()
     }
 
     fn decode_call_action(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
         match call.classification {
             CallClassification::Transfer => {
                 // https://github.com/0xProject/0x-monorepo/blob/development/contracts/asset-proxy/contracts/src/interfaces/IERC20Bridge.sol#L34
-                println!("try bridge transfer decoding");
                 if let Some((_, log, swap)) = tx
                     .call_logs_decoded::<Erc20BridgeTransferFilter>(&call.trace_address)
                     .next()
                 {
-                    println!("decoded zerox transfer");
                     let action = Trade {
                         t1: Transfer {
                             from: swap.from,
@@ -73,9 +134,8 @@ impl DefiProtocol for ZeroEx {
                     };
 
                     // the bridge call will tell us which sub-protocol was used
-                    let additional_protocols = PROTOCOLS
-                        .get(&swap.from)
-                        .cloned()
+                    let additional_protocols = registry()
+                        .protocol(&swap.from)
                         .map(|p| vec![p])
                         .unwrap_or_default();
 
@@ -87,6 +147,7 @@ impl DefiProtocol for ZeroEx {
                     ));
                 }
             }
+            CallClassification::Swap => return self.decode_proxy_trade(call, tx),
             _ => {}
         }
         None
@@ -96,6 +157,10 @@ impl DefiProtocol for ZeroEx {
         &self,
         call: &InternalCall,
     ) -> Option<(CallClassification, Option<SpecificAction>)> {
+        // the proxy delegates to its features with the same calldata
+        if call.call_type != CallType::DelegateCall && self.proxy.is_trade(&call.input) {
+            return Some((CallClassification::Swap, None));
+        }
         // https://github.com/0xProject/0x-monorepo/blob/development/contracts/asset-proxy/contracts/src/bridges/UniswapBridge.sol#L150
         self.bridge
             .decode::<BridgeTransfer, _>("bridgeTransferFrom", &call.input)
@@ -104,6 +169,163 @@ impl DefiProtocol for ZeroEx {
     }
 }
 
+impl ZeroEx {
+    /// Decodes a trade of the Exchange Proxy
+    ///
+    /// Native orders are traded between the order's maker and taker, all other
+    /// trades are between the taker and the proxy.
+    fn decode_proxy_trade(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        let selector = call.input.get(..4)?;
+        if selector == self.proxy.transform_erc20 {
+            // https://github.com/0xProject/protocol/blob/development/contracts/zero-ex/contracts/src/features/TransformERC20Feature.sol
+            let (_, log, transform) = tx
+                .call_logs_decoded::<TransformedERC20Filter>(&call.trace_address)
+                .find(|(c, _, _)| c.to == call.to)?;
+            let action = Trade {
+                t1: Transfer {
+                    from: transform.taker,
+                    to: call.to,
+                    amount: transform.input_token_amount,
+                    token: transform.input_token,
+                },
+                t2: Transfer {
+                    from: call.to,
+                    to: transform.taker,
+                    amount: transform.output_token_amount,
+                    token: transform.output_token,
+                },
+            };
+            Some(Action::with_logs(
+                action.into(),
+                call.trace_address.clone(),
+                vec![log.log_index],
+            ))
+        } else if selector == self.proxy.sell_to_uniswap {
+            self.decode_sell_to_uniswap(call, tx)
+        } else if self.proxy.limit_orders.iter().any(|s| selector == s) {
+            let (_, log, fill) = tx
+                .call_logs_decoded::<LimitOrderFilledFilter>(&call.trace_address)
+                .find(|(c, _, _)| c.to == call.to)?;
+            let action = Trade {
+                t1: Transfer {
+                    from: fill.taker,
+                    to: fill.maker,
+                    amount: fill.taker_token_filled_amount.into(),
+                    token: fill.taker_token,
+                },
+                t2: Transfer {
+                    from: fill.maker,
+                    to: fill.taker,
+                    amount: fill.maker_token_filled_amount.into(),
+                    token: fill.maker_token,
+                },
+            };
+            Some(Action::with_logs(
+                action.into(),
+                call.trace_address.clone(),
+                vec![log.log_index],
+            ))
+        } else {
+            let (_, log, fill) = tx
+                .call_logs_decoded::<RfqOrderFilledFilter>(&call.trace_address)
+                .find(|(c, _, _)| c.to == call.to)?;
+            let action = Trade {
+                t1: Transfer {
+                    from: fill.taker,
+                    to: fill.maker,
+                    amount: fill.taker_token_filled_amount.into(),
+                    token: fill.taker_token,
+                },
+                t2: Transfer {
+                    from: fill.maker,
+                    to: fill.taker,
+                    amount: fill.maker_token_filled_amount.into(),
+                    token: fill.maker_token,
+                },
+            };
+            Some(Action::with_logs(
+                action.into(),
+                call.trace_address.clone(),
+                vec![log.log_index],
+            ))
+        }
+    }
+
+    /// `sellToUniswap` emits no event, the bought amount is the last transfer
+    /// of the output token to the taker, or the ETH sent to the taker after
+    /// unwrapping WETH
+    fn decode_sell_to_uniswap(&self, call: &InternalCall, tx: &TransactionData) -> Option<Action> {
+        // sellToUniswap(tokens, sellAmount, minBuyAmount, isSushi)
+        let mut params = abi::decode(
+            &[
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Bool,
+            ],
+            call.input.get(4..)?,
+        )
+        .ok()?
+        .into_iter();
+        let tokens = params
+            .next()?
+            .into_array()?
+            .into_iter()
+            .map(Token::into_address)
+            .collect::<Option<Vec<_>>>()?;
+        let sell_amount = params.next()?.into_uint()?;
+        let is_sushi = params.nth(1)?.into_bool()?;
+
+        let (input_token, output_token) = (*tokens.first()?, *tokens.last()?);
+        let (bought, logs) = if output_token == *ETH {
+            let payout = tx
+                .all_calls()
+                .filter(|c| {
+                    c.trace_address.starts_with(&call.trace_address)
+                        && c.call_type == CallType::Call
+                        && c.to == call.from
+                        && !c.value.is_zero()
+                })
+                .last()?;
+            (payout.value, vec![])
+        } else {
+            let (_, log, transfer) = tx
+                .call_logs_decoded::<TransferFilter>(&call.trace_address)
+                .filter(|(_, log, transfer)| {
+                    log.address == output_token && transfer.to == call.from
+                })
+                .last()?;
+            (transfer.value, vec![log.log_index])
+        };
+
+        let action = Trade {
+            t1: Transfer {
+                from: call.from,
+                to: call.to,
+                amount: sell_amount,
+                token: input_token,
+            },
+            t2: Transfer {
+                from: call.to,
+                to: call.from,
+                amount: bought,
+                token: output_token,
+            },
+        };
+        let protocol = if is_sushi {
+            Protocol::Sushiswap
+        } else {
+            Protocol::UniswapV2
+        };
+        Some(Action::with_logs_and_protocols(
+            action.into(),
+            call.trace_address.clone(),
+            logs,
+            vec![protocol],
+        ))
+    }
+}
+
 impl Inspector for ZeroEx {
     fn inspect(&self, inspection: &mut Inspection) {
         let actions = inspection.actions.to_vec();
@@ -122,8 +344,8 @@ impl Inspector for ZeroEx {
                     inspection.protocols.insert(Protocol::ZeroEx);
 
                     // the bridge call will tell us which sub-protocol was used
-                    if let Some(protocol) = PROTOCOLS.get(&transfer.1) {
-                        inspection.protocols.insert(*protocol);
+                    if let Some(protocol) = registry().protocol(&transfer.1) {
+                        inspection.protocols.insert(protocol);
                     }
 
                     // change this to a transfer
@@ -154,12 +376,14 @@ impl Inspector for ZeroEx {
 mod tests {
     use super::*;
     use crate::{
+        addresses::ZEROX_EXCHANGE_PROXY,
         inspectors::ERC20,
         reducers::{ArbitrageReducer, TradeReducer},
         test_helpers::*,
         types::Status,
         Reducer, TxReducer,
     };
+    use ethers::{contract::EthEvent, types::H256};
 
     struct MyInspector {
         zeroex: ZeroEx,
@@ -201,6 +425,13 @@ mod tests {
         let zeroex = MyInspector::new();
         zeroex.inspect_tx(&mut tx);
         assert_eq!(tx.status, Status::Reverted);
+
+        // the `transformERC20` call is a trade of the proxy, but it reverted
+        // before `TransformedERC20` was emitted, so no trade is decoded from it
+        let call = tx.get_call(&[]).unwrap();
+        assert_eq!(call.classification, CallClassification::Swap);
+        assert_eq!(call.protocol, Some(Protocol::ZeroEx));
+        assert!(!tx.actions().any(|action| action.call.is_empty()));
     }
 
     #[test]
@@ -240,4 +471,179 @@ mod tests {
         // the trade required more than we put in (TODO: is this correct?)
         assert_ne!(t1.amount, balancer.t1.amount + uniswap.t1.amount);
     }
+
+    #[test]
+    fn transform_erc20() {
+        let addrs = addrs();
+        let [taker, dai, weth] = [addrs[0], addrs[1], addrs[2]];
+        let proxy = *ZEROX_EXCHANGE_PROXY;
+        let input = calldata(
+            "transformERC20(address,address,uint256,uint256,(uint32,bytes)[])",
+            &[
+                Token::Address(dai),
+                Token::Address(weth),
+                Token::Uint(100.into()),
+                Token::Uint(1.into()),
+                Token::Array(vec![]),
+            ],
+        );
+        let traces = vec![
+            mk_trace(taker, proxy, input.clone(), vec![], 1),
+            // the proxy's feature
+            mk_trace(proxy, addrs[3], input, vec![0], 0),
+        ];
+        let logs = vec![mk_log(
+            proxy,
+            vec![TransformedERC20Filter::signature(), H256::from(taker)],
+            vec![
+                Token::Address(dai),
+                Token::Address(weth),
+                Token::Uint(100.into()),
+                Token::Uint(2.into()),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        ZeroEx::default().inspect_tx(&mut tx);
+
+        // the delegatecall into the feature is not a separate trade
+        assert_eq!(tx.actions().count(), 1);
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(trade.t1.from, taker);
+        assert_eq!(trade.t1.token, dai);
+        assert_eq!(trade.t1.amount, 100.into());
+        assert_eq!(trade.t2.to, taker);
+        assert_eq!(trade.t2.token, weth);
+        assert_eq!(trade.t2.amount, 2.into());
+    }
+
+    #[test]
+    fn fill_rfq_order() {
+        let addrs = addrs();
+        let [taker, maker, usdc, weth] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let proxy = *ZEROX_EXCHANGE_PROXY;
+        let mut input = id(format!("fillRfqOrder({},uint128)", RFQ_ORDER)).to_vec();
+        // the order is not decoded
+        input.extend_from_slice(&[0; 32 * 15]);
+        let traces = vec![mk_trace(taker, proxy, input, vec![], 0)];
+        let logs = vec![mk_log(
+            proxy,
+            vec![RfqOrderFilledFilter::signature()],
+            vec![
+                Token::FixedBytes(vec![0; 32]),
+                Token::Address(maker),
+                Token::Address(taker),
+                Token::Address(weth),
+                Token::Address(usdc),
+                Token::Uint(3000.into()),
+                Token::Uint(1.into()),
+                Token::FixedBytes(vec![0; 32]),
+            ],
+            0,
+        )];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        ZeroEx::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(
+            (trade.t1.from, trade.t1.to, trade.t1.token, trade.t1.amount),
+            (taker, maker, usdc, 3000.into())
+        );
+        assert_eq!(
+            (trade.t2.from, trade.t2.to, trade.t2.token, trade.t2.amount),
+            (maker, taker, weth, 1.into())
+        );
+    }
+
+    #[test]
+    fn sell_to_uniswap() {
+        let addrs = addrs();
+        let [taker, pair, dai, weth] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let proxy = *ZEROX_EXCHANGE_PROXY;
+        let input = calldata(
+            "sellToUniswap(address[],uint256,uint256,bool)",
+            &[
+                Token::Array(vec![Token::Address(dai), Token::Address(weth)]),
+                Token::Uint(100.into()),
+                Token::Uint(1.into()),
+                Token::Bool(true),
+            ],
+        );
+        let traces = vec![
+            mk_trace(taker, proxy, input, vec![], 1),
+            mk_trace(
+                pair,
+                weth,
+                calldata(
+                    "transfer(address,uint256)",
+                    &[Token::Address(taker), Token::Uint(2.into())],
+                ),
+                vec![0],
+                0,
+            ),
+        ];
+        let logs = vec![transfer_log(weth, pair, taker, 2, 0)];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        ZeroEx::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!(trade.t1.token, dai);
+        assert_eq!(trade.t1.amount, 100.into());
+        assert_eq!(trade.t2.token, weth);
+        assert_eq!(trade.t2.amount, 2.into());
+        assert!(tx.protocols().contains(&Protocol::Sushiswap));
+    }
+
+    #[test]
+    fn sell_to_uniswap_for_eth() {
+        let addrs = addrs();
+        let [taker, pair, dai, weth] = [addrs[0], addrs[1], addrs[2], addrs[3]];
+        let proxy = *ZEROX_EXCHANGE_PROXY;
+        let input = calldata(
+            "sellToUniswap(address[],uint256,uint256,bool)",
+            &[
+                Token::Array(vec![Token::Address(dai), Token::Address(*ETH)]),
+                Token::Uint(100.into()),
+                Token::Uint(1.into()),
+                Token::Bool(false),
+            ],
+        );
+        // the proxy unwraps the WETH it bought and sends the ETH to the taker
+        let mut payout = mk_trace(proxy, taker, vec![], vec![2], 0);
+        if let ethers::types::Action::Call(ref mut call) = payout.action {
+            call.value = 2.into();
+        }
+        let traces = vec![
+            mk_trace(taker, proxy, input, vec![], 3),
+            mk_trace(
+                pair,
+                weth,
+                calldata(
+                    "transfer(address,uint256)",
+                    &[Token::Address(proxy), Token::Uint(2.into())],
+                ),
+                vec![0],
+                0,
+            ),
+            mk_trace(
+                proxy,
+                weth,
+                calldata("withdraw(uint256)", &[Token::Uint(2.into())]),
+                vec![1],
+                0,
+            ),
+            payout,
+        ];
+        let logs = vec![transfer_log(weth, pair, proxy, 2, 0)];
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        ZeroEx::default().inspect_tx(&mut tx);
+
+        let trade = tx.actions().trades().next().unwrap();
+        assert_eq!((trade.t1.token, trade.t1.amount), (dai, 100.into()));
+        assert_eq!(
+            (trade.t2.to, trade.t2.token, trade.t2.amount),
+            (taker, *ETH, 2.into())
+        );
+        assert!(tx.protocols().contains(&Protocol::UniswapV2));
+    }
 }
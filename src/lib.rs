@@ -40,11 +40,20 @@ mod mevdb;
 pub use mevdb::{BatchInserts, MevDB};
 
 mod prices;
-pub use prices::HistoricalPrice;
+pub use prices::{HistoricalPrice, LiquidationQuote, PriceSource};
+
+/// Discovery of AMM pairs from factory events
+pub mod pairs;
+
+/// Chain specific addresses
+pub mod network;
 
 pub mod model;
 mod sandwich;
 
+/// Comparison against the reference mev-inspect results
+pub mod validation;
+
 /// Checks if `a2` is a subtrace of `a1`
 pub(crate) fn is_subtrace(a1: &[usize], a2: &[usize]) -> bool {
     if a1.is_empty() {
@@ -1,8 +1,17 @@
 use mev_inspect::{
-    inspectors::{Aave, Balancer, Compound, Curve, Uniswap, ZeroEx, ERC20},
+    addresses::AddressRegistry,
+    inspectors::{
+        Aave, Aggregator, Balancer, Bancor, Compound, Curve, CurvePools, DyDx, Kyber, Maker,
+        Uniswap, ZeroEx, ERC20,
+    },
     model::EventLog,
-    reducers::{ArbitrageReducer, LiquidationReducer, TradeReducer},
-    types::Evaluation,
+    network::Network,
+    pairs::{register_pairs, PairIndexer},
+    reducers::{
+        ArbitrageReducer, BackrunReducer, BundleReducer, FlashbotsBlocks, LiquidationReducer,
+        TradeReducer,
+    },
+    types::{fees::block_with_fees, Evaluation, GasFees},
     BatchInserts, BatchInspector, CachedProvider, DefiProtocol, HistoricalPrice, MevDB, TxReducer,
 };
 
@@ -41,6 +50,25 @@ struct Opts {
     #[options(default = "mev_inspections", help = "the table of the database")]
     db_table: String,
 
+    #[options(help = "Directory with address registry overrides and labels")]
+    registry: Option<PathBuf>,
+    #[options(help = "The network's name or chain id, detected from the node if omitted")]
+    network: Option<Network>,
+    #[options(help = "ignore transactions sent to the registry's filtered addresses")]
+    filter: bool,
+    #[options(help = "index new pairs from the factory events before inspecting")]
+    index_pairs: bool,
+    #[options(help = "JSON snapshot of the Curve pools, refreshed and saved back on start")]
+    curve_pools: Option<PathBuf>,
+    #[options(help = "use the Curve pools snapshot as is instead of refreshing it")]
+    no_curve_refresh: bool,
+    #[options(help = "JSON snapshot of the Compound markets, created from the node if missing")]
+    compound_markets: Option<PathBuf>,
+    #[options(help = "JSON dump of the Flashbots blocks API to take the bundles from")]
+    flashbots_blocks: Option<PathBuf>,
+    #[options(help = "value liquidations with the lending protocol's price oracle")]
+    oracle_prices: bool,
+
     // Single tx or many blocks
     #[options(command)]
     cmd: Option<Command>,
@@ -93,35 +121,93 @@ async fn main() -> anyhow::Result<()> {
 
 async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow::Result<()> {
     let provider = Arc::new(provider);
+
+    // Select the chain before any of its addresses are used
+    let network = if let Some(network) = opts.network.clone() {
+        network
+    } else {
+        let chain_id = provider.get_chainid().await?.as_u64();
+        Network::from_chain_id(chain_id)
+            .ok_or_else(|| anyhow::anyhow!("unsupported chain id {}", chain_id))?
+    };
+    log::info!("inspecting {}", network);
+    network
+        .clone()
+        .install()
+        .map_err(|_| anyhow::anyhow!("network already installed"))?;
+
     // Instantiate the thing which will query historical prices
-    let prices = HistoricalPrice::new(provider.clone());
+    let mut prices = HistoricalPrice::new(provider.clone());
+    if opts.oracle_prices {
+        prices = prices.with_lending_oracles();
+    }
 
-    let compound = Compound::create(provider.clone()).await?;
-    let curve = Curve::create(provider.clone()).await?;
-    let inspectors: Vec<Box<dyn DefiProtocol + Send + Sync>> = vec![
+    let mut inspectors: Vec<Box<dyn DefiProtocol + Send + Sync>> = vec![
+        // Classify the user's swap before the AMMs claim its transfers
+        Box::new(Aggregator::one_inch()),
+        Box::new(Aggregator::paraswap()),
         Box::new(ZeroEx::default()),
         // Classify AMMs
         Box::new(Balancer::default()),
         Box::new(Uniswap::default()),
-        Box::new(curve),
-        // Classify Liquidations
-        Box::new(Aave::new()),
-        Box::new(compound),
-        // Classify Transfers
-        Box::new(ERC20::new()),
+        Box::new(Kyber::default()),
+        Box::new(Bancor::default()),
     ];
+    if network.curve_registry.is_some() {
+        let mut pools = match opts.curve_pools {
+            Some(ref path) if path.exists() => CurvePools::load(path)?,
+            _ => CurvePools::default(),
+        };
+        if !opts.no_curve_refresh {
+            pools.refresh(provider.clone()).await?;
+            if let Some(ref path) = opts.curve_pools {
+                pools.save(path)?;
+            }
+        }
+        inspectors.push(Box::new(Curve::new(pools.pools)));
+    }
+    // Classify Liquidations
+    if network.aave_lending_pool.is_some() {
+        inspectors.push(Box::new(Aave::new()));
+    }
+    inspectors.push(Box::new(DyDx::default()));
+    inspectors.push(Box::new(Maker::default()));
+    let mut compound_markets = None;
+    if network.compound.is_some() {
+        let compound = match opts.compound_markets {
+            Some(ref path) if path.exists() => Compound::load(path)?,
+            _ => Compound::create(provider.clone()).await?,
+        };
+        if let Some(ref path) = opts.compound_markets {
+            // clones share their markets, so the ones listed while inspecting
+            // are saved when done
+            compound.save(path)?;
+            compound_markets = Some((compound.clone(), path.clone()));
+        }
+        inspectors.push(Box::new(compound));
+    }
+    // Classify Transfers
+    inspectors.push(Box::new(ERC20::new()));
 
     let reducers: Vec<Box<dyn TxReducer + Send + Sync>> = vec![
         Box::new(LiquidationReducer),
         Box::new(TradeReducer),
         Box::new(ArbitrageReducer),
     ];
-    let processor = BatchInspector::new(inspectors, reducers);
+    let flashbots = if let Some(ref path) = opts.flashbots_blocks {
+        FlashbotsBlocks::load(path)?
+    } else {
+        FlashbotsBlocks::default()
+    };
+    let processor = BatchInspector::new(inspectors, reducers)
+        .with_block_reducer(BackrunReducer::default())
+        .with_block_reducer(BundleReducer::new(flashbots));
 
     // TODO: Pass overwrite parameter
     let mut db = MevDB::connect(opts.db_cfg)
         .await?
-        .with_table_name(&opts.db_table);
+        .with_table_name(&opts.db_table)
+        .with_chain_id(network.chain_id);
 
     if opts.reset {
         db.redo_migration().await?
@@ -133,6 +219,24 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
     db.prepare_statements().await?;
     log::debug!("prepared mevdb statements");
 
+    // Load the known addresses before anything gets classified
+    let mut registry = if let Some(ref dir) = opts.registry {
+        AddressRegistry::load(dir, &network.name)?
+    } else {
+        AddressRegistry::embedded(&network.name)
+    };
+    if opts.index_pairs {
+        PairIndexer::new(provider.clone()).index(&db).await?;
+    }
+    register_pairs(&mut registry, &db.select_pairs().await?);
+    if opts.filter {
+        registry.enable_filter();
+    }
+    registry
+        .install()
+        .map_err(|_| anyhow::anyhow!("address registry already installed"))?;
+    log::debug!("installed address registry");
+
     if let Some(cmd) = opts.cmd {
         match cmd {
             Command::Tx(opts) => {
@@ -153,24 +257,53 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                 let mut tx = TransactionData::create(traces, logs)
                     .unwrap_or_else(|_| panic!("Failed to create tx {:?}", opts.tx));
 
+                let (block, receipts, mut fees) = block_with_fees(provider.as_ref(), block)
+                    .await?
+                    .expect("block not found");
+                let coinbase = block.author;
+                tx.coinbase = Some(coinbase);
+
                 processor.inspect_tx(&mut tx);
                 processor.reduce_tx(&mut tx);
-                let gas_used = provider
-                    .get_transaction_receipt(tx.hash)
-                    .await?
+                let gas_used = receipts
+                    .iter()
+                    .find(|receipt| receipt.transaction_hash == tx.hash)
                     .expect("tx not found")
                     .gas_used
                     .unwrap_or_default();
-                let gas_price = provider
-                    .get_transaction(tx.hash)
-                    .await?
+                let gas_price = block
+                    .transactions
+                    .iter()
+                    .find(|transaction| transaction.hash == tx.hash)
                     .expect("tx not found")
                     .gas_price;
+                let fees = fees
+                    .remove(&tx.hash)
+                    .unwrap_or_else(|| GasFees::legacy(gas_price));
 
-                let evaluation = Evaluation::new(tx, &prices, gas_used, gas_price).await?;
+                let evaluation = Evaluation::new(tx, &prices, gas_used, fees, coinbase).await?;
                 println!("Found: {:?}", evaluation.as_ref().hash);
                 println!("Revenue: {:?} WEI", evaluation.profit);
-                println!("Cost: {:?} WEI", evaluation.gas_used * evaluation.gas_price);
+                println!("Gas cost: {:?} WEI", evaluation.gas_cost);
+                println!("Burned: {:?} WEI", evaluation.burned_fee);
+                println!("Miner tip: {:?} WEI", evaluation.miner_tip);
+                println!(
+                    "Coinbase transfers: {:?} WEI",
+                    evaluation.coinbase_transfers
+                );
+                println!("Net profit: {} WEI", evaluation.net_profit);
+                for liquidation in &evaluation.liquidations {
+                    println!(
+                        "Liquidation: {:?} -> {:?}, bonus: {:?} WEI ({})",
+                        liquidation.debt_token,
+                        liquidation.collateral_token,
+                        liquidation.bonus(),
+                        liquidation.price_source
+                    );
+                }
+                for warning in &evaluation.warnings {
+                    println!("Warning: {}", warning);
+                }
                 println!("Actions: {:?}", evaluation.actions);
                 println!("Protocols: {:?}", evaluation.tx.protocols());
                 println!("Status: {:?}", evaluation.tx.status);
@@ -264,6 +397,9 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                 );
             }
         };
+        if let Some((compound, path)) = compound_markets {
+            compound.save(path)?;
+        }
     } else {
         let provider = Arc::new(provider);
         let processor = Arc::new(processor);
@@ -299,6 +435,9 @@ async fn run<M: Middleware + Clone + 'static>(provider: M, opts: Opts) -> anyhow
                     }
                 }
             }
+            if let Some((ref compound, ref path)) = compound_markets {
+                compound.save(path)?;
+            }
         }
     }
 
@@ -10,9 +10,11 @@ use thiserror::Error;
 use tokio_postgres::{config::Config, Client, NoTls, Statement};
 
 use crate::inspectors::BatchEvaluationError;
-use crate::model::{EventLog, InternalCall, SqlCallType, SqlRowExt};
+use crate::model::{EventLog, FromSqlExt, InternalCall, SqlCallType, SqlRowExt};
+use crate::pairs::Pair;
+use crate::types::actions::ArbitrageLeg;
 use crate::types::evaluation::ActionType;
-use crate::types::{Evaluation, Protocol};
+use crate::types::{Evaluation, LiquidationRecord, Protocol};
 use itertools::Itertools;
 
 /// The SQL script to setup the database schema
@@ -23,6 +25,23 @@ pub const DATABASE_MIGRATION_UP: &str =
 pub const DATABASE_MIGRATION_DOWN: &str =
     include_str!("../migrations/00000000000000_initial_setup/down.sql");
 
+/// The SQL script adding the costs, bundles, arbitrage legs, liquidations and pairs
+pub const EVALUATION_DETAILS_MIGRATION_UP: &str =
+    include_str!("../migrations/2026-10-18-000000_evaluation_details/up.sql");
+
+/// The SQL script reverting `EVALUATION_DETAILS_MIGRATION_UP`
+pub const EVALUATION_DETAILS_MIGRATION_DOWN: &str =
+    include_str!("../migrations/2026-10-18-000000_evaluation_details/down.sql");
+
+/// The `(up, down)` scripts of all migrations, in the order they are run
+const DATABASE_MIGRATIONS: [(&str, &str); 2] = [
+    (DATABASE_MIGRATION_UP, DATABASE_MIGRATION_DOWN),
+    (
+        EVALUATION_DETAILS_MIGRATION_UP,
+        EVALUATION_DETAILS_MIGRATION_DOWN,
+    ),
+];
+
 // default table name for inspections
 const DEFAULT_MEV_INSPECTIONS_TABLE: &str = "mev_inspections";
 
@@ -32,11 +51,28 @@ const DEFAULT_INTERNAL_CALLS_TABLE: &str = "internal_calls";
 // default table name for event logs
 const DEFAULT_LOGS_TABLE: &str = "event_logs";
 
+// mainnet
+const DEFAULT_CHAIN_ID: u64 = 1;
+
+// default table name for indexed pairs
+const DEFAULT_PAIRS_TABLE: &str = "pairs";
+
+// default table name for the scan progress of the factories
+const DEFAULT_PAIR_CURSORS_TABLE: &str = "pair_cursors";
+
+// default table name for the legs of arbitrages
+const DEFAULT_ARBITRAGE_LEGS_TABLE: &str = "arbitrage_legs";
+
+// default table name for the liquidations
+const DEFAULT_LIQUIDATIONS_TABLE: &str = "liquidations";
+
 /// Wrapper around PostGres for storing results in the database
 pub struct MevDB {
     client: Client,
     on_conflict: String,
     table_name: String,
+    /// The chain all entries belong to
+    chain_id: u64,
     /// prepared statements for inserting entries
     prepared_statements: Option<PreparedInsertStatements>,
     /// What to insert
@@ -50,6 +86,10 @@ struct PreparedInsertStatements {
     insert_call_stmt: Statement,
     /// The prepared statement to insert an `EventLog`
     insert_event_log_stmt: Statement,
+    /// The prepared statement to insert an `ArbitrageLeg`
+    insert_arbitrage_leg_stmt: Statement,
+    /// The prepared statement to insert a `LiquidationRecord`
+    insert_liquidation_stmt: Statement,
 }
 
 impl MevDB {
@@ -67,6 +107,7 @@ impl MevDB {
             client,
             // TODO: Allow overwriting on conflict
             table_name: DEFAULT_MEV_INSPECTIONS_TABLE.to_string(),
+            chain_id: DEFAULT_CHAIN_ID,
             on_conflict: "on conflict do nothing".to_string(),
             prepared_statements: None,
             insert_filter: Default::default(),
@@ -90,31 +131,36 @@ impl MevDB {
         self
     }
 
-    /// Runs the database migration
+    /// Sets the chain id that is stored with and used to select all entries
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Runs the database migrations
     pub async fn run_migration(&self) -> Result<(), DbError> {
-        if self.table_name == DEFAULT_MEV_INSPECTIONS_TABLE {
-            Ok(self.client.batch_execute(DATABASE_MIGRATION_UP).await?)
-        } else {
-            Ok(self
-                .client
-                .batch_execute(
-                    &DATABASE_MIGRATION_UP.replace(DEFAULT_MEV_INSPECTIONS_TABLE, &self.table_name),
-                )
-                .await?)
+        for (up, _) in DATABASE_MIGRATIONS.iter() {
+            self.execute_migration(up).await?;
         }
+        Ok(())
     }
 
-    /// Reverts the database migration
+    /// Reverts the database migrations
     pub async fn revert_migration(&self) -> Result<(), DbError> {
+        for (_, down) in DATABASE_MIGRATIONS.iter().rev() {
+            self.execute_migration(down).await?;
+        }
+        Ok(())
+    }
+
+    /// Executes the migration script against the configured table
+    async fn execute_migration(&self, script: &str) -> Result<(), DbError> {
         if self.table_name == DEFAULT_MEV_INSPECTIONS_TABLE {
-            Ok(self.client.batch_execute(DATABASE_MIGRATION_DOWN).await?)
+            Ok(self.client.batch_execute(script).await?)
         } else {
             Ok(self
                 .client
-                .batch_execute(
-                    &DATABASE_MIGRATION_DOWN
-                        .replace(DEFAULT_MEV_INSPECTIONS_TABLE, &self.table_name),
-                )
+                .batch_execute(&script.replace(DEFAULT_MEV_INSPECTIONS_TABLE, &self.table_name))
                 .await?)
         }
     }
@@ -140,8 +186,21 @@ impl MevDB {
                         eoa,
                         contract,
                         proxy_impl,
-                        transaction_position
-                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                        transaction_position,
+                        chain_id,
+                        gas_cost,
+                        coinbase_transfers,
+                        net_profit,
+                        base_fee,
+                        max_fee,
+                        max_priority_fee,
+                        burned_fee,
+                        miner_tip,
+                        backrun_of,
+                        bundle_index,
+                        bundle_type,
+                        warnings
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25)
                     {}",
             self.table_name, self.on_conflict,
         )
@@ -185,6 +244,47 @@ impl MevDB {
         )
     }
 
+    /// The statement to insert `ArbitrageLeg`s
+    fn insert_into_arbitrage_legs_stmt(&self) -> String {
+        format!(
+            "INSERT INTO {} (
+                        transaction_hash,
+                        arbitrage_index,
+                        leg_index,
+                        pool,
+                        protocol,
+                        token_in,
+                        amount_in,
+                        token_out,
+                        amount_out
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    {}",
+            DEFAULT_ARBITRAGE_LEGS_TABLE, self.on_conflict,
+        )
+    }
+
+    /// The statement to insert `LiquidationRecord`s
+    fn insert_into_liquidations_stmt(&self) -> String {
+        format!(
+            "INSERT INTO {} (
+                        transaction_hash,
+                        liquidation_index,
+                        protocol,
+                        debt_token,
+                        debt_amount,
+                        collateral_token,
+                        collateral_amount,
+                        liquidated_user,
+                        debt_value,
+                        collateral_value,
+                        bonus,
+                        price_source
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    {}",
+            DEFAULT_LIQUIDATIONS_TABLE, self.on_conflict,
+        )
+    }
+
     async fn get_prepared_call_stmt(&self) -> Result<Statement, DbError> {
         let insert_call = self.insert_into_internal_call_stmt();
         Ok(self.client.prepare(&insert_call).await?)
@@ -194,29 +294,49 @@ impl MevDB {
         let insert_log = self.insert_into_event_logs_stmt();
         Ok(self.client.prepare(&insert_log).await?)
     }
+    async fn get_prepared_arbitrage_leg_stmt(&self) -> Result<Statement, DbError> {
+        let insert_leg = self.insert_into_arbitrage_legs_stmt();
+        Ok(self.client.prepare(&insert_leg).await?)
+    }
+
+    async fn get_prepared_liquidation_stmt(&self) -> Result<Statement, DbError> {
+        let insert_liquidation = self.insert_into_liquidations_stmt();
+        Ok(self.client.prepare(&insert_liquidation).await?)
+    }
+
     async fn get_prepared_eval_stmt(&self) -> Result<Statement, DbError> {
         let insert_eval = self.insert_into_table_name_stmt();
         Ok(self.client.prepare(&insert_eval).await?)
     }
 
     async fn get_prepared_stmts(&self) -> Result<PreparedInsertStatements, DbError> {
-        let (insert_evaluation_stmt, insert_call_stmt, insert_event_log_stmt) = futures::try_join!(
+        let (
+            insert_evaluation_stmt,
+            insert_call_stmt,
+            insert_event_log_stmt,
+            insert_arbitrage_leg_stmt,
+            insert_liquidation_stmt,
+        ) = futures::try_join!(
             self.get_prepared_eval_stmt(),
             self.get_prepared_call_stmt(),
-            self.get_prepared_log_stmt()
+            self.get_prepared_log_stmt(),
+            self.get_prepared_arbitrage_leg_stmt(),
+            self.get_prepared_liquidation_stmt()
         )?;
         Ok(PreparedInsertStatements {
             insert_evaluation_stmt,
             insert_call_stmt,
             insert_event_log_stmt,
+            insert_arbitrage_leg_stmt,
+            insert_liquidation_stmt,
         })
     }
 
-    /// Creates a new table for the MEV data
+    /// Creates the tables for the MEV data and the arbitrages and liquidations it refers to
     pub async fn create(&self) -> Result<(), DbError> {
         self.client
             .batch_execute(&format!(
-                "CREATE TABLE IF NOT EXISTS {} (
+                "CREATE TABLE IF NOT EXISTS {0} (
                     hash text PRIMARY KEY,
                     status text,
 
@@ -224,6 +344,19 @@ impl MevDB {
                     gas_price NUMERIC,
                     gas_used NUMERIC,
                     revenue NUMERIC,
+                    gas_cost NUMERIC,
+                    coinbase_transfers NUMERIC,
+                    net_profit NUMERIC,
+                    base_fee NUMERIC,
+                    max_fee NUMERIC,
+                    max_priority_fee NUMERIC,
+                    burned_fee NUMERIC,
+                    miner_tip NUMERIC,
+
+                    backrun_of text,
+                    bundle_index NUMERIC,
+                    bundle_type text,
+                    warnings text[],
 
                     protocols text[],
                     actions text[],
@@ -234,9 +367,42 @@ impl MevDB {
 
                     transaction_position NUMERIC,
 
+                    chain_id NUMERIC NOT NULL DEFAULT 1,
+
                     inserted_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+                );
+
+                CREATE TABLE IF NOT EXISTS {1} (
+                    transaction_hash text NOT NULL REFERENCES {0} (hash) ON UPDATE CASCADE ON DELETE CASCADE,
+                    arbitrage_index NUMERIC NOT NULL,
+                    leg_index NUMERIC NOT NULL,
+                    pool text NOT NULL,
+                    protocol text,
+                    token_in text NOT NULL,
+                    amount_in NUMERIC NOT NULL,
+                    token_out text NOT NULL,
+                    amount_out NUMERIC NOT NULL,
+
+                    PRIMARY KEY (transaction_hash, arbitrage_index, leg_index)
+                );
+
+                CREATE TABLE IF NOT EXISTS {2} (
+                    transaction_hash text NOT NULL REFERENCES {0} (hash) ON UPDATE CASCADE ON DELETE CASCADE,
+                    liquidation_index NUMERIC NOT NULL,
+                    protocol text,
+                    debt_token text NOT NULL,
+                    debt_amount NUMERIC NOT NULL,
+                    collateral_token text NOT NULL,
+                    collateral_amount NUMERIC NOT NULL,
+                    liquidated_user text NOT NULL,
+                    debt_value NUMERIC,
+                    collateral_value NUMERIC,
+                    bonus NUMERIC,
+                    price_source text NOT NULL,
+
+                    PRIMARY KEY (transaction_hash, liquidation_index)
                 )",
-                self.table_name
+                self.table_name, DEFAULT_ARBITRAGE_LEGS_TABLE, DEFAULT_LIQUIDATIONS_TABLE
             ))
             .await?;
         Ok(())
@@ -306,8 +472,12 @@ impl MevDB {
         Ok(self
             .client
             .query_one(
-                format!("SELECT MAX(block_number) FROM {}", self.table_name).as_str(),
-                &[],
+                format!(
+                    "SELECT MAX(block_number) FROM {} WHERE chain_id = $1",
+                    self.table_name
+                )
+                .as_str(),
+                &[&Decimal::from(self.chain_id)],
             )
             .await?
             .get::<_, Decimal>(0)
@@ -320,8 +490,12 @@ impl MevDB {
         Ok(self
             .client
             .query_one(
-                format!("SELECT MIN(block_number) FROM {}", self.table_name).as_str(),
-                &[],
+                format!(
+                    "SELECT MIN(block_number) FROM {} WHERE chain_id = $1",
+                    self.table_name
+                )
+                .as_str(),
+                &[&Decimal::from(self.chain_id)],
             )
             .await?
             .get::<_, Decimal>(0)
@@ -384,6 +558,52 @@ impl MevDB {
             .await
     }
 
+    /// Returns the legs of all arbitrages within a transaction, ordered by
+    /// arbitrage and leg
+    pub async fn select_arbitrage_legs_in_tx(
+        &self,
+        tx: TxHash,
+    ) -> Result<Vec<Vec<ArbitrageLeg>>, DbError> {
+        let rows = self
+            .client
+            .query(
+                format!(
+                    "SELECT * FROM {} WHERE transaction_hash = $1 ORDER BY arbitrage_index, leg_index",
+                    DEFAULT_ARBITRAGE_LEGS_TABLE
+                )
+                .as_str(),
+                &[&format!("{:?}", tx)],
+            )
+            .await?;
+        let mut arbitrages = BTreeMap::<u64, Vec<ArbitrageLeg>>::new();
+        for row in &rows {
+            arbitrages
+                .entry(row.try_get_u64("arbitrage_index")?)
+                .or_default()
+                .push(ArbitrageLeg::from_row(row)?);
+        }
+        Ok(arbitrages.into_values().collect())
+    }
+
+    /// Returns the liquidations within a transaction
+    pub async fn select_liquidations_in_tx(
+        &self,
+        tx: TxHash,
+    ) -> Result<Vec<LiquidationRecord>, DbError> {
+        let rows = self
+            .client
+            .query(
+                format!(
+                    "SELECT * FROM {} WHERE transaction_hash = $1 ORDER BY liquidation_index",
+                    DEFAULT_LIQUIDATIONS_TABLE
+                )
+                .as_str(),
+                &[&format!("{:?}", tx)],
+            )
+            .await?;
+        rows.iter().map(LiquidationRecord::from_row).collect()
+    }
+
     /// Returns all internal calls within a transaction
     pub async fn select_logs_in_tx(&self, tx: TxHash) -> Result<Vec<EventLog>, DbError> {
         self.select_logs_where(&format!("transaction_hash = '{:?}'", tx))
@@ -397,8 +617,9 @@ impl MevDB {
         self.client
             .query(
                 format!(
-                    "SELECT * FROM {} WHERE {}",
+                    "SELECT * FROM {} WHERE chain_id = {} AND ({})",
                     self.table_name,
+                    self.chain_id,
                     stmt.trim_start_matches("WHERE ")
                 )
                 .as_str(),
@@ -534,6 +755,105 @@ impl MevDB {
         Ok(())
     }
 
+    /// Insert a single `Pair`
+    pub async fn insert_pair(&self, pair: &Pair) -> Result<(), DbError> {
+        self.client
+            .execute(
+                format!(
+                    "INSERT INTO {} (
+                        address,
+                        protocol,
+                        factory,
+                        token0,
+                        token1,
+                        fee,
+                        block_number,
+                        chain_id
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    {}",
+                    DEFAULT_PAIRS_TABLE, self.on_conflict
+                )
+                .as_str(),
+                &[
+                    &format!("{:?}", pair.address),
+                    &pair.protocol.to_string(),
+                    &format!("{:?}", pair.factory),
+                    &format!("{:?}", pair.token0),
+                    &format!("{:?}", pair.token1),
+                    &Decimal::from(pair.fee),
+                    &Decimal::from(pair.block_number),
+                    &Decimal::from(self.chain_id),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns all indexed pairs
+    pub async fn select_pairs(&self) -> Result<Vec<Pair>, DbError> {
+        self.query(
+            format!(
+                "SELECT * FROM {} WHERE chain_id = {}",
+                DEFAULT_PAIRS_TABLE, self.chain_id
+            )
+            .as_str(),
+        )
+        .await
+    }
+
+    /// Returns the block of the latest pair that was indexed for the factory
+    pub async fn latest_pair_block(&self, factory: Address) -> Result<Option<u64>, DbError> {
+        let block: Option<Decimal> = self
+            .client
+            .query_one(
+                format!(
+                    "SELECT MAX(block_number) FROM {} WHERE factory = $1 AND chain_id = $2",
+                    DEFAULT_PAIRS_TABLE
+                )
+                .as_str(),
+                &[&format!("{:?}", factory), &Decimal::from(self.chain_id)],
+            )
+            .await?
+            .try_get(0)?;
+        Ok(block.map(|block| block.to_u64().expect("block number stored as u64; qed")))
+    }
+
+    /// Returns the last block whose logs were scanned for pairs of the factory
+    pub async fn pair_cursor(&self, factory: Address) -> Result<Option<u64>, DbError> {
+        let row = self
+            .client
+            .query_opt(
+                format!(
+                    "SELECT block_number FROM {} WHERE factory = $1 AND chain_id = $2",
+                    DEFAULT_PAIR_CURSORS_TABLE
+                )
+                .as_str(),
+                &[&format!("{:?}", factory), &Decimal::from(self.chain_id)],
+            )
+            .await?;
+        Ok(row.map(|row| row.try_get_u64("block_number")).transpose()?)
+    }
+
+    /// Stores the last block whose logs were scanned for pairs of the factory
+    pub async fn update_pair_cursor(&self, factory: Address, block: u64) -> Result<(), DbError> {
+        self.client
+            .execute(
+                format!(
+                    "INSERT INTO {} (chain_id, factory, block_number) VALUES ($1, $2, $3)
+                    ON CONFLICT (chain_id, factory) DO UPDATE SET block_number = EXCLUDED.block_number",
+                    DEFAULT_PAIR_CURSORS_TABLE
+                )
+                .as_str(),
+                &[
+                    &Decimal::from(self.chain_id),
+                    &format!("{:?}", factory),
+                    &Decimal::from(block),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
     async fn insert_with_statements(
         &self,
         evaluation: &Evaluation,
@@ -543,6 +863,8 @@ impl MevDB {
             insert_evaluation_stmt,
             insert_call_stmt,
             insert_event_log_stmt,
+            insert_arbitrage_leg_stmt,
+            insert_liquidation_stmt,
         } = stmts;
 
         self.client
@@ -565,10 +887,64 @@ impl MevDB {
                         .map(|x| format!("{:?}", x))
                         .unwrap_or_else(|| "".to_owned()),
                     &Decimal::from(evaluation.tx.transaction_position),
+                    &Decimal::from(self.chain_id),
+                    &u256_decimal(evaluation.gas_cost)?,
+                    &u256_decimal(evaluation.coinbase_transfers)?,
+                    &Decimal::from_str(&evaluation.net_profit.to_string())?,
+                    &evaluation.base_fee.map(u256_decimal).transpose()?,
+                    &evaluation.max_fee.map(u256_decimal).transpose()?,
+                    &evaluation.max_priority_fee.map(u256_decimal).transpose()?,
+                    &u256_decimal(evaluation.burned_fee)?,
+                    &u256_decimal(evaluation.miner_tip)?,
+                    &evaluation.backrun_of.map(|hash| format!("{:?}", hash)),
+                    &evaluation.bundle_index.map(Decimal::from),
+                    &evaluation.bundle_type.map(|ty| ty.to_string()),
+                    &evaluation
+                        .warnings
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>(),
                 ],
             )
             .await?;
 
+        let legs_fut = future::try_join_all(
+            evaluation
+                .tx
+                .actions()
+                .arbitrage()
+                .enumerate()
+                .flat_map(|(arbitrage_index, arbitrage)| {
+                    arbitrage
+                        .legs
+                        .iter()
+                        .enumerate()
+                        .map(move |(leg_index, leg)| (arbitrage_index, leg_index, leg))
+                })
+                .map(|(arbitrage_index, leg_index, leg)| {
+                    self.insert_arbitrage_leg_with_statement(
+                        insert_arbitrage_leg_stmt,
+                        evaluation.tx.hash,
+                        arbitrage_index,
+                        leg_index,
+                        leg,
+                    )
+                }),
+        );
+        legs_fut.await?;
+
+        future::try_join_all(evaluation.liquidations.iter().enumerate().map(
+            |(liquidation_index, liquidation)| {
+                self.insert_liquidation_with_statement(
+                    insert_liquidation_stmt,
+                    evaluation.tx.hash,
+                    liquidation_index,
+                    liquidation,
+                )
+            },
+        ))
+        .await?;
+
         let (calls_fut, logs_fut) =
             match self.insert_filter {
                 InsertFilter::EvaluationOnly => return Ok(()),
@@ -611,6 +987,65 @@ impl MevDB {
         Ok(())
     }
 
+    async fn insert_arbitrage_leg_with_statement(
+        &self,
+        stmt: &Statement,
+        hash: TxHash,
+        arbitrage_index: usize,
+        leg_index: usize,
+        leg: &ArbitrageLeg,
+    ) -> Result<(), DbError> {
+        self.client
+            .execute(
+                stmt,
+                &[
+                    &format!("{:?}", hash),
+                    &Decimal::from(arbitrage_index),
+                    &Decimal::from(leg_index),
+                    &format!("{:?}", leg.pool),
+                    &leg.protocol.map(|proto| proto.to_string()),
+                    &format!("{:?}", leg.token_in),
+                    &u256_decimal(leg.amount_in)?,
+                    &format!("{:?}", leg.token_out),
+                    &u256_decimal(leg.amount_out)?,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_liquidation_with_statement(
+        &self,
+        stmt: &Statement,
+        hash: TxHash,
+        liquidation_index: usize,
+        liquidation: &LiquidationRecord,
+    ) -> Result<(), DbError> {
+        self.client
+            .execute(
+                stmt,
+                &[
+                    &format!("{:?}", hash),
+                    &Decimal::from(liquidation_index),
+                    &liquidation.protocol.map(|proto| proto.to_string()),
+                    &format!("{:?}", liquidation.debt_token),
+                    &u256_decimal(liquidation.debt_amount)?,
+                    &format!("{:?}", liquidation.collateral_token),
+                    &u256_decimal(liquidation.collateral_amount)?,
+                    &format!("{:?}", liquidation.liquidated_user),
+                    &liquidation.debt_value.map(u256_decimal).transpose()?,
+                    &liquidation.collateral_value.map(u256_decimal).transpose()?,
+                    &liquidation
+                        .bonus()
+                        .map(|bonus| Decimal::from_str(&bonus.to_string()))
+                        .transpose()?,
+                    &liquidation.price_source.to_string(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Inserts data from this evaluation to PostGres
     pub async fn insert(&self, evaluation: &Evaluation) -> Result<(), DbError> {
         if let Some(ref stmts) = self.prepared_statements {
@@ -644,11 +1079,14 @@ impl MevDB {
             .client
             .query(
                 format!(
-                    "SELECT block_number FROM {} WHERE block_number = $1 LIMIT 1;",
+                    "SELECT block_number FROM {} WHERE block_number = $1 AND chain_id = $2 LIMIT 1;",
                     self.table_name
                 )
                 .as_str(),
-                &[&Decimal::from_u64(block).ok_or(DbError::InvalidDecimal)?],
+                &[
+                    &Decimal::from_u64(block).ok_or(DbError::InvalidDecimal)?,
+                    &Decimal::from(self.chain_id),
+                ],
             )
             .await?;
         if rows.get(0).is_some() {
@@ -863,8 +1301,10 @@ fn u256_decimal(src: U256) -> Result<Decimal, rust_decimal::Error> {
 mod tests {
     use std::collections::HashSet;
 
+    use crate::addresses::WETH;
     use crate::test_helpers::{get_tx, test_inspector};
-    use crate::types::evaluation::ActionType;
+    use crate::types::evaluation::{ActionType, EvaluationWarning};
+    use crate::PriceSource;
 
     use super::*;
 
@@ -889,13 +1329,39 @@ mod tests {
             .cloned()
             .collect::<HashSet<_>>();
 
+        let gas_used = U256::from(21000);
+        let gas_price = U256::from(100e9 as u64);
+        let profit = U256::from(1e18 as u64);
         Evaluation {
             protocols: tx.protocols(),
             tx,
-            gas_used: 21000.into(),
-            gas_price: (100e9 as u64).into(),
+            gas_used,
+            gas_price,
             actions,
-            profit: (1e18 as u64).into(),
+            profit,
+            base_fee: None,
+            max_fee: None,
+            max_priority_fee: None,
+            gas_cost: gas_used * gas_price,
+            burned_fee: 0.into(),
+            miner_tip: gas_used * gas_price,
+            coinbase_transfers: 0.into(),
+            net_profit: 1e18 as i128 - 21000 * 100e9 as i128,
+            backrun_of: None,
+            bundle_index: None,
+            bundle_type: None,
+            warnings: vec![EvaluationWarning::SentinelAmount { token: *WETH }],
+            liquidations: vec![LiquidationRecord {
+                protocol: Some(Protocol::Aave),
+                debt_token: *WETH,
+                debt_amount: 100.into(),
+                collateral_token: *WETH,
+                collateral_amount: 105.into(),
+                liquidated_user: Address::zero(),
+                debt_value: Some(100.into()),
+                collateral_value: None,
+                price_source: PriceSource::Oracle,
+            }],
         }
     }
 
@@ -914,11 +1380,34 @@ mod tests {
             .unwrap();
         assert_eq!(evals.len(), 1);
         assert_eq!(evals[&evaluation.tx.block_number].len(), 1);
+        assert_eq!(
+            evals[&evaluation.tx.block_number][0].warnings,
+            evaluation.warnings
+        );
         let _ = client
             .select_internal_calls_in_tx(evaluation.tx.hash)
             .await
             .unwrap();
         let _ = client.select_logs_in_tx(evaluation.tx.hash).await.unwrap();
+        let liquidations = client
+            .select_liquidations_in_tx(evaluation.tx.hash)
+            .await
+            .unwrap();
+        assert_eq!(liquidations, evaluation.liquidations);
+        assert_eq!(liquidations[0].bonus(), None);
+        let legs = client
+            .select_arbitrage_legs_in_tx(evaluation.tx.hash)
+            .await
+            .unwrap();
+        assert_eq!(
+            legs,
+            evaluation
+                .tx
+                .actions()
+                .arbitrage()
+                .map(|arbitrage| arbitrage.legs.clone())
+                .collect::<Vec<_>>()
+        );
 
         client.revert_migration().await.unwrap();
     }
@@ -954,6 +1443,7 @@ mod tests {
         assert_eq!(selected.len(), 1);
         assert_eq!(selected[0].tx.hash, evaluation.tx.hash);
         assert_eq!(&selected[0].actions, &evaluation.actions);
+        assert_eq!(selected[0].net_profit, evaluation.net_profit);
 
         for proto in evaluation.protocols.iter().cloned() {
             let selected = client.select_where_protocols(&[proto]).await.unwrap();
@@ -35,10 +35,18 @@ pub(crate) trait FromSqlExt {
     where
         I: RowIndex + fmt::Display;
 
+    fn try_get_opt_u256<I>(&self, idx: I) -> Result<Option<U256>, DbError>
+    where
+        I: RowIndex + fmt::Display;
+
     fn try_get_h256<I>(&self, idx: I) -> Result<H256, DbError>
     where
         I: RowIndex + fmt::Display;
 
+    fn try_get_opt_h256<I>(&self, idx: I) -> Result<Option<H256>, DbError>
+    where
+        I: RowIndex + fmt::Display;
+
     fn try_get_u64<I>(&self, idx: I) -> Result<u64, DbError>
     where
         I: RowIndex + fmt::Display;
@@ -46,6 +54,10 @@ pub(crate) trait FromSqlExt {
     fn try_get_usize<I>(&self, idx: I) -> Result<usize, DbError>
     where
         I: RowIndex + fmt::Display;
+
+    fn try_get_i128<I>(&self, idx: I) -> Result<i128, DbError>
+    where
+        I: RowIndex + fmt::Display;
 }
 
 impl FromSqlExt for Row {
@@ -65,6 +77,17 @@ impl FromSqlExt for Row {
             .map_err(|err| DbError::FromSqlError(err.to_string()))
     }
 
+    fn try_get_opt_u256<I>(&self, idx: I) -> Result<Option<U256>, DbError>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        let value: Option<Decimal> = self.try_get(idx)?;
+        value
+            .map(|value| U256::from_str_radix(&value.to_string(), 10))
+            .transpose()
+            .map_err(|err| DbError::FromSqlError(err.to_string()))
+    }
+
     fn try_get_h256<I>(&self, idx: I) -> Result<H256, DbError>
     where
         I: RowIndex + fmt::Display,
@@ -72,6 +95,17 @@ impl FromSqlExt for Row {
         H256::from_str(self.try_get(idx)?).map_err(|err| DbError::FromSqlError(err.to_string()))
     }
 
+    fn try_get_opt_h256<I>(&self, idx: I) -> Result<Option<H256>, DbError>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        let value: Option<&str> = self.try_get(idx)?;
+        value
+            .map(H256::from_str)
+            .transpose()
+            .map_err(|err| DbError::FromSqlError(err.to_string()))
+    }
+
     fn try_get_u64<I>(&self, idx: I) -> Result<u64, DbError>
     where
         I: RowIndex + fmt::Display,
@@ -91,6 +125,16 @@ impl FromSqlExt for Row {
             .to_usize()
             .ok_or_else(|| DbError::FromSqlError("Failed to convert decimal to usize".to_string()))
     }
+
+    fn try_get_i128<I>(&self, idx: I) -> Result<i128, DbError>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        let value: Decimal = self.try_get(idx)?;
+        value
+            .to_i128()
+            .ok_or_else(|| DbError::FromSqlError("Failed to convert decimal to i128".to_string()))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Hash, ToSql, FromSql)]
@@ -121,6 +165,8 @@ pub enum CallClassification {
     Swap,
     #[postgres(name = "flashswap")]
     FlashSwap,
+    #[postgres(name = "flashloan")]
+    FlashLoan,
 }
 
 impl CallClassification {
@@ -162,6 +208,7 @@ impl FromStr for CallClassification {
             "repay" => Ok(CallClassification::Repay),
             "swap" => Ok(CallClassification::Swap),
             "flashswap" => Ok(CallClassification::FlashSwap),
+            "flashloan" => Ok(CallClassification::FlashLoan),
             s => Err(format!("`{}` is not a valid action type", s)),
         }
     }
@@ -0,0 +1,280 @@
+//! Chain specific configuration
+//!
+//! The inspectors were written against Ethereum mainnet, but the same
+//! protocols (or forks of them) are deployed on other EVM chains. A `Network`
+//! bundles everything that differs between these chains: the wrapped native
+//! token, the router used for price quotes, the deployments of lending
+//! protocols and the factories of AMM pairs.
+use crate::{
+    addresses::parse_address,
+    pairs::{Factory, FactoryKind},
+    types::Protocol,
+};
+
+use ethers::types::Address;
+use once_cell::sync::OnceCell;
+use std::fmt;
+use std::str::FromStr;
+
+static NETWORK: OnceCell<Network> = OnceCell::new();
+
+/// Returns the network in use.
+///
+/// Unless another network was installed with `Network::install` before the
+/// first call, this is mainnet.
+pub fn network() -> &'static Network {
+    NETWORK.get_or_init(Network::mainnet)
+}
+
+/// The addresses of a Compound deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompoundConfig {
+    pub comptroller: Address,
+    pub oracle: Address,
+    /// The cToken of the native currency
+    pub cether: Address,
+}
+
+/// A chain and the protocol deployments on it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    /// The name used to select the network and its address registry
+    pub name: String,
+    pub chain_id: u64,
+    /// The wrapped native currency, e.g. WETH or WMATIC
+    pub wrapped_native: Address,
+    /// The Uniswap V2 style router that is used to quote historical prices
+    pub price_router: Address,
+    /// Tokens to route a price quote through if there is no direct pair with
+    /// the wrapped native currency
+    pub price_hops: Vec<Address>,
+    /// Compound (or a fork of it)
+    pub compound: Option<CompoundConfig>,
+    /// The Aave lending pool
+    pub aave_lending_pool: Option<Address>,
+    /// The Curve registry
+    pub curve_registry: Option<Address>,
+    /// The Curve factory of permissionless (meta)pools
+    pub curve_factory: Option<Address>,
+    /// The factories of AMM pairs
+    pub factories: Vec<Factory>,
+}
+
+impl Network {
+    /// Ethereum mainnet
+    pub fn mainnet() -> Self {
+        Self {
+            name: "mainnet".to_string(),
+            chain_id: 1,
+            wrapped_native: parse_address("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            price_router: parse_address("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"),
+            price_hops: vec![
+                // USDC
+                parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                // DAI
+                parse_address("0x6b175474e89094c44da98b954eedeac495271d0f"),
+            ],
+            compound: Some(CompoundConfig {
+                comptroller: parse_address("0x3d9819210a31b4961b30ef54be2aed79b9c9cd3b"),
+                oracle: parse_address("0x922018674c12a7f0d394ebeef9b58f186cde13c1"),
+                cether: parse_address("0x4ddc2d193948926d02f9b1fe9e1daa0718270ed5"),
+            }),
+            aave_lending_pool: Some(parse_address("0x398ec7346dcd622edc5ae82352f02be94c62d119")),
+            curve_registry: Some(parse_address("0x7D86446dDb609eD0F5f8684AcF30380a356b2B4c")),
+            curve_factory: Some(parse_address("0x0959158b6040D32d04c301A72CBFD6b39E21c9AE")),
+            factories: vec![
+                Factory::new(
+                    parse_address("0xc0a47dfe034b400b47bdad5fecda2621de6c4d95"),
+                    FactoryKind::UniswapV1,
+                    Protocol::UniswapV1,
+                    6_627_917,
+                ),
+                Factory::new(
+                    parse_address("0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"),
+                    FactoryKind::UniswapV2,
+                    Protocol::UniswapV2,
+                    10_000_835,
+                ),
+                Factory::new(
+                    parse_address("0xc0aee478e3658e2610c5f7a4a2e1777ce9e4f2ac"),
+                    FactoryKind::UniswapV2,
+                    Protocol::Sushiswap,
+                    10_794_229,
+                ),
+                Factory::new(
+                    parse_address("0x1f98431c8ad98523631ae4a59f21b0eb31c2f984"),
+                    FactoryKind::UniswapV3,
+                    Protocol::UniswapV3,
+                    12_369_621,
+                ),
+            ],
+        }
+    }
+
+    /// Polygon PoS, prices are quoted via QuickSwap
+    pub fn polygon() -> Self {
+        Self {
+            name: "polygon".to_string(),
+            chain_id: 137,
+            wrapped_native: parse_address("0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270"),
+            price_router: parse_address("0xa5e0829caced8ffdd4de3c43696c57f7d7a678ff"),
+            price_hops: vec![
+                // USDC
+                parse_address("0x2791bca1f2de4661ed88a30c99a7a9449aa84174"),
+                // WETH
+                parse_address("0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"),
+            ],
+            compound: None,
+            aave_lending_pool: None,
+            curve_registry: None,
+            curve_factory: None,
+            factories: vec![
+                // QuickSwap
+                Factory::new(
+                    parse_address("0x5757371414417b8c6caad45baef941abc7d3ab32"),
+                    FactoryKind::UniswapV2,
+                    Protocol::Uniswappy,
+                    4_931_780,
+                ),
+                Factory::new(
+                    parse_address("0xc35dadb65012ec5796536bd9864ed8773abc74c4"),
+                    FactoryKind::UniswapV2,
+                    Protocol::Sushiswap,
+                    11_333_218,
+                ),
+            ],
+        }
+    }
+
+    /// Arbitrum One, prices are quoted via Sushiswap
+    pub fn arbitrum() -> Self {
+        Self {
+            name: "arbitrum".to_string(),
+            chain_id: 42161,
+            wrapped_native: parse_address("0x82af49447d8a07e3bd95bd0d56f35241523fbab1"),
+            price_router: parse_address("0x1b02da8cb0d097eb8d57a175b88c7d8b47997506"),
+            price_hops: vec![
+                // USDC
+                parse_address("0xff970a61a04b1ca14834a43f5de4533ebddb5cc8"),
+            ],
+            compound: None,
+            aave_lending_pool: None,
+            curve_registry: None,
+            curve_factory: None,
+            factories: vec![
+                Factory::new(
+                    parse_address("0xc35dadb65012ec5796536bd9864ed8773abc74c4"),
+                    FactoryKind::UniswapV2,
+                    Protocol::Sushiswap,
+                    70,
+                ),
+                Factory::new(
+                    parse_address("0x1f98431c8ad98523631ae4a59f21b0eb31c2f984"),
+                    FactoryKind::UniswapV3,
+                    Protocol::UniswapV3,
+                    165,
+                ),
+            ],
+        }
+    }
+
+    /// Binance Smart Chain, prices are quoted via PancakeSwap and Venus is a Compound fork
+    pub fn bsc() -> Self {
+        Self {
+            name: "bsc".to_string(),
+            chain_id: 56,
+            wrapped_native: parse_address("0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c"),
+            price_router: parse_address("0x10ed43c718714eb63d5aa57b78b54704e256024e"),
+            price_hops: vec![
+                // BUSD
+                parse_address("0xe9e7cea3dedca5984780bafc599bd69add087d56"),
+            ],
+            compound: Some(CompoundConfig {
+                comptroller: parse_address("0xfd36e2c2a6789db23113685031d7f16329158384"),
+                oracle: parse_address("0xd8b6da2bfec71d684d3e2a2fc9492ddad5c3787f"),
+                cether: parse_address("0xa07c5b74c9b40447a954e1466938b865b6bbea36"),
+            }),
+            aave_lending_pool: None,
+            curve_registry: None,
+            curve_factory: None,
+            factories: vec![
+                // PancakeSwap V2
+                Factory::new(
+                    parse_address("0xca143ce32fe78f1f7019d7d551a6402fc5350c73"),
+                    FactoryKind::UniswapV2,
+                    Protocol::Uniswappy,
+                    6_809_737,
+                ),
+            ],
+        }
+    }
+
+    /// All supported networks
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::mainnet(),
+            Self::polygon(),
+            Self::arbitrum(),
+            Self::bsc(),
+        ]
+    }
+
+    /// The network with the given chain id
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|network| network.chain_id == chain_id)
+    }
+
+    /// Makes this the network returned by `network()`.
+    ///
+    /// Returns the network back if there is already one in use.
+    pub fn install(self) -> Result<(), Self> {
+        NETWORK.set(self)
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.chain_id)
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    /// Parses the network by its name or its chain id
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let network = if let Ok(chain_id) = s.parse::<u64>() {
+            Self::from_chain_id(chain_id)
+        } else {
+            Self::all().into_iter().find(|network| network.name == s)
+        };
+        network.ok_or_else(|| format!("`{}` is not a supported network", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::{COMPTROLLER, CURVE_REGISTRY, WETH};
+
+    #[test]
+    fn parse_network() {
+        assert_eq!(Network::from_str("mainnet").unwrap(), Network::mainnet());
+        assert_eq!(Network::from_str("137").unwrap(), Network::polygon());
+        assert_eq!(
+            Network::from_chain_id(42161).unwrap().name,
+            Network::arbitrum().name
+        );
+        assert!(Network::from_str("ropsten").is_err());
+    }
+
+    #[test]
+    fn mainnet_matches_addresses() {
+        let mainnet = Network::mainnet();
+        assert_eq!(mainnet.wrapped_native, *WETH);
+        assert_eq!(mainnet.compound.unwrap().comptroller, *COMPTROLLER);
+        assert_eq!(mainnet.curve_registry, Some(*CURVE_REGISTRY));
+    }
+}
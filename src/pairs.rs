@@ -0,0 +1,377 @@
+//! Discovery of AMM pairs from the events of their factories
+//!
+//! Every Uniswap V1 exchange, Uniswap V2 / Sushiswap pair and Uniswap V3 pool
+//! is deployed by a factory which emits an event with the new contract and its
+//! tokens. The `PairIndexer` collects these events incrementally, so that pairs
+//! which are missing from the embedded CSVs are attributed to their protocol
+//! instead of `Uniswappy`.
+use crate::{
+    addresses::{AddressRegistry, PoolTokens, ETH},
+    mevdb::DbError,
+    model::{FromSqlExt, SqlRowExt},
+    network::network,
+    types::Protocol,
+};
+
+use ethers::{
+    abi::RawLog,
+    contract::{abigen, EthEvent, EthLogDecode},
+    providers::Middleware,
+    types::{Address, Filter, Log, H256},
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio_postgres::Row;
+
+abigen!(
+    UniFactoryV1,
+    r#"[
+        event NewExchange(address indexed token, address indexed exchange)
+    ]"#
+);
+
+abigen!(
+    UniFactoryV2,
+    r#"[
+        event PairCreated(address indexed token0, address indexed token1, address pair, uint256 index)
+    ]"#
+);
+
+abigen!(
+    UniFactoryV3,
+    r#"[
+        event PoolCreated(address indexed token0, address indexed token1, uint24 indexed fee, int24 tickSpacing, address pool)
+    ]"#
+);
+
+/// The fee of Uniswap V1/V2 and Sushiswap pairs in hundredths of a bip
+pub const DEFAULT_FEE: u32 = 3000;
+
+/// How many blocks to request logs for at once
+const DEFAULT_BLOCK_STEP: u64 = 10_000;
+
+/// The event interface of a factory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactoryKind {
+    /// Emits `NewExchange(token, exchange)`, all exchanges trade against ETH
+    UniswapV1,
+    /// Emits `PairCreated(token0, token1, pair, index)`
+    UniswapV2,
+    /// Emits `PoolCreated(token0, token1, fee, tickSpacing, pool)`
+    UniswapV3,
+}
+
+impl FactoryKind {
+    /// The topic of the event that is emitted for every new pair
+    pub fn signature(&self) -> H256 {
+        match self {
+            FactoryKind::UniswapV1 => NewExchangeFilter::signature(),
+            FactoryKind::UniswapV2 => PairCreatedFilter::signature(),
+            FactoryKind::UniswapV3 => PoolCreatedFilter::signature(),
+        }
+    }
+}
+
+/// A contract which deploys the pairs of a protocol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factory {
+    pub address: Address,
+    pub kind: FactoryKind,
+    /// The protocol of all pairs deployed by this factory
+    pub protocol: Protocol,
+    /// The block the factory was deployed at
+    pub deployed_at: u64,
+}
+
+impl Factory {
+    pub fn new(address: Address, kind: FactoryKind, protocol: Protocol, deployed_at: u64) -> Self {
+        Self {
+            address,
+            kind,
+            protocol,
+            deployed_at,
+        }
+    }
+
+    /// Decodes the pair if the log is a creation event of this factory
+    pub fn decode_pair(&self, log: &Log) -> Option<Pair> {
+        if log.address != self.address {
+            return None;
+        }
+        let raw_log = RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        };
+        let (address, token0, token1, fee) = match self.kind {
+            FactoryKind::UniswapV1 => {
+                let event = NewExchangeFilter::decode_log(&raw_log).ok()?;
+                (event.exchange, event.token, *ETH, DEFAULT_FEE)
+            }
+            FactoryKind::UniswapV2 => {
+                let event = PairCreatedFilter::decode_log(&raw_log).ok()?;
+                (event.pair, event.token_0, event.token_1, DEFAULT_FEE)
+            }
+            FactoryKind::UniswapV3 => {
+                let event = PoolCreatedFilter::decode_log(&raw_log).ok()?;
+                (event.pool, event.token_0, event.token_1, event.fee)
+            }
+        };
+        Some(Pair {
+            address,
+            protocol: self.protocol,
+            factory: self.address,
+            token0,
+            token1,
+            fee,
+            block_number: log.block_number?.as_u64(),
+        })
+    }
+}
+
+/// A pair (or pool) deployed by a factory
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub address: Address,
+    pub protocol: Protocol,
+    /// The factory which created the pair
+    pub factory: Address,
+    pub token0: Address,
+    /// `ETH` for Uniswap V1 exchanges
+    pub token1: Address,
+    /// The swap fee in hundredths of a bip
+    pub fee: u32,
+    /// The block the pair was created at
+    pub block_number: u64,
+}
+
+impl SqlRowExt for Pair {
+    fn from_row(row: &Row) -> Result<Self, DbError>
+    where
+        Self: Sized,
+    {
+        let protocol =
+            Protocol::from_str(row.try_get("protocol")?).map_err(DbError::FromSqlError)?;
+        Ok(Self {
+            address: row.try_get_address("address")?,
+            protocol,
+            factory: row.try_get_address("factory")?,
+            token0: row.try_get_address("token0")?,
+            token1: row.try_get_address("token1")?,
+            fee: row.try_get_u64("fee")? as u32,
+            block_number: row.try_get_u64("block_number")?,
+        })
+    }
+}
+
+impl Pair {
+    pub fn tokens(&self) -> PoolTokens {
+        PoolTokens {
+            token0: self.token0,
+            token1: self.token1,
+            fee: self.fee,
+        }
+    }
+}
+
+/// Adds all pairs and their tokens to the registry
+pub fn register_pairs<'a>(
+    registry: &mut AddressRegistry,
+    pairs: impl IntoIterator<Item = &'a Pair>,
+) {
+    for pair in pairs {
+        registry.insert_protocol(pair.address, pair.protocol);
+        registry.insert_pool(pair.address, pair.tokens());
+    }
+}
+
+/// Collects the pairs created by the factories
+pub struct PairIndexer<M> {
+    provider: Arc<M>,
+    factories: Vec<Factory>,
+    /// How many blocks to query logs for at once
+    step: u64,
+}
+
+impl<M: Middleware + 'static> PairIndexer<M> {
+    /// Indexes the pairs of all factories of the network in use
+    pub fn new(provider: Arc<M>) -> Self {
+        Self::with_factories(provider, network().factories.clone())
+    }
+
+    pub fn with_factories(provider: Arc<M>, factories: Vec<Factory>) -> Self {
+        Self {
+            provider,
+            factories,
+            step: DEFAULT_BLOCK_STEP,
+        }
+    }
+
+    /// Sets the number of blocks to request logs for at once
+    pub fn with_step(mut self, step: u64) -> Self {
+        self.step = step.max(1);
+        self
+    }
+
+    pub fn factories(&self) -> &[Factory] {
+        &self.factories
+    }
+
+    /// Returns all pairs the factory created within `[from..=to]`
+    pub async fn pairs_in_range(
+        &self,
+        factory: &Factory,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<Pair>, PairIndexerError<M>> {
+        let mut pairs = Vec::new();
+        let mut start = from.max(factory.deployed_at);
+        while start <= to {
+            let end = to.min(start + self.step - 1);
+            let filter = Filter::new()
+                .address(factory.address)
+                .topic0(factory.kind.signature())
+                .from_block(start)
+                .to_block(end);
+            let logs = self
+                .provider
+                .get_logs(&filter)
+                .await
+                .map_err(PairIndexerError::Provider)?;
+            pairs.extend(logs.iter().filter_map(|log| factory.decode_pair(log)));
+            log::debug!(
+                "indexed {} {} pairs up to block {}",
+                pairs.len(),
+                factory.protocol,
+                end
+            );
+            start = end + 1;
+        }
+        Ok(pairs)
+    }
+
+    /// Indexes all pairs created since the last scanned block of every factory
+    /// and inserts them into the database.
+    ///
+    /// Returns the newly found pairs.
+    pub async fn index(&self, db: &crate::MevDB) -> Result<Vec<Pair>, PairIndexerError<M>> {
+        let latest = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(PairIndexerError::Provider)?
+            .as_u64();
+
+        let mut pairs = Vec::new();
+        for factory in &self.factories {
+            let from = match db.pair_cursor(factory.address).await? {
+                Some(scanned) => scanned + 1,
+                // without a cursor only the block of the latest pair is known to be indexed,
+                // which may have been interrupted midway, so it's scanned again
+                None => db
+                    .latest_pair_block(factory.address)
+                    .await?
+                    .unwrap_or(factory.deployed_at),
+            };
+            let new_pairs = self.pairs_in_range(factory, from, latest).await?;
+            for pair in &new_pairs {
+                db.insert_pair(pair).await?;
+            }
+            db.update_pair_cursor(factory.address, latest).await?;
+            log::info!(
+                "indexed {} new pairs of factory {:?}",
+                new_pairs.len(),
+                factory.address
+            );
+            pairs.extend(new_pairs);
+        }
+        Ok(pairs)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PairIndexerError<M: Middleware + 'static> {
+    #[error(transparent)]
+    Provider(<M as Middleware>::Error),
+
+    #[error(transparent)]
+    DbError(#[from] DbError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::{parse_address, WETH};
+    use crate::network::Network;
+    use ethers::abi::{encode, Token};
+
+    fn creation_log(factory: &Factory, topics: Vec<H256>, data: Vec<Token>) -> Log {
+        Log {
+            address: factory.address,
+            topics,
+            data: encode(&data).into(),
+            block_number: Some(10_008_355u64.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decode_pair_created() {
+        let factory = Network::mainnet().factories[1].clone();
+        let usdc = parse_address("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let pair = parse_address("0xb4e16d0168e52d35cacd2c6185b44281ec28c9dc");
+        let log = creation_log(
+            &factory,
+            vec![
+                FactoryKind::UniswapV2.signature(),
+                H256::from(usdc),
+                H256::from(*WETH),
+            ],
+            vec![Token::Address(pair), Token::Uint(1.into())],
+        );
+
+        let decoded = factory.decode_pair(&log).unwrap();
+        assert_eq!(
+            decoded,
+            Pair {
+                address: pair,
+                protocol: Protocol::UniswapV2,
+                factory: factory.address,
+                token0: usdc,
+                token1: *WETH,
+                fee: DEFAULT_FEE,
+                block_number: 10_008_355,
+            }
+        );
+
+        // logs of other contracts are ignored
+        assert!(Network::mainnet().factories[2].decode_pair(&log).is_none());
+
+        let mut registry = AddressRegistry::default();
+        register_pairs(&mut registry, &[decoded]);
+        assert_eq!(registry.protocol(&pair), Some(Protocol::UniswapV2));
+        assert_eq!(registry.pool(&pair).unwrap().token0, usdc);
+    }
+
+    #[test]
+    fn decode_new_exchange() {
+        let factory = Network::mainnet().factories[0].clone();
+        let dai = parse_address("0x6b175474e89094c44da98b954eedeac495271d0f");
+        let exchange = parse_address("0x2a1530c4c41db0b0b2bb646cb5eb1a67b7158667");
+        let log = creation_log(
+            &factory,
+            vec![
+                FactoryKind::UniswapV1.signature(),
+                H256::from(dai),
+                H256::from(exchange),
+            ],
+            vec![],
+        );
+
+        let decoded = factory.decode_pair(&log).unwrap();
+        assert_eq!(decoded.address, exchange);
+        assert_eq!(decoded.protocol, Protocol::UniswapV1);
+        assert_eq!(decoded.token0, dai);
+        assert_eq!(decoded.token1, *ETH);
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(clippy::too_many_arguments)]
-use crate::addresses::{parse_address, ETH, WETH};
+use crate::addresses::{parse_address, ETH};
+use crate::network::{network, CompoundConfig, Network};
+use crate::types::{actions::Liquidation, Protocol};
 use ethers::{
     contract::{abigen, ContractError},
     providers::Middleware,
@@ -7,15 +9,98 @@ use ethers::{
     utils::WEI_IN_ETHER,
 };
 use once_cell::sync::Lazy;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
 
 // Generate type-safe bindings to Uniswap's router
 abigen!(Uniswap, "abi/unirouterv2.json");
 
+// The price oracles of the lending protocols
+abigen!(
+    CompoundOracle,
+    r#"[
+        function getUnderlyingPrice(address cToken) external view returns (uint256)
+    ]"#
+);
+abigen!(
+    CTokenExchangeRate,
+    r#"[
+        function exchangeRateStored() external view returns (uint256)
+    ]"#
+);
+abigen!(
+    AaveLendingPool,
+    r#"[
+        function addressesProvider() external view returns (address)
+    ]"#
+);
+abigen!(
+    AaveAddressesProvider,
+    r#"[
+        function getPriceOracle() external view returns (address)
+    ]"#
+);
+abigen!(
+    AaveOracle,
+    r#"[
+        function getAssetPrice(address asset) external view returns (uint256)
+    ]"#
+);
+abigen!(
+    TokenDecimals,
+    r#"[
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+/// Where the ETH value of an amount comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PriceSource {
+    /// The price router of the network
+    Uniswap,
+    /// The price oracle of the lending protocol
+    Oracle,
+}
+
+impl fmt::Display for PriceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+impl FromStr for PriceSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniswap" => Ok(PriceSource::Uniswap),
+            "oracle" => Ok(PriceSource::Oracle),
+            s => Err(format!("`{}` is not a valid price source", s)),
+        }
+    }
+}
+
+/// The ETH values of both legs of a liquidation
+pub struct LiquidationQuote<M: Middleware> {
+    /// The value of the repaid debt
+    pub debt: Result<U256, ContractError<M>>,
+    /// The value of the seized collateral
+    pub collateral: Result<U256, ContractError<M>>,
+    pub source: PriceSource,
+}
+
 /// Gets historical prices in ETH for any token via Uniswap.
 /// **Requires an archive node to work**
 pub struct HistoricalPrice<M> {
+    provider: Arc<M>,
     uniswap: Uniswap<M>,
+    /// The wrapped native currency prices are quoted in
+    wrapped_native: Address,
+    /// Intermediate tokens to try if there's no direct pair
+    hops: Vec<Address>,
+    /// Whether liquidations are valued with the lending protocol's oracle
+    lending_oracles: bool,
+    compound: Option<CompoundConfig>,
+    aave_lending_pool: Option<Address>,
 }
 
 static DECIMALS: Lazy<HashMap<Address, usize>> = Lazy::new(|| {
@@ -29,16 +114,32 @@ static DECIMALS: Lazy<HashMap<Address, usize>> = Lazy::new(|| {
 });
 
 impl<M: Middleware> HistoricalPrice<M> {
-    /// Instantiates a Unirouter
+    /// Instantiates the price router of the network in use
     pub fn new<T: Into<Arc<M>>>(provider: T) -> Self {
-        let unirouter: Address = "7a250d5630b4cf539739df2c5dacb4c659f2488d"
-            .parse()
-            .expect("cannot parse unirouter");
+        Self::with_network(provider, network())
+    }
+
+    /// Instantiates the price router of the network
+    pub fn with_network<T: Into<Arc<M>>>(provider: T, network: &Network) -> Self {
+        let provider = provider.into();
         Self {
-            uniswap: Uniswap::new(unirouter, provider.into()),
+            uniswap: Uniswap::new(network.price_router, provider.clone()),
+            provider,
+            wrapped_native: network.wrapped_native,
+            hops: network.price_hops.clone(),
+            lending_oracles: false,
+            compound: network.compound,
+            aave_lending_pool: network.aave_lending_pool,
         }
     }
 
+    /// Values the legs of Compound and Aave liquidations with the protocol's
+    /// own price oracle instead of the price router
+    pub fn with_lending_oracles(mut self) -> Self {
+        self.lending_oracles = true;
+        self
+    }
+
     /// Converts any token amount to ETH by querying historical Uniswap prices
     /// at a specific block
     pub async fn quote<T: Into<BlockNumber>, A: Into<U256>>(
@@ -50,7 +151,7 @@ impl<M: Middleware> HistoricalPrice<M> {
         let amount = amount.into();
 
         // assume price parity of WETH / ETH
-        if token == *ETH || token == *WETH {
+        if token == *ETH || token == self.wrapped_native {
             return Ok(amount);
         }
 
@@ -60,18 +161,195 @@ impl<M: Middleware> HistoricalPrice<M> {
             .map(|decimals| U256::from(10u64.pow(*decimals as u32)))
             .unwrap_or(WEI_IN_ETHER);
 
-        // ask uniswap how much we'd get from the TOKEN -> WETH path
-        let amounts = self
-            .uniswap
-            .get_amounts_out(one, vec![token, *WETH])
-            .block(block.into())
+        // ask uniswap how much we'd get from the TOKEN -> WETH path, and
+        // route through the hops if there's no direct pair
+        let block = block.into();
+        let direct = vec![token, self.wrapped_native];
+        let paths = std::iter::once(direct).chain(
+            self.hops
+                .iter()
+                .filter(|hop| **hop != token)
+                .map(|hop| vec![token, *hop, self.wrapped_native]),
+        );
+        let mut error = None;
+        for path in paths {
+            let len = path.len();
+            match self
+                .uniswap
+                .get_amounts_out(one, path)
+                .block(block)
+                .call()
+                .await
+            {
+                Ok(amounts) => {
+                    debug_assert_eq!(one, amounts[0]);
+                    debug_assert_eq!(amounts.len(), len);
+                    return Ok(amounts[len - 1] / one * amount);
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        Err(error.expect("there is at least one path"))
+    }
+
+    /// Converts both legs of the liquidation to ETH at a specific block.
+    ///
+    /// `market` is the contract the liquidation was called on, which is the
+    /// borrowed cToken for Compound.
+    pub async fn quote_liquidation(
+        &self,
+        liquidation: &Liquidation,
+        protocol: Option<Protocol>,
+        market: Address,
+        block: u64,
+    ) -> LiquidationQuote<M> {
+        let block = BlockNumber::from(block);
+        if self.lending_oracles {
+            match (protocol, self.compound, self.aave_lending_pool) {
+                (Some(Protocol::Compound), Some(compound), _) => {
+                    let (debt, collateral) = futures::future::join(
+                        self.compound_value(&compound, market, liquidation.sent_amount, block),
+                        self.compound_collateral_value(
+                            &compound,
+                            liquidation.received_token,
+                            liquidation.received_amount,
+                            block,
+                        ),
+                    )
+                    .await;
+                    return LiquidationQuote {
+                        debt,
+                        collateral,
+                        source: PriceSource::Oracle,
+                    };
+                }
+                (Some(Protocol::Aave), _, Some(pool)) => {
+                    let (debt, collateral) = futures::future::join(
+                        self.aave_value(
+                            pool,
+                            liquidation.sent_token,
+                            liquidation.sent_amount,
+                            block,
+                        ),
+                        self.aave_value(
+                            pool,
+                            liquidation.received_token,
+                            liquidation.received_amount,
+                            block,
+                        ),
+                    )
+                    .await;
+                    return LiquidationQuote {
+                        debt,
+                        collateral,
+                        source: PriceSource::Oracle,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        let (debt, collateral) = futures::future::join(
+            self.quote(liquidation.sent_token, liquidation.sent_amount, block),
+            self.quote(
+                liquidation.received_token,
+                liquidation.received_amount,
+                block,
+            ),
+        )
+        .await;
+        LiquidationQuote {
+            debt,
+            collateral,
+            source: PriceSource::Uniswap,
+        }
+    }
+
+    /// Values an amount of the cToken's underlying with the Compound oracle,
+    /// which prices all underlyings in USD
+    async fn compound_value(
+        &self,
+        compound: &CompoundConfig,
+        ctoken: Address,
+        amount: U256,
+        block: BlockNumber,
+    ) -> Result<U256, ContractError<M>> {
+        let oracle = CompoundOracle::new(compound.oracle, self.provider.clone());
+        let price = oracle.get_underlying_price(ctoken).block(block);
+        let eth_price = oracle.get_underlying_price(compound.cether).block(block);
+        let (price, eth_price) = futures::try_join!(price.call(), eth_price.call())?;
+        Ok(compound_eth_value(amount, price, eth_price))
+    }
+
+    /// Values an amount of cTokens via their underlying
+    async fn compound_collateral_value(
+        &self,
+        compound: &CompoundConfig,
+        ctoken: Address,
+        amount: U256,
+        block: BlockNumber,
+    ) -> Result<U256, ContractError<M>> {
+        let exchange_rate = CTokenExchangeRate::new(ctoken, self.provider.clone())
+            .exchange_rate_stored()
+            .block(block)
             .call()
             .await?;
+        let underlying = ctoken_underlying(amount, exchange_rate);
+        self.compound_value(compound, ctoken, underlying, block)
+            .await
+    }
 
-        debug_assert_eq!(one, amounts[0]);
-        debug_assert_eq!(amounts.len(), 2);
-        Ok(amounts[1] / one * amount)
+    /// Values an amount of the reserve with the Aave oracle, which prices one
+    /// unit of each reserve in ETH
+    async fn aave_value(
+        &self,
+        pool: Address,
+        token: Address,
+        amount: U256,
+        block: BlockNumber,
+    ) -> Result<U256, ContractError<M>> {
+        if token == *ETH || token == self.wrapped_native {
+            return Ok(amount);
+        }
+        let addresses_provider = AaveLendingPool::new(pool, self.provider.clone())
+            .addresses_provider()
+            .block(block)
+            .call()
+            .await?;
+        let oracle = AaveAddressesProvider::new(addresses_provider, self.provider.clone())
+            .get_price_oracle()
+            .block(block)
+            .call()
+            .await?;
+        let price = AaveOracle::new(oracle, self.provider.clone())
+            .get_asset_price(token)
+            .block(block)
+            .call();
+        let decimals = TokenDecimals::new(token, self.provider.clone())
+            .decimals()
+            .block(block)
+            .call();
+        let (price, decimals) = futures::try_join!(price, decimals)?;
+        Ok(amount * price / U256::exp10(decimals as usize))
+    }
+}
+
+/// Converts an amount of an underlying to ETH with the Compound oracle's prices,
+/// which are scaled by `1e(36 - decimals)` so that `amount * price` is in USD
+/// with 36 decimals for every underlying.
+///
+/// `eth_price` is the price of cETH's underlying with 18 decimals.
+fn compound_eth_value(amount: U256, price: U256, eth_price: U256) -> U256 {
+    if eth_price.is_zero() {
+        return U256::zero();
     }
+    amount * price / eth_price
+}
+
+/// The amount of the underlying that `amount` cTokens are worth, the exchange
+/// rate is scaled by `1e(18 - 8 + underlying decimals)`
+fn ctoken_underlying(amount: U256, exchange_rate: U256) -> U256 {
+    amount * exchange_rate / WEI_IN_ETHER
 }
 
 #[cfg(test)]
@@ -87,6 +365,45 @@ mod tests {
         ethers::utils::WEI_IN_ETHER / amt
     }
 
+    #[test]
+    fn compound_oracle_scaling() {
+        // $1 per USDC with 6 decimals, $2000 per ETH with 18 decimals
+        let usdc_price = U256::exp10(30);
+        let eth_price = U256::from(2000) * U256::exp10(18);
+
+        let thousand_usdc = U256::from(1000) * U256::exp10(6);
+        assert_eq!(
+            compound_eth_value(thousand_usdc, usdc_price, eth_price),
+            U256::exp10(18) / 2
+        );
+        // 1 ETH is worth 1 ETH
+        assert_eq!(compound_eth_value(WEI, eth_price, eth_price), WEI);
+        assert_eq!(
+            compound_eth_value(thousand_usdc, usdc_price, U256::zero()),
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn ctoken_exchange_rate() {
+        // 0.02 USDC per cUSDC, scaled by 1e(18 - 8 + 6)
+        let exchange_rate = U256::from(2) * U256::exp10(14);
+        let ctokens = U256::from(5000) * U256::exp10(8);
+        assert_eq!(
+            ctoken_underlying(ctokens, exchange_rate),
+            U256::from(100) * U256::exp10(6)
+        );
+    }
+
+    #[test]
+    fn price_source_round_trip() {
+        for source in [PriceSource::Uniswap, PriceSource::Oracle].iter() {
+            assert_eq!(source.to_string().parse::<PriceSource>(), Ok(*source));
+        }
+        assert_eq!(PriceSource::Oracle.to_string(), "oracle");
+        assert!("chainlink".parse::<PriceSource>().is_err());
+    }
+
     static PROVIDER: Lazy<Provider<Http>> = Lazy::new(|| {
         let url: String = std::env::var("ARCHIVE").expect("Archive node URL should be set");
         let provider = Provider::<Http>::try_from(url).unwrap();
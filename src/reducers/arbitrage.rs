@@ -1,13 +1,20 @@
-use crate::types::TransactionData;
+use crate::types::{balances::native, Action, BalanceDeltas, Protocol, TransactionData};
 use crate::{
-    inspectors::find_matching,
+    addresses::registry,
+    inspectors::{erc20::TransferFilter, find_matching},
+    network::network,
     types::{
-        actions::{Arbitrage, SpecificAction},
+        actions::{Arbitrage, ArbitrageLeg, SpecificAction, Trade},
         Classification, Inspection,
     },
     Reducer, TxReducer,
 };
-use std::collections::BTreeMap;
+use ethers::{
+    contract::EthLogDecode,
+    types::{Address, CallType},
+};
+use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Clone, Debug)]
 pub struct ArbitrageReducer;
@@ -42,6 +49,12 @@ impl Reducer for ArbitrageReducer {
                                 profit: trade2.t2.amount.saturating_sub(trade.t1.amount),
                                 token: trade2.t2.token,
                                 to: trade2.t2.to,
+                                other_profits: Vec::new(),
+                                legs: actions[i..=j]
+                                    .iter()
+                                    .filter_map(|t| t.as_action().and_then(|t| t.as_trade()))
+                                    .map(|t| ArbitrageLeg::from_trade(t, None))
+                                    .collect(),
                             },
                             // TODO!
                             Vec::new(),
@@ -71,6 +84,87 @@ impl Reducer for ArbitrageReducer {
     }
 }
 
+/// The maximum number of hops of a cycle
+const MAX_HOPS: usize = 8;
+
+/// The trades of a transaction as a directed graph of tokens
+///
+/// Every trade is an edge from the token it sold to the token it bought.
+struct TradeGraph<'a> {
+    /// `(action index, trade)` in the order of execution
+    trades: Vec<(usize, &'a Trade)>,
+    /// token -> positions in `trades` of the trades which sold it
+    edges: HashMap<Address, Vec<usize>>,
+}
+
+impl<'a> TradeGraph<'a> {
+    fn new(trades: Vec<(usize, &'a Trade)>) -> Self {
+        let mut edges = HashMap::<_, Vec<_>>::new();
+        for (pos, (_, trade)) in trades.iter().enumerate() {
+            edges.entry(native(trade.t1.token)).or_default().push(pos);
+        }
+        Self { trades, edges }
+    }
+
+    /// Finds all closed cycles that start and end in the same token with the
+    /// same beneficiary.
+    ///
+    /// The beneficiary of a cycle is the sender of its first trade, unless
+    /// that is one of the `searcher` accounts, which may send the proceeds
+    /// anywhere. Every trade is part of at most one cycle. Returns the
+    /// positions of the cycles' trades in `trades`.
+    fn cycles(&self, searcher: &[Address]) -> Vec<Vec<usize>> {
+        let mut used = HashSet::new();
+        let mut cycles = Vec::new();
+        for start in 0..self.trades.len() {
+            if used.contains(&start) {
+                continue;
+            }
+            let mut path = vec![start];
+            if self.close_cycle(&mut path, &used, searcher) {
+                used.extend(path.iter().cloned());
+                cycles.push(path);
+            }
+        }
+        cycles
+    }
+
+    /// Depth first search for the earliest trades that close the `path`
+    fn close_cycle(
+        &self,
+        path: &mut Vec<usize>,
+        used: &HashSet<usize>,
+        searcher: &[Address],
+    ) -> bool {
+        let first = self.trades[path[0]].1;
+        let last_pos = *path.last().expect("path is not empty");
+        let last = self.trades[last_pos].1;
+
+        if path.len() > 1 && native(last.t2.token) == native(first.t1.token) {
+            let beneficiary = last.t2.to;
+            return beneficiary == first.t1.from || searcher.contains(&first.t1.from);
+        }
+        if path.len() == MAX_HOPS {
+            return false;
+        }
+
+        let next = self
+            .edges
+            .get(&native(last.t2.token))
+            .into_iter()
+            .flatten()
+            .filter(|pos| **pos > last_pos && !used.contains(*pos));
+        for pos in next {
+            path.push(*pos);
+            if self.close_cycle(path, used, searcher) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
 impl TxReducer for ArbitrageReducer {
     fn reduce_tx(&self, tx: &mut TransactionData) {
         let trades: Vec<_> = tx
@@ -79,31 +173,136 @@ impl TxReducer for ArbitrageReducer {
             .filter_map(|(idx, action)| action.inner.as_trade().map(|trade| (idx, action, trade)))
             .collect();
 
-        // action index to arbitrage, if None then prune
-        let mut updates = BTreeMap::new();
-        let mut actions = trades.iter();
+        let searcher = [tx.from, tx.contract];
+        let leg = |action: &Action, trade: &Trade| {
+            let protocol = tx
+                .get_call(&action.call)
+                .and_then(|call| call.protocol)
+                .filter(|protocol| *protocol != Protocol::Erc20)
+                .or_else(|| registry().protocol(&trade.t2.from));
+            ArbitrageLeg::from_trade(trade, protocol)
+        };
 
-        while let Some((idx, _, trade)) = actions.next() {
-            if let Some((reverse_idx, _, reverse_trade)) =
-                actions.clone().find(|(_, _, reverse)| {
-                    // find a reverse trade
-                    reverse.t2.token == trade.t1.token
-                })
-            {
-                if reverse_trade.t2.amount > trade.t1.amount {
-                    let arbitrage = Arbitrage {
-                        profit: reverse_trade.t2.amount.saturating_sub(trade.t1.amount),
-                        token: reverse_trade.t2.token,
-                        to: reverse_trade.t2.to,
-                    };
-
-                    updates.insert(*idx, Some(arbitrage));
-                    for i in (idx + 1)..=*reverse_idx {
-                        updates.insert(i, None);
+        // the trades of each arbitrage, in the order they were executed
+        let mut paths = Vec::new();
+
+        let graph = TradeGraph::new(
+            trades
+                .iter()
+                .map(|(idx, _, trade)| (*idx, *trade))
+                .collect(),
+        );
+        for cycle in graph.cycles(&searcher) {
+            paths.push(cycle.iter().map(|pos| trades[*pos]).collect::<Vec<_>>());
+        }
+
+        let mut used = paths
+            .iter()
+            .flatten()
+            .map(|(idx, _, _)| *idx)
+            .collect::<HashSet<_>>();
+        let mut actions = trades.iter();
+        while let Some((idx, action, trade)) = actions.next() {
+            if used.contains(idx) {
+                continue;
+            }
+            // trades that do not line up as a cycle, e.g. when an intermediate
+            // trade was not decoded
+            if let Some(reverse) = actions.clone().find(|(i, _, reverse)| {
+                // find a reverse trade
+                !used.contains(i) && reverse.t2.token == trade.t1.token
+            }) {
+                // the trades in between which continue the path of the first trade
+                let mut token = trade.t2.token;
+                let mut path = vec![(*idx, *action, *trade)];
+                for hop in trades
+                    .iter()
+                    .filter(|(i, _, _)| idx < i && *i < reverse.0 && !used.contains(i))
+                {
+                    if hop.2.t1.token == token {
+                        token = hop.2.t2.token;
+                        path.push(*hop);
                     }
                 }
+                path.push(*reverse);
+                used.extend(path.iter().map(|(i, _, _)| *i));
+                paths.push(path);
             }
         }
+
+        // the profit is whatever the searcher actually gained, in any token
+        // and in any of its accounts, which also accounts for fee-on-transfer
+        // tokens, flashloan fees and side payments
+        let pools = trades
+            .iter()
+            .flat_map(|(_, _, trade)| vec![trade.t1.to, trade.t2.from])
+            .collect::<HashSet<_>>();
+        let mut accounts = searcher.to_vec();
+        accounts.extend(
+            paths
+                .iter()
+                .map(|path| path[path.len() - 1].2.t2.to)
+                .filter(|to| !pools.contains(to)),
+        );
+        let forwarded_to = payees(tx, &accounts, &pools);
+        accounts.extend(forwarded_to);
+        let mut gains = BalanceDeltas::from_tx(tx)
+            .combined(&accounts)
+            .into_iter()
+            .filter(|(_, delta)| delta.is_gain())
+            .map(|(token, delta)| (token, delta.gain()))
+            .collect::<BTreeMap<_, _>>();
+
+        // arbitrages through the same token share its gain, the first one
+        // also gets what is left of it
+        let mut arbitrages = paths
+            .iter()
+            .map(|path| {
+                let (_, _, first) = path[0];
+                let (_, _, last) = path[path.len() - 1];
+                let gain = gains.entry(native(first.t1.token)).or_default();
+                let profit = (*gain).min(last.t2.amount.saturating_sub(first.t1.amount));
+                *gain -= profit;
+                let arbitrage = Arbitrage {
+                    profit,
+                    token: last.t2.token,
+                    to: last.t2.to,
+                    other_profits: Vec::new(),
+                    legs: path
+                        .iter()
+                        .map(|(_, action, trade)| leg(action, trade))
+                        .collect(),
+                };
+                let legs = path.iter().map(|(idx, _, _)| *idx).collect::<Vec<_>>();
+                (legs, arbitrage)
+            })
+            .collect::<Vec<_>>();
+        for (_, arbitrage) in arbitrages.iter_mut() {
+            arbitrage.profit += gains.remove(&native(arbitrage.token)).unwrap_or_default();
+        }
+        // profits that exit in tokens the arbitrages do not end in
+        if let Some((_, arbitrage)) = arbitrages.first_mut() {
+            arbitrage.other_profits = gains
+                .into_iter()
+                .filter(|(_, gain)| !gain.is_zero())
+                .collect();
+        }
+        arbitrages.retain(|(_, arbitrage)| {
+            !arbitrage.profit.is_zero() || !arbitrage.other_profits.is_empty()
+        });
+
+        // action index to arbitrage, if None then prune
+        let mut updates = BTreeMap::new();
+
+        // the first trade of an arbitrage becomes the arbitrage and its other
+        // trades are pruned, anything executed in between is left as it is
+        for (legs, arbitrage) in arbitrages {
+            updates.insert(legs[0], Some(arbitrage));
+            for idx in &legs[1..] {
+                updates.insert(*idx, None);
+            }
+        }
+
         // iterating from highest index to lowest ensure `remove` is safe
         for (idx, arbitrage) in updates.into_iter().rev() {
             if let Some(arbitrage) = arbitrage {
@@ -117,11 +316,52 @@ impl TxReducer for ArbitrageReducer {
     }
 }
 
+/// The accounts that received tokens or ETH from the `searcher` without
+/// sending any themselves, e.g. the wallet a searcher forwards its profit
+/// to. Pools, tokens and the miner are never payees.
+fn payees(tx: &TransactionData, searcher: &[Address], pools: &HashSet<Address>) -> Vec<Address> {
+    let mut tokens = HashSet::new();
+    let mut transfers = Vec::new();
+    for log in tx.all_logs() {
+        if let Ok(transfer) = TransferFilter::decode_log(&log.raw_log) {
+            tokens.insert(log.address);
+            transfers.push((transfer.from, transfer.to));
+        }
+    }
+    transfers.extend(
+        tx.all_calls()
+            .filter(|call| call.call_type == CallType::Call && !call.value.is_zero())
+            .map(|call| (call.from, call.to)),
+    );
+    let senders = transfers
+        .iter()
+        .map(|(from, _)| *from)
+        .collect::<HashSet<_>>();
+
+    transfers
+        .into_iter()
+        .filter(|(from, to)| {
+            searcher.contains(from)
+                && !searcher.contains(to)
+                && !senders.contains(to)
+                && !pools.contains(to)
+                && !tokens.contains(to)
+                && !to.is_zero()
+                && *to != network().wrapped_native
+                && Some(*to) != tx.coinbase
+        })
+        .map(|(_, to)| to)
+        .unique()
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::addresses::WETH;
     use crate::test_helpers::*;
     use crate::types::actions::{Arbitrage, Trade, Transfer};
+    use ethers::types::Action as TraceAction;
 
     fn test_trade_to_arbitrage(input: Vec<Classification>, expected: Vec<Classification>) {
         let uniswap = ArbitrageReducer;
@@ -181,6 +421,11 @@ mod tests {
                     profit: 10.into(),
                     token: token1,
                     to: usr,
+                    other_profits: Vec::new(),
+                    legs: vec![
+                        ArbitrageLeg::from_trade(&t1, None),
+                        ArbitrageLeg::from_trade(&t2, None),
+                    ],
                 },
                 Vec::new(),
             ),
@@ -189,4 +434,162 @@ mod tests {
 
         test_trade_to_arbitrage(input, expected);
     }
+
+    fn trade(from: Address, pool: Address, sold: (Address, u64), bought: (Address, u64)) -> Trade {
+        Trade::new(
+            Transfer {
+                from,
+                to: pool,
+                amount: sold.1.into(),
+                token: sold.0,
+            },
+            Transfer {
+                from: pool,
+                to: from,
+                amount: bought.1.into(),
+                token: bought.0,
+            },
+        )
+    }
+
+    #[test]
+    fn interleaved_cycles() {
+        let addrs = addrs();
+        let (weth, usdc, dai, yfi) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let (bot, other) = (addrs[4], addrs[5]);
+        let pools = &addrs[6..];
+
+        let trades = vec![
+            // WETH -> USDC -> DAI -> WETH
+            trade(bot, pools[0], (weth, 100), (usdc, 200)),
+            // an unrelated trade of someone else in between
+            trade(other, pools[1], (yfi, 1), (weth, 10)),
+            trade(bot, pools[1], (usdc, 200), (dai, 200)),
+            // another trade which does not close a cycle
+            trade(other, pools[2], (weth, 10), (usdc, 20)),
+            trade(bot, pools[3], (dai, 200), (weth, 110)),
+        ];
+        let graph = TradeGraph::new(trades.iter().enumerate().collect());
+
+        assert_eq!(graph.cycles(&[bot]), vec![vec![0, 2, 4]]);
+    }
+
+    /// A transaction of the `bot` contract with the trades as its actions, the
+    /// logs of the trades and `transfers` and the ETH `payments` of the bot
+    fn arb_tx(
+        eoa: Address,
+        bot: Address,
+        trades: &[Trade],
+        transfers: &[Transfer],
+        payments: &[(Address, u64)],
+    ) -> TransactionData {
+        let mut traces = vec![mk_trace(eoa, bot, vec![], vec![], payments.len())];
+        for (i, (to, value)) in payments.iter().enumerate() {
+            let mut trace = mk_trace(bot, *to, vec![], vec![i], 0);
+            if let TraceAction::Call(ref mut call) = trace.action {
+                call.value = (*value).into();
+            }
+            traces.push(trace);
+        }
+        let logs = trades
+            .iter()
+            .flat_map(|trade| vec![&trade.t1, &trade.t2])
+            .chain(transfers)
+            .enumerate()
+            .map(|(i, t)| transfer_log(t.token, t.from, t.to, t.amount, i as u64))
+            .collect();
+        let mut tx = TransactionData::create(traces, logs).unwrap();
+        for trade in trades {
+            tx.push_action(Action::new(trade.clone().into(), vec![]));
+        }
+        tx
+    }
+
+    #[test]
+    fn miner_payments_are_not_losses() {
+        let addrs = addrs();
+        let usdc = addrs[0];
+        let (eoa, bot, miner) = (addrs[1], addrs[2], addrs[3]);
+        let trades = [
+            trade(bot, addrs[4], (*WETH, 100), (usdc, 200)),
+            trade(bot, addrs[5], (usdc, 200), (*WETH, 110)),
+        ];
+        let mut tx = arb_tx(eoa, bot, &trades, &[], &[(miner, 5)]);
+        tx.coinbase = Some(miner);
+        ArbitrageReducer.reduce_tx(&mut tx);
+
+        // the bribe is only subtracted from the evaluation's net profit
+        let arb = tx.actions().arbitrage().next().unwrap();
+        assert_eq!((arb.profit, arb.token), (10.into(), *WETH));
+        assert_eq!(tx.coinbase_transfers(miner), 5.into());
+    }
+
+    #[test]
+    fn profit_sent_to_another_account() {
+        let addrs = addrs();
+        let usdc = addrs[0];
+        let (eoa, bot, wallet) = (addrs[1], addrs[2], addrs[3]);
+        let trades = [
+            trade(bot, addrs[4], (*WETH, 100), (usdc, 200)),
+            trade(bot, addrs[5], (usdc, 200), (*WETH, 110)),
+        ];
+        let forward = Transfer {
+            from: bot,
+            to: wallet,
+            amount: 10.into(),
+            token: *WETH,
+        };
+        let mut tx = arb_tx(eoa, bot, &trades, &[forward], &[]);
+        ArbitrageReducer.reduce_tx(&mut tx);
+
+        let arb = tx.actions().arbitrage().next().unwrap();
+        assert_eq!(arb.profit, 10.into());
+    }
+
+    #[test]
+    fn profit_in_another_token() {
+        let addrs = addrs();
+        let usdc = addrs[0];
+        let (eoa, bot) = (addrs[1], addrs[2]);
+        // the bot sells back only part of the USDC it bought
+        let trades = [
+            trade(bot, addrs[4], (*WETH, 100), (usdc, 200)),
+            trade(bot, addrs[5], (usdc, 150), (*WETH, 100)),
+        ];
+        let mut tx = arb_tx(eoa, bot, &trades, &[], &[]);
+        ArbitrageReducer.reduce_tx(&mut tx);
+
+        let arb = tx.actions().arbitrage().next().unwrap();
+        assert!(arb.profit.is_zero());
+        assert_eq!(arb.other_profits, vec![(usdc, 50.into())]);
+    }
+
+    #[test]
+    fn reduces_interleaved_cycles() {
+        let addrs = addrs();
+        let (usdc, dai, yfi, link) = (addrs[0], addrs[1], addrs[2], addrs[3]);
+        let (eoa, bot, other) = (addrs[4], addrs[5], addrs[6]);
+        let pools = &addrs[7..];
+        let unrelated = trade(other, pools[2], (link, 5), (usdc, 20));
+        let trades = [
+            // WETH -> USDC -> WETH and DAI -> YFI -> DAI
+            trade(bot, pools[0], (*WETH, 100), (usdc, 200)),
+            trade(bot, pools[1], (dai, 50), (yfi, 1)),
+            unrelated.clone(),
+            trade(bot, pools[2], (usdc, 200), (*WETH, 110)),
+            trade(bot, pools[0], (yfi, 1), (dai, 60)),
+        ];
+        let mut tx = arb_tx(eoa, bot, &trades, &[], &[]);
+        ArbitrageReducer.reduce_tx(&mut tx);
+
+        let arbs = tx.actions().arbitrage().collect::<Vec<_>>();
+        assert_eq!(arbs.len(), 2);
+        assert_eq!((arbs[0].profit, arbs[0].token), (10.into(), *WETH));
+        assert_eq!(arbs[0].path(), vec![*WETH, usdc, *WETH]);
+        assert_eq!((arbs[1].profit, arbs[1].token), (10.into(), dai));
+        assert_eq!(arbs[1].path(), vec![dai, yfi, dai]);
+        // the trade executed in between the cycles is left as it is
+        assert_eq!(tx.actions().trades().collect::<Vec<_>>(), vec![&unrelated]);
+        assert_eq!(tx.actions().count(), 3);
+    }
 }
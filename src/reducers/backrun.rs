@@ -0,0 +1,137 @@
+use crate::{
+    types::{balances::native, Evaluation, Status, TransactionData},
+    BlockReducer,
+};
+use ethers::types::{Block, Transaction};
+
+/// Tags arbitrages with the transaction they back-ran.
+///
+/// An arbitrage back-runs a transaction that shortly precedes it, when that
+/// transaction was sent by someone else and sold one of the arbitrage's pools
+/// at least as much of the token that the arbitrage then bought back from it.
+#[derive(Debug, Clone)]
+pub struct BackrunReducer {
+    /// How many positions the trade may precede the arbitrage
+    max_distance: usize,
+}
+
+impl BackrunReducer {
+    pub fn new(max_distance: usize) -> Self {
+        Self { max_distance }
+    }
+}
+
+impl Default for BackrunReducer {
+    /// Only the transaction right before the arbitrage
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl BlockReducer for BackrunReducer {
+    fn reduce_block(&self, _: &Block<Transaction>, evaluations: &mut [Evaluation]) {
+        let mut backruns = Vec::new();
+        for (idx, evaluation) in evaluations.iter().enumerate() {
+            let arb_tx = &evaluation.tx;
+            if arb_tx.status != Status::Success {
+                continue;
+            }
+            let position = arb_tx.transaction_position;
+            let trigger = evaluations[..idx]
+                .iter()
+                .rev()
+                .take_while(|prev| {
+                    position.saturating_sub(prev.tx.transaction_position) <= self.max_distance
+                })
+                .find(|prev| is_backrun_of(arb_tx, &prev.tx));
+            if let Some(trigger) = trigger {
+                backruns.push((idx, trigger.tx.hash));
+            }
+        }
+
+        for (idx, hash) in backruns {
+            evaluations[idx].backrun_of = Some(hash);
+        }
+    }
+}
+
+/// Whether an arbitrage of `arb_tx` reverts the price impact of a trade in `tx`
+fn is_backrun_of(arb_tx: &TransactionData, tx: &TransactionData) -> bool {
+    if tx.from == arb_tx.from
+        || tx.status != Status::Success
+        || tx.actions().arbitrage().next().is_some()
+    {
+        return false;
+    }
+    arb_tx
+        .actions()
+        .arbitrage()
+        .flat_map(|arb| arb.legs.iter())
+        .any(|leg| {
+            tx.actions().trades().any(|trade| {
+                trade.t2.from == leg.pool
+                    && native(trade.t1.token) == native(leg.token_out)
+                    && trade.t1.amount >= leg.amount_out
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use crate::types::{
+        actions::{Trade, Transfer},
+        Action,
+    };
+    use ethers::types::{Address, TxHash};
+
+    /// A user's trade that sells `amount` of the leg's output token to its pool
+    fn user_trade(mut tx: TransactionData, user: Address, amount: u64) -> TransactionData {
+        let leg = tx.actions().arbitrage().next().unwrap().legs[0].clone();
+        while tx.actions().next().is_some() {
+            tx.remove_action(0);
+        }
+        let trade = Trade::new(
+            Transfer {
+                from: user,
+                to: leg.pool,
+                amount: leg.amount_out * amount,
+                token: leg.token_out,
+            },
+            Transfer {
+                from: leg.pool,
+                to: user,
+                amount: leg.amount_in * amount,
+                token: leg.token_in,
+            },
+        );
+        tx.push_action(Action::new(trade.into(), vec![0]));
+        tx.from = user;
+        tx.hash = TxHash::from_low_u64_be(1);
+        tx.transaction_position = 0;
+        tx
+    }
+
+    #[test]
+    fn arb_after_user_trade() {
+        let mut arb_tx = read_tx("triangular_arb.data.json");
+        let inspector = test_inspector();
+        inspector.inspect_tx(&mut arb_tx);
+        inspector.reduce_tx(&mut arb_tx);
+        arb_tx.transaction_position = 2;
+
+        let user = addrs()[0];
+        let user_tx = user_trade(arb_tx.clone(), user, 3);
+        let mut evaluations = vec![mk_evaluation(user_tx), mk_evaluation(arb_tx)];
+        let block = Block::default();
+
+        // a transaction in between is out of range by default
+        BackrunReducer::default().reduce_block(&block, &mut evaluations);
+        assert_eq!(evaluations[1].backrun_of, None);
+
+        BackrunReducer::new(2).reduce_block(&block, &mut evaluations);
+        assert_eq!(evaluations[0].backrun_of, None);
+        assert_eq!(evaluations[1].backrun_of, Some(TxHash::from_low_u64_be(1)));
+    }
+}
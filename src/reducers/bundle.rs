@@ -0,0 +1,275 @@
+use crate::{
+    types::{BundleType, Evaluation},
+    BlockReducer,
+};
+use ethers::types::{Block, Transaction, TxHash, U256};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// A transaction of a block of the Flashbots blocks API
+#[derive(Debug, Clone, Deserialize)]
+struct FlashbotsTransaction {
+    transaction_hash: TxHash,
+    bundle_index: usize,
+    bundle_type: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FlashbotsBlock {
+    block_number: u64,
+    transactions: Vec<FlashbotsTransaction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FlashbotsBlocksResponse {
+    blocks: Vec<FlashbotsBlock>,
+}
+
+/// The bundles of the blocks of a local dump of the Flashbots blocks API
+/// (`https://blocks.flashbots.net/v1/blocks`)
+#[derive(Debug, Clone, Default)]
+pub struct FlashbotsBlocks {
+    /// block number -> tx hash -> (bundle_index, bundle_type)
+    blocks: HashMap<u64, HashMap<TxHash, (usize, BundleType)>>,
+}
+
+impl FlashbotsBlocks {
+    /// Reads the JSON response of the blocks API
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(json: &str) -> io::Result<Self> {
+        let response: FlashbotsBlocksResponse = serde_json::from_str(json)?;
+        let mut blocks = HashMap::<_, HashMap<_, _>>::new();
+        for block in response.blocks {
+            let txs = blocks.entry(block.block_number).or_default();
+            for tx in block.transactions {
+                // the API only knows flashbots, rogue and miner_payout bundles
+                let bundle_type = tx
+                    .bundle_type
+                    .parse()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                txs.insert(tx.transaction_hash, (tx.bundle_index, bundle_type));
+            }
+        }
+        Ok(Self { blocks })
+    }
+
+    /// The bundled transactions of the block, `None` if the block is not part
+    /// of the dump
+    fn block(&self, block_number: u64) -> Option<&HashMap<TxHash, (usize, BundleType)>> {
+        self.blocks.get(&block_number)
+    }
+}
+
+/// Assigns the transactions of a block to bundles.
+///
+/// Blocks of the Flashbots dump take their bundles from it. In all other blocks
+/// a bundle is a run of adjacent transactions that pay the same priority fee,
+/// with at least one of them paying the miner directly, or a run of adjacent
+/// transactions without any gas price, which only a miner includes.
+#[derive(Debug, Clone, Default)]
+pub struct BundleReducer {
+    flashbots: FlashbotsBlocks,
+}
+
+impl BundleReducer {
+    pub fn new(flashbots: FlashbotsBlocks) -> Self {
+        Self { flashbots }
+    }
+}
+
+impl BlockReducer for BundleReducer {
+    fn reduce_block(&self, block: &Block<Transaction>, evaluations: &mut [Evaluation]) {
+        let block_number = block
+            .number
+            .map(|number| number.as_u64())
+            .or_else(|| evaluations.first().map(|eval| eval.tx.block_number));
+        if let Some(bundles) = block_number.and_then(|number| self.flashbots.block(number)) {
+            for evaluation in evaluations.iter_mut() {
+                if let Some((index, bundle_type)) = bundles.get(&evaluation.tx.hash) {
+                    evaluation.bundle_index = Some(*index);
+                    evaluation.bundle_type = Some(*bundle_type);
+                }
+            }
+            return;
+        }
+
+        // the whole block, since transactions which were not evaluated still
+        // split or join bundles
+        let base_fee = evaluations
+            .first()
+            .and_then(|eval| eval.base_fee)
+            .unwrap_or_default();
+        let coinbase_transfers = evaluations
+            .iter()
+            .map(|eval| (eval.tx.hash, eval.coinbase_transfers))
+            .collect::<HashMap<_, _>>();
+        let mut txs = block.transactions.iter().collect::<Vec<_>>();
+        txs.sort_by_key(|tx| tx.transaction_index);
+        let txs = txs
+            .into_iter()
+            .map(|tx| {
+                // a plain transfer to the miner is not evaluated
+                let pays_miner = coinbase_transfers
+                    .get(&tx.hash)
+                    .map(|value| !value.is_zero())
+                    .unwrap_or_else(|| tx.to == Some(block.author) && !tx.value.is_zero());
+                (tx, pays_miner, tx.gas_price.saturating_sub(base_fee))
+            })
+            .collect::<Vec<_>>();
+
+        let mut bundles = HashMap::new();
+        let mut bundle_index = 0;
+        let mut end = 0;
+        while let Some(anchor) =
+            (end..txs.len()).find(|idx| txs[*idx].1 || txs[*idx].0.gas_price.is_zero())
+        {
+            let (_, pays_miner, fee) = txs[anchor];
+            let joins = |tx: &(&Transaction, bool, U256)| {
+                if pays_miner {
+                    tx.2 == fee
+                } else {
+                    tx.0.gas_price.is_zero()
+                }
+            };
+
+            let mut start = anchor;
+            while start > end && joins(&txs[start - 1]) {
+                start -= 1;
+            }
+            end = anchor + 1;
+            while end < txs.len() && joins(&txs[end]) {
+                end += 1;
+            }
+
+            for (tx, _, _) in &txs[start..end] {
+                bundles.insert(tx.hash, bundle_index);
+            }
+            bundle_index += 1;
+        }
+
+        for evaluation in evaluations.iter_mut() {
+            if let Some(index) = bundles.get(&evaluation.tx.hash) {
+                evaluation.bundle_index = Some(*index);
+                evaluation.bundle_type = Some(BundleType::Inferred);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+    use ethers::types::Address;
+
+    /// A block of `(gas_price, coinbase_transfers)` and the evaluations of its transactions
+    fn block(txs: &[(u64, u64)]) -> (Block<Transaction>, Vec<Evaluation>) {
+        let tx = read_tx("bot_trade.data.json");
+        let block = Block {
+            transactions: txs
+                .iter()
+                .enumerate()
+                .map(|(position, (gas_price, _))| Transaction {
+                    hash: TxHash::from_low_u64_be(position as u64),
+                    transaction_index: Some(position.into()),
+                    gas_price: (*gas_price).into(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let evaluations = txs
+            .iter()
+            .enumerate()
+            .map(|(position, (gas_price, coinbase_transfers))| {
+                let mut tx = tx.clone();
+                tx.hash = TxHash::from_low_u64_be(position as u64);
+                tx.transaction_position = position;
+                let mut evaluation = mk_evaluation(tx);
+                evaluation.gas_price = (*gas_price).into();
+                evaluation.coinbase_transfers = (*coinbase_transfers).into();
+                evaluation
+            })
+            .collect();
+        (block, evaluations)
+    }
+
+    fn bundles(evaluations: &[Evaluation]) -> Vec<Option<usize>> {
+        evaluations.iter().map(|eval| eval.bundle_index).collect()
+    }
+
+    #[test]
+    fn infers_bundles() {
+        let (block, mut evaluations) =
+            block(&[(0, 0), (0, 100), (50, 0), (50, 0), (10, 20), (20, 0)]);
+        BundleReducer::default().reduce_block(&block, &mut evaluations);
+
+        assert_eq!(
+            bundles(&evaluations),
+            vec![Some(0), Some(0), None, None, Some(1), None]
+        );
+        assert_eq!(evaluations[0].bundle_type, Some(BundleType::Inferred));
+    }
+
+    #[test]
+    fn infers_zero_gas_price_bundles() {
+        // nothing is paid to the miner, but only the miner includes these
+        let (block, mut evaluations) = block(&[(0, 0), (0, 0), (30, 0), (0, 0)]);
+        BundleReducer::default().reduce_block(&block, &mut evaluations);
+
+        assert_eq!(bundles(&evaluations), vec![Some(0), Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn walks_transactions_without_evaluation() {
+        let (mut block, mut evaluations) = block(&[(10, 0), (10, 0), (10, 0), (10, 0)]);
+        // a plain payment to the miner in the middle, which was not evaluated
+        block.author = Address::repeat_byte(1);
+        block.transactions[1].to = Some(block.author);
+        block.transactions[1].value = 100.into();
+        // and a transaction with another gas price that was not evaluated either
+        block.transactions[2].gas_price = 20.into();
+        evaluations.remove(2);
+        evaluations.remove(1);
+        BundleReducer::default().reduce_block(&block, &mut evaluations);
+
+        // the gap splits the bundle, even though the evaluations are adjacent
+        assert_eq!(bundles(&evaluations), vec![Some(0), None]);
+    }
+
+    #[test]
+    fn prefers_flashbots_blocks() {
+        let flashbots = FlashbotsBlocks::parse(
+            r#"{
+                "blocks": [
+                    {
+                        "block_number": 12000000,
+                        "miner": "0x5a0b54d5dc17e0aadc383d2db43b0a0d3e029c4c",
+                        "transactions": [
+                            {
+                                "transaction_hash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+                                "tx_index": 2,
+                                "bundle_type": "rogue",
+                                "bundle_index": 0
+                            }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let (block, mut evaluations) = block(&[(0, 0), (0, 100), (50, 0)]);
+        for evaluation in evaluations.iter_mut() {
+            evaluation.tx.block_number = 12_000_000;
+        }
+        BundleReducer::new(flashbots).reduce_block(&block, &mut evaluations);
+
+        assert_eq!(evaluations[1].bundle_index, None);
+        assert_eq!(evaluations[2].bundle_index, Some(0));
+        assert_eq!(evaluations[2].bundle_type, Some(BundleType::Rogue));
+    }
+}
@@ -1,13 +1,15 @@
 use crate::types::TransactionData;
 use crate::{
-    addresses::{ETH, WETH},
+    addresses::ETH,
     inspectors::find_matching,
+    network::network,
     types::{
         actions::{ProfitableLiquidation, Transfer},
         Classification, Inspection,
     },
     Reducer, TxReducer,
 };
+use ethers::types::Address;
 
 pub struct LiquidationReducer;
 
@@ -64,9 +66,7 @@ impl Reducer for LiquidationReducer {
 
                     if let Some((_, paid)) = res {
                         // prune.push(idx2);
-                        let tokens_match = (received.token == paid.t1.token)
-                            || ((received.token == *ETH && paid.t1.token == *WETH)
-                                || (received.token == *WETH && paid.t1.token == *ETH));
+                        let tokens_match = is_same_token(received.token, paid.t1.token);
                         if received.amount > paid.t1.amount && tokens_match {
                             liq.received_amount = received.amount;
                             let profitable_liq = ProfitableLiquidation {
@@ -137,9 +137,7 @@ impl TxReducer for LiquidationReducer {
                     })
                     .next()
                 {
-                    let tokens_match = (received.token == paid.t1.token)
-                        || ((received.token == *ETH && paid.t1.token == *WETH)
-                            || (received.token == *WETH && paid.t1.token == *ETH));
+                    let tokens_match = is_same_token(received.token, paid.t1.token);
                     if received.amount > paid.t1.amount && tokens_match {
                         let mut liquidation = liq.clone();
                         liquidation.received_amount = received.amount;
@@ -183,6 +181,12 @@ impl TxReducer for LiquidationReducer {
     }
 }
 
+/// Whether both are the same token, treating the native currency and its wrapped token as equal
+fn is_same_token(a: Address, b: Address) -> bool {
+    let wrapped = network().wrapped_native;
+    a == b || (a == *ETH && b == wrapped) || (a == wrapped && b == *ETH)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -259,6 +263,7 @@ mod tests {
 
             from: usr,
             liquidated_user: liquidated,
+            collateral_type: None,
         };
 
         // gets paid out in ETH
@@ -6,3 +6,9 @@ pub use arbitrage::ArbitrageReducer;
 
 mod liquidation;
 pub use liquidation::LiquidationReducer;
+
+mod backrun;
+pub use backrun::BackrunReducer;
+
+mod bundle;
+pub use bundle::{BundleReducer, FlashbotsBlocks};
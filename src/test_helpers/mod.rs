@@ -1,9 +1,16 @@
 use crate::inspectors::*;
 use crate::model::EventLog;
 use crate::reducers::*;
-use crate::types::{inspection::TraceWrapper, Classification, Inspection, Status, TransactionData};
+use crate::types::{
+    inspection::TraceWrapper, Classification, Evaluation, Inspection, Status, TransactionData,
+};
 use crate::BatchInspector;
-use ethers::types::{Address, Log, Trace, TxHash};
+use ethers::{
+    abi::{self, RawLog, Token},
+    contract::EthEvent,
+    types::{Address, Bytes, Log, Trace, TxHash, H256, U256},
+    utils::id,
+};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
@@ -45,6 +52,31 @@ pub fn mk_inspection(actions: Vec<Classification>) -> Inspection {
     }
 }
 
+/// An `Evaluation` of the transaction without any costs or profit
+pub fn mk_evaluation(tx: TransactionData) -> Evaluation {
+    Evaluation {
+        protocols: tx.protocols(),
+        tx,
+        gas_used: Default::default(),
+        gas_price: Default::default(),
+        base_fee: None,
+        max_fee: None,
+        max_priority_fee: None,
+        actions: HashSet::new(),
+        profit: Default::default(),
+        gas_cost: Default::default(),
+        burned_fee: Default::default(),
+        miner_tip: Default::default(),
+        coinbase_transfers: Default::default(),
+        net_profit: 0,
+        backrun_of: None,
+        bundle_index: None,
+        bundle_type: None,
+        warnings: Vec::new(),
+        liquidations: Vec::new(),
+    }
+}
+
 pub fn read_trace(path: &str) -> Inspection {
     let input = std::fs::read_to_string(format!("res/{}", path)).unwrap();
     let traces: Vec<Trace> = serde_json::from_str(&input).unwrap();
@@ -61,6 +93,81 @@ pub fn read_tx(path: &str) -> TransactionData {
     TransactionData::create(traces.into_iter(), logs).unwrap()
 }
 
+/// A plain call of a transaction's trace
+pub fn mk_trace(
+    from: Address,
+    to: Address,
+    input: Vec<u8>,
+    trace_address: Vec<usize>,
+    subtraces: usize,
+) -> Trace {
+    serde_json::from_value(serde_json::json!({
+        "action": {
+            "from": from,
+            "to": to,
+            "value": "0x0",
+            "gas": "0x100000",
+            "input": Bytes::from(input),
+            "callType": "call"
+        },
+        "result": { "gasUsed": "0x0", "output": "0x" },
+        "traceAddress": trace_address,
+        "subtraces": subtraces,
+        "transactionPosition": 0,
+        "transactionHash": TxHash::zero(),
+        "blockNumber": 12500000,
+        "blockHash": H256::zero(),
+        "type": "call",
+        "error": null
+    }))
+    .unwrap()
+}
+
+/// A log of the transaction of `mk_trace`, `topics[0]` is the event's signature
+pub fn mk_log(address: Address, topics: Vec<H256>, data: Vec<Token>, log_index: u64) -> EventLog {
+    EventLog {
+        address,
+        transaction_hash: TxHash::zero(),
+        signature: topics[0],
+        raw_log: RawLog {
+            topics,
+            data: abi::encode(&data),
+        },
+        transaction_index: 0,
+        log_index: log_index.into(),
+        block_number: 12_500_000,
+    }
+}
+
+/// An ERC20 `Transfer` log of the token
+pub fn transfer_log(
+    token: Address,
+    from: Address,
+    to: Address,
+    amount: impl Into<U256>,
+    log_index: u64,
+) -> EventLog {
+    mk_log(
+        token,
+        vec![
+            erc20::TransferFilter::signature(),
+            H256::from(from),
+            H256::from(to),
+        ],
+        vec![Token::Uint(amount.into())],
+        log_index,
+    )
+}
+
+/// The input of a call to the function with the signature
+pub fn calldata(signature: &str, args: &[Token]) -> Vec<u8> {
+    id(signature)
+        .iter()
+        .copied()
+        .chain(abi::encode(args))
+        .collect()
+}
+
 pub fn get_tx(hash: &str) -> TransactionData {
     let hash = if hash.starts_with("0x") {
         &hash[2..]
@@ -86,6 +193,22 @@ pub fn get_tx(hash: &str) -> TransactionData {
         .unwrap()
 }
 
+/// All the transactions of block 11017338
+pub fn block_txs() -> Vec<TransactionData> {
+    TXINFOS
+        .iter()
+        .cloned()
+        .filter_map(|t| {
+            let logs = t
+                .logs
+                .into_iter()
+                .filter_map(|log| EventLog::try_from(log).ok())
+                .collect();
+            TransactionData::create(t.traces.into_iter(), logs).ok()
+        })
+        .collect()
+}
+
 pub fn test_inspector() -> BatchInspector {
     BatchInspector::new(
         vec![
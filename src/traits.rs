@@ -1,8 +1,9 @@
-use crate::addresses::PROTOCOLS;
+use crate::addresses::registry;
 use crate::model::{CallClassification, EventLog, InternalCall};
 use crate::types::actions::SpecificAction;
-use crate::types::{Action, Inspection, Protocol, TransactionData};
+use crate::types::{Action, Evaluation, Inspection, Protocol, TransactionData};
 use ethers::prelude::BaseContract;
+use ethers::types::{Block, Transaction};
 use std::borrow::Cow;
 
 pub trait Reducer {
@@ -19,6 +20,21 @@ pub trait TxReducer {
     fn reduce_tx(&self, _: &mut TransactionData);
 }
 
+/// Reduces across all the transactions of a block, e.g. to find transactions
+/// that only make sense in relation to each other.
+///
+/// Both stages are empty by default, so a reducer only implements the ones it
+/// needs.
+pub trait BlockReducer {
+    /// Receives all the block's transactions after they were inspected and
+    /// reduced, sorted by their `transaction_position`.
+    fn reduce_block_txs(&self, _block: &Block<Transaction>, _txs: &mut [TransactionData]) {}
+
+    /// Receives the `Evaluation`s of all the block's transactions, sorted by
+    /// their `transaction_position`.
+    fn reduce_block(&self, _block: &Block<Transaction>, _evaluations: &mut [Evaluation]) {}
+}
+
 /// Trait for defining an inspector for a specific DeFi protocol
 pub trait Inspector: core::fmt::Debug {
     /// Classifies an inspection's actions
@@ -91,7 +107,7 @@ pub(crate) fn inspect_tx<T: DefiProtocol + ?Sized>(proto: &T, tx: &mut Transacti
         }
         // check if the address of the contract is a known protocol
         if call.protocol.is_none() {
-            call.protocol = PROTOCOLS.get(&call.to).cloned();
+            call.protocol = registry().protocol(&call.to);
         }
     }
 
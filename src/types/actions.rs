@@ -1,8 +1,13 @@
 use crate::addresses::lookup;
+use crate::mevdb::DbError;
+use crate::model::{FromSqlExt, SqlRowExt};
+use crate::types::Protocol;
 
 use ethers::types::{Address, Bytes, U256};
 
 use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::Row;
 
 // https://github.com/flashbots/mev-inspect/blob/master/src/types.ts#L65-L87
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
@@ -19,6 +24,10 @@ pub enum SpecificAction {
     AddLiquidity(AddLiquidity),
     RemoveLiquidity(RemoveLiquidity),
 
+    FlashLoan(FlashLoan),
+    AuctionKick(AuctionKick),
+    AuctionBid(AuctionBid),
+
     Arbitrage(Arbitrage),
     ProfitableLiquidation(ProfitableLiquidation),
 
@@ -29,12 +38,16 @@ pub enum SpecificAction {
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub struct AddLiquidity {
+    /// The pool the liquidity was added to
+    pub pool: Address,
     pub tokens: Vec<Address>,
     pub amounts: Vec<U256>,
 }
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub struct RemoveLiquidity {
+    /// The pool the liquidity was removed from
+    pub pool: Address,
     pub tokens: Vec<Address>,
     pub amounts: Vec<U256>,
 }
@@ -45,6 +58,114 @@ impl From<AddLiquidity> for SpecificAction {
     }
 }
 
+impl From<RemoveLiquidity> for SpecificAction {
+    fn from(src: RemoveLiquidity) -> Self {
+        SpecificAction::RemoveLiquidity(src)
+    }
+}
+
+/// A loan that must be repaid within the same transaction
+#[derive(Clone, PartialOrd, PartialEq)]
+pub struct FlashLoan {
+    /// The contract that lent the tokens
+    pub lender: Address,
+    pub recipient: Address,
+    pub token: Address,
+    pub amount: U256,
+    /// The fee paid on top of `amount`
+    pub fee: U256,
+}
+
+impl From<FlashLoan> for SpecificAction {
+    fn from(src: FlashLoan) -> Self {
+        SpecificAction::FlashLoan(src)
+    }
+}
+
+impl fmt::Debug for FlashLoan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlashLoan")
+            .field("lender", &lookup(self.lender))
+            .field("recipient", &lookup(self.recipient))
+            .field("token", &lookup(self.token))
+            .field("amount", &self.amount)
+            .field("fee", &self.fee)
+            .finish()
+    }
+}
+
+/// A vault put up for a collateral auction.
+///
+/// The keeper that started the auction neither pays the debt nor receives
+/// the collateral, which happens when the auction is filled.
+#[derive(Clone, PartialOrd, PartialEq)]
+pub struct AuctionKick {
+    /// The auction contract
+    pub auction: Address,
+    /// The id of the auction
+    pub id: U256,
+    pub kicker: Address,
+    pub liquidated_user: Address,
+    /// The collateral type of the vault
+    pub collateral_type: String,
+    /// The collateral put up for auction
+    pub lot: U256,
+    /// The amount of DAI the auction has to raise
+    pub debt: U256,
+}
+
+impl From<AuctionKick> for SpecificAction {
+    fn from(src: AuctionKick) -> Self {
+        SpecificAction::AuctionKick(src)
+    }
+}
+
+impl fmt::Debug for AuctionKick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuctionKick")
+            .field("auction", &lookup(self.auction))
+            .field("id", &self.id)
+            .field("kicker", &lookup(self.kicker))
+            .field("liquidated_user", &lookup(self.liquidated_user))
+            .field("collateral_type", &self.collateral_type)
+            .field("lot", &self.lot)
+            .field("debt", &self.debt)
+            .finish()
+    }
+}
+
+/// A keeper's bid in a collateral auction
+#[derive(Clone, PartialOrd, PartialEq)]
+pub struct AuctionBid {
+    /// The auction contract
+    pub auction: Address,
+    /// The id of the auction
+    pub id: U256,
+    pub bidder: Address,
+    /// The collateral the bidder bids for
+    pub lot: U256,
+    /// The amount of DAI the bidder bids
+    pub bid: U256,
+}
+
+impl From<AuctionBid> for SpecificAction {
+    fn from(src: AuctionBid) -> Self {
+        SpecificAction::AuctionBid(src)
+    }
+}
+
+impl fmt::Debug for AuctionBid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuctionBid")
+            .field("auction", &lookup(self.auction))
+            .field("id", &self.id)
+            .field("bidder", &lookup(self.bidder))
+            .field("lot", &self.lot)
+            .field("bid", &self.bid)
+            .finish()
+    }
+}
+
 impl SpecificAction {
     pub fn as_deposit(&self) -> Option<&Deposit> {
         match self {
@@ -116,6 +237,27 @@ impl SpecificAction {
             _ => None,
         }
     }
+
+    pub fn as_flash_loan(&self) -> Option<&FlashLoan> {
+        match self {
+            SpecificAction::FlashLoan(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    pub fn as_auction_kick(&self) -> Option<&AuctionKick> {
+        match self {
+            SpecificAction::AuctionKick(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    pub fn as_auction_bid(&self) -> Option<&AuctionBid> {
+        match self {
+            SpecificAction::AuctionBid(inner) => Some(inner),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, PartialOrd, PartialEq)]
@@ -240,6 +382,36 @@ pub struct Arbitrage {
     pub profit: U256,
     pub token: Address,
     pub to: Address,
+    /// What the searcher gained in tokens other than `token`
+    pub other_profits: Vec<(Address, U256)>,
+    /// The hops of the arbitrage, in the order they were executed
+    pub legs: Vec<ArbitrageLeg>,
+}
+
+impl Arbitrage {
+    /// The tokens along the path of the arbitrage, starting and ending with the
+    /// same token for a closed cycle
+    pub fn path(&self) -> Vec<Address> {
+        self.legs
+            .first()
+            .map(|leg| leg.token_in)
+            .into_iter()
+            .chain(self.legs.iter().map(|leg| leg.token_out))
+            .collect()
+    }
+
+    /// The distinct protocols used by the legs
+    pub fn protocols(&self) -> Vec<Protocol> {
+        self.legs.iter().filter_map(|leg| leg.protocol).fold(
+            Vec::new(),
+            |mut protocols, protocol| {
+                if !protocols.contains(&protocol) {
+                    protocols.push(protocol);
+                }
+                protocols
+            },
+        )
+    }
 }
 
 impl From<Arbitrage> for SpecificAction {
@@ -254,6 +426,77 @@ impl fmt::Debug for Arbitrage {
             .field("profit", &self.profit)
             .field("to", &lookup(self.to))
             .field("token", &lookup(self.token))
+            .field(
+                "other_profits",
+                &self
+                    .other_profits
+                    .iter()
+                    .map(|(token, profit)| (lookup(*token), profit))
+                    .collect::<Vec<_>>(),
+            )
+            .field("legs", &self.legs)
+            .finish()
+    }
+}
+
+/// A single hop of an arbitrage
+#[derive(Clone, PartialOrd, PartialEq)]
+pub struct ArbitrageLeg {
+    /// The pool that was traded against
+    pub pool: Address,
+    pub protocol: Option<Protocol>,
+    /// The token sold to the pool
+    pub token_in: Address,
+    pub amount_in: U256,
+    /// The token bought from the pool
+    pub token_out: Address,
+    pub amount_out: U256,
+}
+
+impl ArbitrageLeg {
+    /// The leg of a trade against the pool which sent `t2`
+    pub fn from_trade(trade: &Trade, protocol: Option<Protocol>) -> Self {
+        Self {
+            pool: trade.t2.from,
+            protocol,
+            token_in: trade.t1.token,
+            amount_in: trade.t1.amount,
+            token_out: trade.t2.token,
+            amount_out: trade.t2.amount,
+        }
+    }
+}
+
+impl SqlRowExt for ArbitrageLeg {
+    fn from_row(row: &Row) -> Result<Self, DbError>
+    where
+        Self: Sized,
+    {
+        let protocol: Option<&str> = row.try_get("protocol")?;
+        let protocol = protocol
+            .map(Protocol::from_str)
+            .transpose()
+            .map_err(DbError::FromSqlError)?;
+        Ok(Self {
+            pool: row.try_get_address("pool")?,
+            protocol,
+            token_in: row.try_get_address("token_in")?,
+            amount_in: row.try_get_u256("amount_in")?,
+            token_out: row.try_get_address("token_out")?,
+            amount_out: row.try_get_u256("amount_out")?,
+        })
+    }
+}
+
+impl fmt::Debug for ArbitrageLeg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArbitrageLeg")
+            .field("pool", &lookup(self.pool))
+            .field("protocol", &self.protocol)
+            .field("token_in", &lookup(self.token_in))
+            .field("amount_in", &self.amount_in)
+            .field("token_out", &lookup(self.token_out))
+            .field("amount_out", &self.amount_out)
             .finish()
     }
 }
@@ -268,6 +511,10 @@ pub struct Liquidation {
 
     pub from: Address,
     pub liquidated_user: Address,
+
+    /// The collateral type of the liquidated position, for protocols with
+    /// several of them per collateral token (MakerDAO's `ilk`s)
+    pub collateral_type: Option<String>,
 }
 
 impl From<Liquidation> for SpecificAction {
@@ -285,6 +532,7 @@ impl fmt::Debug for Liquidation {
             .field("received_amount", &self.received_amount)
             .field("liquidated_user", &lookup(self.liquidated_user))
             .field("from", &lookup(self.from))
+            .field("collateral_type", &self.collateral_type)
             .finish()
     }
 }
@@ -0,0 +1,222 @@
+//! Token balance changes within a transaction
+//!
+//! Instead of matching trades against each other, the `BalanceDeltas` are
+//! derived from everything that actually moved value in a transaction: ERC20
+//! `Transfer` events, `Deposit` and `Withdrawal` events of the wrapped native
+//! token and calls that sent ETH. Payments to the block's miner are costs
+//! like gas rather than value moved between accounts, so they are left out.
+use crate::{
+    addresses::ETH, inspectors::erc20::TransferFilter, network::network, types::TransactionData,
+};
+
+use ethers::{
+    contract::{abigen, EthLogDecode},
+    types::{Address, CallType, U256},
+};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::ops::AddAssign;
+
+abigen!(
+    WrappedNative,
+    r#"[
+        event Deposit(address indexed dst, uint256 wad)
+        event Withdrawal(address indexed src, uint256 wad)
+    ]"#
+);
+
+/// How much of a token an account received and sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BalanceDelta {
+    pub received: U256,
+    pub sent: U256,
+}
+
+impl BalanceDelta {
+    /// The net increase of the balance, zero if it decreased
+    pub fn gain(&self) -> U256 {
+        self.received.saturating_sub(self.sent)
+    }
+
+    /// The net decrease of the balance, zero if it increased
+    pub fn loss(&self) -> U256 {
+        self.sent.saturating_sub(self.received)
+    }
+
+    pub fn is_gain(&self) -> bool {
+        self.received > self.sent
+    }
+
+    pub fn is_loss(&self) -> bool {
+        self.sent > self.received
+    }
+}
+
+impl AddAssign for BalanceDelta {
+    fn add_assign(&mut self, other: Self) {
+        self.received = self.received.saturating_add(other.received);
+        self.sent = self.sent.saturating_add(other.sent);
+    }
+}
+
+/// The net per token change of every account that was involved in a transaction
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BalanceDeltas {
+    /// account -> token -> delta
+    deltas: BTreeMap<Address, BTreeMap<Address, BalanceDelta>>,
+}
+
+impl BalanceDeltas {
+    /// Collects the balance changes of all accounts in the transaction.
+    ///
+    /// ETH is tracked as the `ETH` pseudo token, ETH sent to the `coinbase`
+    /// of the transaction is not.
+    pub fn from_tx(tx: &TransactionData) -> Self {
+        let wrapped_native = network().wrapped_native;
+        let mut deltas = Self::default();
+
+        for log in tx.all_logs() {
+            if let Ok(transfer) = TransferFilter::decode_log(&log.raw_log) {
+                deltas.transfer(log.address, transfer.from, transfer.to, transfer.value);
+            } else if log.address == wrapped_native {
+                match WrappedNativeEvents::decode_log(&log.raw_log) {
+                    Ok(WrappedNativeEvents::DepositFilter(deposit)) => {
+                        deltas.mint(wrapped_native, deposit.dst, deposit.wad)
+                    }
+                    Ok(WrappedNativeEvents::WithdrawalFilter(withdrawal)) => {
+                        deltas.burn(wrapped_native, withdrawal.src, withdrawal.wad)
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+
+        for call in tx.all_calls() {
+            if call.call_type == CallType::Call
+                && !call.value.is_zero()
+                && Some(call.to) != tx.coinbase
+            {
+                deltas.transfer(*ETH, call.from, call.to, call.value);
+            }
+        }
+
+        deltas
+    }
+
+    /// Records a transfer of `amount` tokens
+    pub fn transfer(&mut self, token: Address, from: Address, to: Address, amount: U256) {
+        self.burn(token, from, amount);
+        self.mint(token, to, amount);
+    }
+
+    /// Records that `to` received `amount` tokens
+    pub fn mint(&mut self, token: Address, to: Address, amount: U256) {
+        *self.entry(to, token) += BalanceDelta {
+            received: amount,
+            sent: U256::zero(),
+        };
+    }
+
+    /// Records that `from` sent `amount` tokens
+    pub fn burn(&mut self, token: Address, from: Address, amount: U256) {
+        *self.entry(from, token) += BalanceDelta {
+            received: U256::zero(),
+            sent: amount,
+        };
+    }
+
+    fn entry(&mut self, account: Address, token: Address) -> &mut BalanceDelta {
+        self.deltas
+            .entry(account)
+            .or_default()
+            .entry(token)
+            .or_default()
+    }
+
+    /// The change of the account's balance of the token
+    pub fn get(&self, account: &Address, token: &Address) -> BalanceDelta {
+        self.deltas
+            .get(account)
+            .and_then(|tokens| tokens.get(token))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// All tokens the account received or sent
+    pub fn account(&self, account: &Address) -> impl Iterator<Item = (&Address, &BalanceDelta)> {
+        self.deltas.get(account).into_iter().flatten()
+    }
+
+    /// The changes of all accounts combined, as if they were a single account.
+    ///
+    /// Wrapping and unwrapping does not change the value an account holds, so
+    /// the wrapped native token is merged into `ETH`.
+    pub fn combined(&self, accounts: &[Address]) -> BTreeMap<Address, BalanceDelta> {
+        let mut combined = BTreeMap::<Address, BalanceDelta>::new();
+        for account in accounts.iter().unique() {
+            for (token, delta) in self.account(account) {
+                *combined.entry(native(*token)).or_default() += *delta;
+            }
+        }
+        combined
+    }
+
+    /// The combined change of the accounts' balance of the token
+    pub fn combined_of(&self, accounts: &[Address], token: Address) -> BalanceDelta {
+        self.combined(accounts)
+            .remove(&native(token))
+            .unwrap_or_default()
+    }
+}
+
+/// Maps the wrapped native token to `ETH`
+pub(crate) fn native(token: Address) -> Address {
+    if token == network().wrapped_native {
+        *ETH
+    } else {
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::addresses::{parse_address, WETH};
+    use crate::test_helpers::*;
+
+    #[test]
+    fn wrapping_is_not_a_gain() {
+        let addrs = addrs();
+        let (bot, pair) = (addrs[0], addrs[1]);
+        let mut deltas = BalanceDeltas::default();
+        // wrap 10 ETH, sell them and buy back 11 WETH
+        deltas.transfer(*ETH, bot, *WETH, 10.into());
+        deltas.mint(*WETH, bot, 10.into());
+        deltas.transfer(*WETH, bot, pair, 10.into());
+        deltas.transfer(*WETH, pair, bot, 11.into());
+
+        assert_eq!(deltas.get(&bot, &ETH).loss(), 10.into());
+        assert_eq!(deltas.get(&bot, &WETH).gain(), 11.into());
+        assert_eq!(deltas.combined_of(&[bot, bot], *WETH).gain(), 1.into());
+        assert!(deltas.combined_of(&[pair], *ETH).is_loss());
+    }
+
+    #[test]
+    // https://etherscan.io/tx/0xd369757f482fb8c62b86a136742c0ff11881ef0427a69be79f7d301bf9cf9a89
+    fn curve_arb_deltas() {
+        let tx = read_tx("curve_arb.data.json");
+        let deltas = BalanceDeltas::from_tx(&tx);
+        let combined = deltas.combined(&[tx.from, tx.contract]);
+
+        // the gross revenue of 0.0143975 WETH minus 0.0012845 ETH sent to
+        // another contract
+        assert_eq!(
+            combined[&*ETH].gain(),
+            U256::from_dec_str("13113025374450478").unwrap()
+        );
+        // sUSD dust that was left in the bot
+        let susd = parse_address("0x57ab1ec28d129707052df4df418d58a2d46d5f51");
+        assert_eq!(combined[&susd].gain(), 170_755_337_091u64.into());
+        assert!(combined.values().all(|delta| !delta.is_loss()));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
-    types::{actions::SpecificAction, Status},
-    HistoricalPrice,
+    types::{
+        actions::{Liquidation, SpecificAction},
+        Action, GasFees, Status,
+    },
+    HistoricalPrice, PriceSource,
 };
 
 use ethers::{
@@ -20,13 +23,14 @@ use std::str::FromStr;
 use thiserror::Error;
 use tokio_postgres::Row;
 
-#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum ActionType {
     Liquidation,
     Arbitrage,
     Trade,
     RemoveLiquidity,
     AddLiquidity,
+    AuctionBid,
 }
 
 impl fmt::Display for ActionType {
@@ -35,6 +39,22 @@ impl fmt::Display for ActionType {
     }
 }
 
+impl ActionType {
+    /// Returns the type of the given action, if it is one we keep track of
+    pub fn of(action: &SpecificAction) -> Option<Self> {
+        use SpecificAction::*;
+        match action {
+            Arbitrage(_) => Some(ActionType::Arbitrage),
+            Liquidation(_) | ProfitableLiquidation(_) => Some(ActionType::Liquidation),
+            Trade(_) => Some(ActionType::Trade),
+            AddLiquidity(_) => Some(ActionType::AddLiquidity),
+            RemoveLiquidity(_) => Some(ActionType::RemoveLiquidity),
+            AuctionBid(_) => Some(ActionType::AuctionBid),
+            _ => None,
+        }
+    }
+}
+
 impl FromStr for ActionType {
     type Err = String;
 
@@ -45,25 +65,203 @@ impl FromStr for ActionType {
             "trade" | "Trade" => Ok(ActionType::Trade),
             "addliquidity" | "Addliquidity" => Ok(ActionType::AddLiquidity),
             "removeliquidity" | "Removeliquidity" => Ok(ActionType::RemoveLiquidity),
+            "auctionbid" | "Auctionbid" => Ok(ActionType::AuctionBid),
             s => Err(format!("`{}` is nat a valid action type", s)),
         }
     }
 }
 
+/// How a transaction's bundle was identified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BundleType {
+    /// A bundle submitted through the Flashbots relay
+    Flashbots,
+    /// A bundle the miner included without the relay
+    Rogue,
+    /// The miner's payout of the bundle rewards
+    MinerPayout,
+    /// A group of transactions that looks like a bundle but is not listed in
+    /// the Flashbots blocks
+    Inferred,
+}
+
+impl fmt::Display for BundleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            BundleType::Flashbots => "flashbots",
+            BundleType::Rogue => "rogue",
+            BundleType::MinerPayout => "miner_payout",
+            BundleType::Inferred => "inferred",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for BundleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flashbots" => Ok(BundleType::Flashbots),
+            "rogue" => Ok(BundleType::Rogue),
+            "miner_payout" => Ok(BundleType::MinerPayout),
+            "inferred" => Ok(BundleType::Inferred),
+            s => Err(format!("`{}` is not a valid bundle type", s)),
+        }
+    }
+}
+
+/// Something that was left out of an `Evaluation`'s profit
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EvaluationWarning {
+    /// The amount of the token could not be priced in ETH
+    PriceUnavailable { token: Address, amount: U256 },
+    /// An amount of the token was `U256::MAX`, which stands for "all of it"
+    /// rather than an actual amount
+    SentinelAmount { token: Address },
+}
+
+impl fmt::Display for EvaluationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluationWarning::PriceUnavailable { token, amount } => {
+                write!(f, "price_unavailable:{:?}:{}", token, amount)
+            }
+            EvaluationWarning::SentinelAmount { token } => {
+                write!(f, "sentinel_amount:{:?}", token)
+            }
+        }
+    }
+}
+
+impl FromStr for EvaluationWarning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let token = |s: &str| Address::from_str(s).map_err(|err| err.to_string());
+        match parts.as_slice() {
+            ["price_unavailable", t, amount] => Ok(EvaluationWarning::PriceUnavailable {
+                token: token(t)?,
+                amount: U256::from_dec_str(amount).map_err(|err| err.to_string())?,
+            }),
+            ["sentinel_amount", t] => Ok(EvaluationWarning::SentinelAmount { token: token(t)? }),
+            _ => Err(format!("`{}` is not a valid evaluation warning", s)),
+        }
+    }
+}
+
+/// The debt and collateral of a liquidation and their values in ETH
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidationRecord {
+    /// The lending protocol, if known
+    pub protocol: Option<Protocol>,
+    /// The token of the repaid debt
+    pub debt_token: Address,
+    pub debt_amount: U256,
+    /// The token of the seized collateral
+    pub collateral_token: Address,
+    pub collateral_amount: U256,
+    pub liquidated_user: Address,
+    /// The value of the repaid debt, `None` if it could not be priced
+    pub debt_value: Option<U256>,
+    /// The value of the seized collateral, `None` if it could not be priced
+    pub collateral_value: Option<U256>,
+    pub price_source: PriceSource,
+}
+
+impl LiquidationRecord {
+    fn new(
+        liquidation: &Liquidation,
+        protocol: Option<Protocol>,
+        price_source: PriceSource,
+    ) -> Self {
+        Self {
+            protocol,
+            debt_token: liquidation.sent_token,
+            debt_amount: liquidation.sent_amount,
+            collateral_token: liquidation.received_token,
+            collateral_amount: liquidation.received_amount,
+            liquidated_user: liquidation.liquidated_user,
+            debt_value: None,
+            collateral_value: None,
+            price_source,
+        }
+    }
+
+    /// The value of the collateral the liquidator received on top of the
+    /// repaid debt, `None` unless both legs could be priced
+    pub fn bonus(&self) -> Option<i128> {
+        Some(to_i128(self.collateral_value?).saturating_sub(to_i128(self.debt_value?)))
+    }
+}
+
+impl SqlRowExt for LiquidationRecord {
+    fn from_row(row: &Row) -> Result<Self, DbError>
+    where
+        Self: Sized,
+    {
+        let protocol: Option<&str> = row.try_get("protocol")?;
+        let protocol = protocol
+            .map(Protocol::from_str)
+            .transpose()
+            .map_err(DbError::FromSqlError)?;
+        let price_source =
+            PriceSource::from_str(row.try_get("price_source")?).map_err(DbError::FromSqlError)?;
+        Ok(Self {
+            protocol,
+            debt_token: row.try_get_address("debt_token")?,
+            debt_amount: row.try_get_u256("debt_amount")?,
+            collateral_token: row.try_get_address("collateral_token")?,
+            collateral_amount: row.try_get_u256("collateral_amount")?,
+            liquidated_user: row.try_get_address("liquidated_user")?,
+            debt_value: row.try_get_opt_u256("debt_value")?,
+            collateral_value: row.try_get_opt_u256("collateral_value")?,
+            price_source,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Evaluation {
     /// The internal inspection which produced this evaluation
     pub tx: TransactionData,
     /// The gas used in total by this transaction
     pub gas_used: U256,
-    /// The gas price used in this transaction
+    /// The effective gas price paid by this transaction
     pub gas_price: U256,
+    /// The base fee of the block, `None` before London
+    pub base_fee: Option<U256>,
+    /// The `maxFeePerGas` of a type 2 transaction
+    pub max_fee: Option<U256>,
+    /// The `maxPriorityFeePerGas` of a type 2 transaction
+    pub max_priority_fee: Option<U256>,
     /// The actions involved
     pub actions: HashSet<ActionType>,
-    /// The money made by this transfer
+    /// The money made by this transfer, before any costs
     pub profit: U256,
+    /// The fees paid for gas, `gas_used * gas_price`
+    pub gas_cost: U256,
+    /// The part of the gas cost that was burned, `gas_used * base_fee`
+    pub burned_fee: U256,
+    /// The part of the gas cost that was paid to the miner
+    pub miner_tip: U256,
+    /// The ETH sent directly to the block's miner
+    pub coinbase_transfers: U256,
+    /// The profit after subtracting the gas cost and coinbase transfers, negative for a loss
+    pub net_profit: i128,
     /// All the protcols
     pub protocols: HashSet<Protocol>,
+    /// The transaction of the same block this one back-ran, set by the `BackrunReducer`
+    pub backrun_of: Option<TxHash>,
+    /// The index of the bundle within the block, set by the `BundleReducer`
+    pub bundle_index: Option<usize>,
+    /// How the bundle was identified
+    pub bundle_type: Option<BundleType>,
+    /// Why the `profit` may be incomplete
+    pub warnings: Vec<EvaluationWarning>,
+    /// The liquidations of the transaction
+    pub liquidations: Vec<LiquidationRecord>,
 }
 
 impl AsRef<TransactionData> for Evaluation {
@@ -75,11 +273,14 @@ impl AsRef<TransactionData> for Evaluation {
 impl Evaluation {
     /// Takes an inspection and reduces it to the data format which will be pushed
     /// to the database.
+    ///
+    /// `coinbase` is the miner of the transaction's block.
     pub async fn new<T: Middleware + 'static>(
         tx: TransactionData,
         prices: &HistoricalPrice<T>,
         gas_used: U256,
-        gas_price: U256,
+        fees: GasFees,
+        coinbase: Address,
     ) -> Result<Self, EvalError<T>> {
         // TODO: Figure out how to sum up liquidations & arbs while pruning
         // aggressively
@@ -87,28 +288,12 @@ impl Evaluation {
         // then probably this is an Arbitrage?
         let mut actions = HashSet::new();
         let mut profit = U256::zero();
+        let mut warnings = Vec::new();
+        let mut liquidations = Vec::new();
 
         for action in tx.actions() {
             // set their action type
-            use SpecificAction::*;
-            match action.deref() {
-                Arbitrage(_) => {
-                    actions.insert(ActionType::Arbitrage);
-                }
-                Liquidation(_) | ProfitableLiquidation(_) | LiquidationCheck => {
-                    actions.insert(ActionType::Liquidation);
-                }
-                Trade(_) => {
-                    actions.insert(ActionType::Trade);
-                }
-                AddLiquidity(_) => {
-                    actions.insert(ActionType::AddLiquidity);
-                }
-                RemoveLiquidity(_) => {
-                    actions.insert(ActionType::RemoveLiquidity);
-                }
-                _ => {}
-            };
+            actions.extend(ActionType::of(action.deref()));
 
             // dont try to calculate & normalize profits for unsuccessful txs
             if tx.status != Status::Success {
@@ -122,60 +307,174 @@ impl Evaluation {
                             .await
                             .map_err(EvalError::Contract)?;
                     }
+                    for (token, amount) in &arb.other_profits {
+                        match prices.quote(*token, *amount, tx.block_number).await {
+                            Ok(value) => profit += value,
+                            Err(_) => warnings.push(EvaluationWarning::PriceUnavailable {
+                                token: *token,
+                                amount: *amount,
+                            }),
+                        }
+                    }
                 }
                 SpecificAction::Liquidation(liq) => {
                     if liq.sent_amount == U256::MAX {
-                        eprintln!(
-                            "U256::max detected in {}, skipping profit calculation",
-                            tx.hash
-                        );
+                        warnings.push(EvaluationWarning::SentinelAmount {
+                            token: liq.sent_token,
+                        });
+                        liquidations.push(unpriced_liquidation(&tx, action, liq));
                         continue;
                     }
-                    let res = futures::future::join(
-                        prices.quote(liq.sent_token, liq.sent_amount, tx.block_number),
-                        prices.quote(liq.received_token, liq.received_amount, tx.block_number),
-                    )
-                    .await;
-
-                    match res {
-                        (Ok(amount_in), Ok(amount_out)) => {
-                            profit += amount_out.saturating_sub(amount_in);
+                    let record = quote_liquidation(prices, &tx, action, liq).await;
+                    match (record.debt_value, record.collateral_value) {
+                        (Some(debt), Some(collateral)) => {
+                            profit += collateral.saturating_sub(debt);
+                        }
+                        (debt, collateral) => {
+                            if debt.is_none() {
+                                warnings.push(EvaluationWarning::PriceUnavailable {
+                                    token: liq.sent_token,
+                                    amount: liq.sent_amount,
+                                });
+                            }
+                            if collateral.is_none() {
+                                warnings.push(EvaluationWarning::PriceUnavailable {
+                                    token: liq.received_token,
+                                    amount: liq.received_amount,
+                                });
+                            }
                         }
-                        _ => println!("Could not fetch prices from Uniswap"),
                     };
-
-                    if res.0.is_err() {
-                        println!("Sent: {} of token {:?}", liq.sent_amount, liq.sent_token);
-                    }
-
-                    if res.1.is_err() {
-                        println!(
-                            "Received: {} of token {:?}",
-                            liq.received_amount, liq.received_token
-                        );
-                    }
+                    liquidations.push(record);
                 }
                 SpecificAction::ProfitableLiquidation(liq) => {
-                    profit += prices
-                        .quote(liq.token, liq.profit, tx.block_number)
-                        .await
-                        .map_err(EvalError::Contract)?;
+                    let record = if liq.as_ref().sent_amount == U256::MAX {
+                        warnings.push(EvaluationWarning::SentinelAmount {
+                            token: liq.as_ref().sent_token,
+                        });
+                        unpriced_liquidation(&tx, action, liq.as_ref())
+                    } else {
+                        quote_liquidation(prices, &tx, action, liq.as_ref()).await
+                    };
+                    // with the lending protocol's oracle the profit is the
+                    // bonus it values the liquidation at
+                    match (
+                        record.price_source,
+                        record.debt_value,
+                        record.collateral_value,
+                    ) {
+                        (PriceSource::Oracle, Some(debt), Some(collateral)) => {
+                            profit += collateral.saturating_sub(debt);
+                        }
+                        _ => {
+                            profit += prices
+                                .quote(liq.token, liq.profit, tx.block_number)
+                                .await
+                                .map_err(EvalError::Contract)?;
+                        }
+                    }
+                    liquidations.push(record);
                 }
                 _ => (),
             };
         }
 
+        let gas_cost = gas_used * fees.effective_gas_price;
+        let burned_fee = fees.burned(gas_used);
+        let miner_tip = fees.miner_tip(gas_used);
+        let coinbase_transfers = if tx.status == Status::Success {
+            tx.coinbase_transfers(coinbase)
+        } else {
+            U256::zero()
+        };
+        let net_profit = net_profit(profit, gas_cost, coinbase_transfers);
+
+        for warning in &warnings {
+            log::warn!("incomplete profit of {:?}: {}", tx.hash, warning);
+        }
+
         Ok(Evaluation {
             protocols: tx.protocols(),
             tx,
             gas_used,
-            gas_price,
+            gas_price: fees.effective_gas_price,
+            base_fee: fees.base_fee,
+            max_fee: fees.max_fee,
+            max_priority_fee: fees.max_priority_fee,
             actions,
             profit,
+            gas_cost,
+            burned_fee,
+            miner_tip,
+            coinbase_transfers,
+            net_profit,
+            backrun_of: None,
+            bundle_index: None,
+            bundle_type: None,
+            warnings,
+            liquidations,
         })
     }
 }
 
+/// The lending protocol of the liquidation `action` of the transaction
+fn liquidation_protocol(tx: &TransactionData, action: &Action) -> Option<Protocol> {
+    tx.get_call(&action.call)
+        .and_then(|call| call.protocol)
+        .or_else(|| action.protocols.first().cloned())
+}
+
+/// The record of a liquidation whose amounts can not be priced
+fn unpriced_liquidation(
+    tx: &TransactionData,
+    action: &Action,
+    liquidation: &Liquidation,
+) -> LiquidationRecord {
+    LiquidationRecord::new(
+        liquidation,
+        liquidation_protocol(tx, action),
+        PriceSource::Uniswap,
+    )
+}
+
+/// Prices both legs of the liquidation `action` of the transaction
+async fn quote_liquidation<M: Middleware + 'static>(
+    prices: &HistoricalPrice<M>,
+    tx: &TransactionData,
+    action: &Action,
+    liquidation: &Liquidation,
+) -> LiquidationRecord {
+    let protocol = liquidation_protocol(tx, action);
+    let market = tx
+        .get_call(&action.call)
+        .map(|call| call.to)
+        .unwrap_or(liquidation.from);
+    let quote = prices
+        .quote_liquidation(liquidation, protocol, market, tx.block_number)
+        .await;
+    LiquidationRecord {
+        debt_value: quote.debt.ok(),
+        collateral_value: quote.collateral.ok(),
+        ..LiquidationRecord::new(liquidation, protocol, quote.source)
+    }
+}
+
+/// `revenue - gas_cost - coinbase_transfers`, saturating at the bounds of `i128`
+pub fn net_profit(revenue: U256, gas_cost: U256, coinbase_transfers: U256) -> i128 {
+    to_i128(revenue)
+        .saturating_sub(to_i128(gas_cost))
+        .saturating_sub(to_i128(coinbase_transfers))
+}
+
+/// Converts to `i128`, saturating at `i128::MAX`
+fn to_i128(value: U256) -> i128 {
+    if value > U256::from(i128::MAX as u128) {
+        i128::MAX
+    } else {
+        value.as_u128() as i128
+    }
+}
+
 impl SqlRowExt for Evaluation {
     fn from_row(row: &Row) -> Result<Self, DbError>
     where
@@ -189,6 +488,29 @@ impl SqlRowExt for Evaluation {
         let gas_price = row.try_get_u256("gas_price")?;
         let gas_used = row.try_get_u256("gas_used")?;
         let revenue = row.try_get_u256("revenue")?;
+        let base_fee = row.try_get_opt_u256("base_fee")?;
+        let max_fee = row.try_get_opt_u256("max_fee")?;
+        let max_priority_fee = row.try_get_opt_u256("max_priority_fee")?;
+        let gas_cost = row.try_get_u256("gas_cost")?;
+        let burned_fee = row.try_get_u256("burned_fee")?;
+        let miner_tip = row.try_get_u256("miner_tip")?;
+        let coinbase_transfers = row.try_get_u256("coinbase_transfers")?;
+        let net_profit = row.try_get_i128("net_profit")?;
+        let backrun_of = row.try_get_opt_h256("backrun_of")?;
+        let bundle_index = row
+            .try_get_opt_u256("bundle_index")?
+            .map(|index| index.as_usize());
+        let warnings: Vec<&str> = row.try_get("warnings")?;
+        let warnings = warnings
+            .into_iter()
+            .map(EvaluationWarning::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DbError::FromSqlError)?;
+        let bundle_type: Option<&str> = row.try_get("bundle_type")?;
+        let bundle_type = bundle_type
+            .map(BundleType::from_str)
+            .transpose()
+            .map_err(DbError::FromSqlError)?;
         let from = row.try_get_address("eoa")?;
         let contract = row.try_get_address("contract")?;
         let transaction_position = row.try_get_usize("transaction_position")?;
@@ -231,6 +553,7 @@ impl SqlRowExt for Evaluation {
                 proxy_impl,
                 hash,
                 block_number,
+                coinbase: None,
                 transaction_position,
                 logs: Default::default(),
                 calls: Default::default(),
@@ -240,8 +563,21 @@ impl SqlRowExt for Evaluation {
             protocols,
             gas_used,
             gas_price,
+            base_fee,
+            max_fee,
+            max_priority_fee,
             actions,
             profit: revenue,
+            gas_cost,
+            burned_fee,
+            miner_tip,
+            coinbase_transfers,
+            net_profit,
+            backrun_of,
+            bundle_index,
+            bundle_type,
+            warnings,
+            liquidations: Vec::new(),
         })
     }
 }
@@ -259,3 +595,71 @@ where
     #[error(transparent)]
     Contract(ContractError<M>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_type_round_trip() {
+        for action in [
+            ActionType::Liquidation,
+            ActionType::Arbitrage,
+            ActionType::Trade,
+            ActionType::RemoveLiquidity,
+            ActionType::AddLiquidity,
+            ActionType::AuctionBid,
+        ]
+        .iter()
+        {
+            assert_eq!(ActionType::from_str(&action.to_string()), Ok(*action));
+        }
+    }
+
+    #[test]
+    fn only_completed_liquidations_are_liquidations() {
+        assert_eq!(ActionType::of(&SpecificAction::LiquidationCheck), None);
+        let kick = crate::types::actions::AuctionKick {
+            auction: Address::zero(),
+            id: 1.into(),
+            kicker: Address::zero(),
+            liquidated_user: Address::zero(),
+            collateral_type: "ETH-A".to_string(),
+            lot: 1.into(),
+            debt: 1.into(),
+        };
+        assert_eq!(ActionType::of(&SpecificAction::AuctionKick(kick)), None);
+    }
+
+    #[test]
+    fn net_profit_of_a_loss() {
+        assert_eq!(net_profit(100.into(), 30.into(), 20.into()), 50);
+        // paying the miner more than the revenue is a loss
+        assert_eq!(net_profit(100.into(), 30.into(), 90.into()), -20);
+        assert_eq!(net_profit(U256::zero(), 30.into(), U256::zero()), -30);
+    }
+
+    fn record(debt_value: Option<u64>, collateral_value: Option<u64>) -> LiquidationRecord {
+        LiquidationRecord {
+            debt_value: debt_value.map(U256::from),
+            collateral_value: collateral_value.map(U256::from),
+            ..LiquidationRecord::new(&Liquidation::default(), None, PriceSource::Uniswap)
+        }
+    }
+
+    #[test]
+    fn liquidation_bonus() {
+        assert_eq!(record(Some(100), Some(108)).bonus(), Some(8));
+        // the collateral was worth less than the repaid debt
+        assert_eq!(record(Some(100), Some(90)).bonus(), Some(-10));
+        assert_eq!(record(None, Some(108)).bonus(), None);
+        assert_eq!(record(Some(100), None).bonus(), None);
+    }
+
+    #[test]
+    fn net_profit_saturates() {
+        assert_eq!(net_profit(U256::MAX, U256::zero(), U256::zero()), i128::MAX);
+        assert_eq!(net_profit(U256::MAX, 1.into(), U256::zero()), i128::MAX - 1);
+        assert_eq!(net_profit(U256::zero(), U256::MAX, U256::MAX), i128::MIN);
+    }
+}
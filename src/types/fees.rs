@@ -0,0 +1,212 @@
+//! EIP-1559 fee data of transactions
+//!
+//! The `Block`, `Transaction` and `TransactionReceipt` types of ethers predate
+//! the London hard fork and silently drop the base fee, the fee caps of type 2
+//! transactions and the effective gas price. The block and its receipts are
+//! therefore requested as raw JSON, which is decoded into both the ethers types
+//! and the minimal types of this module.
+use ethers::{
+    providers::{Middleware, ProviderError},
+    types::{Block, Transaction, TransactionReceipt, TxHash, U256, U64},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The fees a transaction paid per unit of gas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasFees {
+    /// The price that was actually paid per unit of gas
+    pub effective_gas_price: U256,
+    /// The base fee of the block, `None` before London
+    pub base_fee: Option<U256>,
+    /// The `maxFeePerGas` of a type 2 transaction
+    pub max_fee: Option<U256>,
+    /// The `maxPriorityFeePerGas` of a type 2 transaction
+    pub max_priority_fee: Option<U256>,
+}
+
+impl GasFees {
+    /// The fees of a transaction that was included before London
+    pub fn legacy(gas_price: U256) -> Self {
+        Self {
+            effective_gas_price: gas_price,
+            ..Default::default()
+        }
+    }
+
+    /// The part of the gas cost that is burned
+    pub fn burned(&self, gas_used: U256) -> U256 {
+        gas_used * self.base_fee.unwrap_or_default()
+    }
+
+    /// The part of the gas cost that is paid to the miner
+    pub fn miner_tip(&self, gas_used: U256) -> U256 {
+        gas_used
+            * self
+                .effective_gas_price
+                .saturating_sub(self.base_fee.unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeBlock {
+    #[serde(default)]
+    base_fee_per_gas: Option<U256>,
+    transactions: Vec<FeeTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeTransaction {
+    hash: TxHash,
+    #[serde(default)]
+    gas_price: Option<U256>,
+    #[serde(default)]
+    max_fee_per_gas: Option<U256>,
+    #[serde(default)]
+    max_priority_fee_per_gas: Option<U256>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeReceipt {
+    transaction_hash: TxHash,
+    #[serde(default)]
+    effective_gas_price: Option<U256>,
+}
+
+impl FeeBlock {
+    /// Combines the block's transactions with the effective gas prices of
+    /// their receipts.
+    ///
+    /// Pre-London receipts have no effective gas price, in which case it is the
+    /// gas price of the transaction.
+    fn into_fees(self, receipts: Vec<FeeReceipt>) -> HashMap<TxHash, GasFees> {
+        let effective_gas_prices = receipts
+            .into_iter()
+            .filter_map(|receipt| Some((receipt.transaction_hash, receipt.effective_gas_price?)))
+            .collect::<HashMap<_, _>>();
+        let base_fee = self.base_fee_per_gas;
+        self.transactions
+            .into_iter()
+            .map(|tx| {
+                let effective_gas_price = effective_gas_prices
+                    .get(&tx.hash)
+                    .cloned()
+                    .or(tx.gas_price)
+                    .unwrap_or_default();
+                let fees = GasFees {
+                    effective_gas_price,
+                    base_fee,
+                    max_fee: tx.max_fee_per_gas,
+                    max_priority_fee: tx.max_priority_fee_per_gas,
+                };
+                (tx.hash, fees)
+            })
+            .collect()
+    }
+}
+
+/// Decodes the `GasFees` of all transactions from the raw JSON of the block,
+/// including its transactions, and its receipts
+pub fn block_fees(
+    block: &Value,
+    receipts: &Value,
+) -> Result<HashMap<TxHash, GasFees>, serde_json::Error> {
+    let block = FeeBlock::deserialize(block)?;
+    let receipts = Vec::<FeeReceipt>::deserialize(receipts)?;
+    Ok(block.into_fees(receipts))
+}
+
+/// Fetches the block with its transactions and receipts and the `GasFees` of
+/// all its transactions, `None` if the block does not exist
+#[allow(clippy::type_complexity)]
+pub async fn block_with_fees<M: Middleware>(
+    provider: &M,
+    block_number: u64,
+) -> Result<
+    Option<(
+        Block<Transaction>,
+        Vec<TransactionReceipt>,
+        HashMap<TxHash, GasFees>,
+    )>,
+    ProviderError,
+> {
+    let number = U64::from(block_number);
+    let block = provider
+        .provider()
+        .request::<_, Value>("eth_getBlockByNumber", (number, true));
+    let receipts = provider
+        .provider()
+        .request::<_, Value>("parity_getBlockReceipts", [number]);
+    let (block, receipts) = futures::try_join!(block, receipts)?;
+    if block.is_null() {
+        return Ok(None);
+    }
+    let fees = block_fees(&block, &receipts)?;
+    Ok(Some((
+        serde_json::from_value(block)?,
+        serde_json::from_value(receipts)?,
+        fees,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_eip1559_fees() {
+        let block: Value = serde_json::from_str(
+            r#"{
+                "baseFeePerGas": "0x2540be400",
+                "transactions": [
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                        "gasPrice": "0x2e90edd000",
+                        "maxFeePerGas": "0x2e90edd000",
+                        "maxPriorityFeePerGas": "0x3b9aca00"
+                    },
+                    {
+                        "hash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+                        "gasPrice": "0x4a817c800"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let receipts: Value = serde_json::from_str(
+            r#"[
+                {
+                    "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000001",
+                    "effectiveGasPrice": "0x28fa6ae00"
+                },
+                {
+                    "transactionHash": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                }
+            ]"#,
+        )
+        .unwrap();
+        let fees = block_fees(&block, &receipts).unwrap();
+        let gas_used = U256::from(21_000);
+
+        // 10 gwei base fee, 1 gwei tip
+        let dynamic = fees[&TxHash::from_low_u64_be(1)];
+        assert_eq!(dynamic.effective_gas_price, U256::from(11_000_000_000u64));
+        assert_eq!(dynamic.max_priority_fee, Some(U256::from(1_000_000_000u64)));
+        assert_eq!(dynamic.burned(gas_used), gas_used * 10_000_000_000u64);
+        assert_eq!(dynamic.miner_tip(gas_used), gas_used * 1_000_000_000u64);
+
+        // legacy transaction without an effective gas price in its receipt
+        let legacy = fees[&TxHash::from_low_u64_be(2)];
+        assert_eq!(legacy.effective_gas_price, U256::from(20_000_000_000u64));
+        assert_eq!(legacy.max_fee, None);
+        assert_eq!(legacy.miner_tip(gas_used), gas_used * 10_000_000_000u64);
+
+        let pre_london = GasFees::legacy(20_000_000_000u64.into());
+        assert_eq!(pre_london.burned(gas_used), U256::zero());
+        assert_eq!(pre_london.miner_tip(gas_used), gas_used * 20_000_000_000u64);
+    }
+}
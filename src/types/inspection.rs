@@ -1,6 +1,6 @@
 use crate::model::{EventLog, InternalCall};
 use crate::{
-    addresses::{DYDX, FILTER, ZEROX},
+    addresses::{registry, DYDX, ZEROX},
     types::{
         classification::{ActionTrace, CallTrace},
         Classification, Protocol, Status,
@@ -117,7 +117,7 @@ impl<T: IntoIterator<Item = Trace>> TryFrom<TraceWrapper<T>> for Inspection {
         };
 
         // Filter out unwanted calls
-        if FILTER.get(&call.to).is_some() {
+        if registry().is_filtered(&call.to) {
             return Err(());
         }
 
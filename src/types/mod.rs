@@ -5,15 +5,18 @@ use std::str::FromStr;
 
 use ethers::types::{Action as TraceAction, Address, CallType, Trace, TxHash, U256};
 
+pub use balances::{BalanceDelta, BalanceDeltas};
 pub use classification::Classification;
-pub use evaluation::{EvalError, Evaluation};
+pub use evaluation::{BundleType, EvalError, Evaluation, EvaluationWarning, LiquidationRecord};
+pub use fees::GasFees;
 pub use inspection::Inspection;
 
 use crate::types::actions::{
-    AddLiquidity, Arbitrage, Liquidation, ProfitableLiquidation, RemoveLiquidity, Trade, Transfer,
+    AddLiquidity, Arbitrage, AuctionBid, AuctionKick, FlashLoan, Liquidation,
+    ProfitableLiquidation, RemoveLiquidity, Trade, Transfer,
 };
 use crate::{
-    addresses::{DYDX, FILTER, ZEROX},
+    addresses::{registry, BALANCER_VAULT, DYDX, ZEROX},
     is_subtrace,
     model::{EventLog, InternalCall},
     types::actions::SpecificAction,
@@ -23,9 +26,11 @@ use itertools::Itertools;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 pub mod actions;
+pub mod balances;
 
 pub(crate) mod classification;
 pub mod evaluation;
+pub mod fees;
 pub(crate) mod inspection;
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
@@ -71,13 +76,19 @@ pub enum Protocol {
     // Other AMMs
     Curve,
     Balancer,
+    BalancerV2,
+    Kyber,
+    Bancor,
 
     // Lending / Liquidations
     Aave,
     Compound,
+    Maker,
 
     // Aggregators
     ZeroEx,
+    OneInch,
+    Paraswap,
 
     // Misc.
     Flashloan,
@@ -115,8 +126,12 @@ impl Protocol {
         matches!(self, Protocol::Compound)
     }
 
+    pub fn is_maker(&self) -> bool {
+        matches!(self, Protocol::Maker)
+    }
+
     pub fn is_balancer(&self) -> bool {
-        matches!(self, Protocol::Balancer)
+        matches!(self, Protocol::Balancer | Protocol::BalancerV2)
     }
 
     pub fn is_zerox(&self) -> bool {
@@ -143,9 +158,15 @@ impl FromStr for Protocol {
             "sakeswap" => Ok(Protocol::SakeSwap),
             "curve" => Ok(Protocol::Curve),
             "balancer" => Ok(Protocol::Balancer),
+            "balancerv2" => Ok(Protocol::BalancerV2),
+            "kyber" => Ok(Protocol::Kyber),
+            "bancor" => Ok(Protocol::Bancor),
             "aave" => Ok(Protocol::Aave),
             "compound" => Ok(Protocol::Compound),
+            "maker" => Ok(Protocol::Maker),
             "zeroex" => Ok(Protocol::ZeroEx),
+            "oneinch" => Ok(Protocol::OneInch),
+            "paraswap" => Ok(Protocol::Paraswap),
             "flashloan" => Ok(Protocol::Flashloan),
             "dydx" => Ok(Protocol::DyDx),
             "erc20" => Ok(Protocol::Erc20),
@@ -283,6 +304,9 @@ pub struct TransactionData {
     /// The block number of this tx
     pub block_number: u64,
 
+    /// The miner of the tx's block, if known
+    pub coinbase: Option<Address>,
+
     /// Transaction position
     pub transaction_position: usize,
 
@@ -322,7 +346,7 @@ impl TransactionData {
         };
 
         // Filter out unwanted calls
-        if FILTER.get(&initial_call.to).is_some() {
+        if registry().is_filtered(&initial_call.to) {
             return Err((traces.collect(), logs));
         }
 
@@ -413,6 +437,7 @@ impl TransactionData {
             proxy_impl,
             hash,
             block_number,
+            coinbase: None,
             transaction_position,
             logs,
             calls,
@@ -491,6 +516,14 @@ impl TransactionData {
         self.calls.iter()
     }
 
+    /// The ETH that was sent to the `coinbase` by all calls of this transaction
+    pub fn coinbase_transfers(&self, coinbase: Address) -> U256 {
+        self.calls
+            .iter()
+            .filter(|call| call.to == coinbase && call.call_type == CallType::Call)
+            .fold(U256::zero(), |total, call| total + call.value)
+    }
+
     /// All the internal that are classified
     pub fn assigned_calls(&self) -> impl Iterator<Item = &InternalCall> {
         self.calls
@@ -693,7 +726,8 @@ impl TransactionData {
             if let Some(trade) = action.as_trade() {
                 for (idx, act) in self.actions().enumerate() {
                     if let Some(transfer) = act.as_transfer() {
-                        if (transfer == &trade.t1 || transfer == &trade.t2)
+                        if (is_trade_transfer(transfer, &trade.t1)
+                            || is_trade_transfer(transfer, &trade.t2))
                             && (action.call == act.call || is_subtrace(&action.call, &act.call))
                         {
                             transfers.insert(idx);
@@ -709,6 +743,18 @@ impl TransactionData {
     }
 }
 
+/// Whether the transfer moved the tokens of the trade's leg.
+///
+/// Balancer V2 pools hold no tokens, so the Vault sends and receives them on
+/// behalf of the pool the trade is attributed to.
+fn is_trade_transfer(transfer: &Transfer, leg: &Transfer) -> bool {
+    transfer == leg
+        || (transfer.token == leg.token
+            && transfer.amount == leg.amount
+            && ((transfer.from == leg.from && transfer.to == *BALANCER_VAULT)
+                || (transfer.from == *BALANCER_VAULT && transfer.to == leg.to)))
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct TokenTransfer {
     pub from: Address,
@@ -808,6 +854,27 @@ impl<'a> ActionsIter<'a> {
             .filter_map(|action| action.inner.as_remove_liquidity())
     }
 
+    pub fn flash_loans(&self) -> impl Iterator<Item = &'a FlashLoan> {
+        self.iter
+            .as_slice()
+            .iter()
+            .filter_map(|action| action.inner.as_flash_loan())
+    }
+
+    pub fn auction_kicks(&self) -> impl Iterator<Item = &'a AuctionKick> {
+        self.iter
+            .as_slice()
+            .iter()
+            .filter_map(|action| action.inner.as_auction_kick())
+    }
+
+    pub fn auction_bids(&self) -> impl Iterator<Item = &'a AuctionBid> {
+        self.iter
+            .as_slice()
+            .iter()
+            .filter_map(|action| action.inner.as_auction_bid())
+    }
+
     pub fn trades(&self) -> impl Iterator<Item = &'a Trade> {
         self.iter
             .as_slice()
@@ -838,3 +905,51 @@ impl<'a> Iterator for ActionsIter<'a> {
         self.iter.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn with_value(mut trace: Trace, value: u64, call_type: CallType) -> Trace {
+        if let TraceAction::Call(ref mut call) = trace.action {
+            call.value = value.into();
+            call.call_type = call_type;
+        }
+        trace
+    }
+
+    #[test]
+    fn coinbase_transfers() {
+        let addrs = addrs();
+        let [bot, contract, coinbase] = [addrs[0], addrs[1], addrs[2]];
+        let traces = vec![
+            mk_trace(bot, contract, vec![], vec![], 4),
+            with_value(
+                mk_trace(contract, coinbase, vec![], vec![0], 0),
+                100,
+                CallType::Call,
+            ),
+            with_value(
+                mk_trace(contract, coinbase, vec![], vec![1], 0),
+                20,
+                CallType::Call,
+            ),
+            // neither transfer any ETH to the coinbase
+            with_value(
+                mk_trace(contract, coinbase, vec![], vec![2], 0),
+                1000,
+                CallType::DelegateCall,
+            ),
+            with_value(
+                mk_trace(contract, coinbase, vec![], vec![3], 0),
+                1000,
+                CallType::StaticCall,
+            ),
+        ];
+        let tx = TransactionData::create(traces, vec![]).unwrap();
+
+        assert_eq!(tx.coinbase_transfers(coinbase), 120.into());
+        assert_eq!(tx.coinbase_transfers(bot), U256::zero());
+    }
+}
@@ -0,0 +1,310 @@
+//! Validation against the reference output of the TypeScript mev-inspect
+//!
+//! `res/11017338.csv` contains the per transaction results of
+//! [flashbots/mev-inspect](https://github.com/flashbots/mev-inspect) for block
+//! 11017338. Comparing our classification of the same block against it gives
+//! an estimate of the classifiers' precision and recall.
+use crate::types::{evaluation::ActionType, Status, TransactionData};
+use ethers::types::TxHash;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// The action types the reference implementation classifies
+pub const REFERENCE_ACTION_TYPES: [ActionType; 2] =
+    [ActionType::Arbitrage, ActionType::Liquidation];
+
+/// A single row of the reference CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceTx {
+    /// The transaction's hash
+    pub hash: TxHash,
+    /// The classified action type, `None` if it is unclassified or an unknown bot
+    pub action: Option<ActionType>,
+    /// `None` if the reference did not determine a status
+    pub status: Option<Status>,
+    /// The compacted protocols, e.g. `UNISWAP(2)`
+    pub providers: Vec<String>,
+    /// The profit in ETH
+    pub profit: String,
+}
+
+impl ReferenceTx {
+    /// Whether the reference classified this transaction as a successful action of the given type.
+    ///
+    /// `CHECKED` transactions only looked for an opportunity and did not execute the action.
+    pub fn is_positive(&self, action: ActionType) -> bool {
+        self.action == Some(action) && self.status == Some(Status::Success)
+    }
+}
+
+impl FromStr for ReferenceTx {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let columns = split_csv_line(s);
+        if columns.len() != 11 {
+            return Err(format!(
+                "expected 11 columns, got {}: `{}`",
+                columns.len(),
+                s
+            ));
+        }
+        let hash =
+            TxHash::from_str(columns[0].trim_start_matches("0x")).map_err(|err| err.to_string())?;
+        let action = match columns[8].as_str() {
+            "ARBITRAGE" => Some(ActionType::Arbitrage),
+            "LIQUIDATION" => Some(ActionType::Liquidation),
+            _ => None,
+        };
+        let status = if columns[9].is_empty() {
+            None
+        } else {
+            Some(Status::from_str(&columns[9].to_lowercase())?)
+        };
+        let providers = columns[7]
+            .split(',')
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(Self {
+            hash,
+            action,
+            status,
+            providers,
+            profit: columns[10].clone(),
+        })
+    }
+}
+
+/// Parses the reference CSV, skipping the header and empty lines
+pub fn read_reference(csv: &str) -> Result<Vec<ReferenceTx>, String> {
+    csv.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(ReferenceTx::from_str)
+        .collect()
+}
+
+// splits a line at all commas that are not enclosed in quotes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut columns = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.trim().chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => columns.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    columns.push(current);
+    columns
+}
+
+/// Confusion counts for a single action type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl Score {
+    /// The share of our classifications that the reference agrees with, `None` if we found nothing
+    pub fn precision(&self) -> Option<f64> {
+        let found = self.true_positives + self.false_positives;
+        if found == 0 {
+            None
+        } else {
+            Some(self.true_positives as f64 / found as f64)
+        }
+    }
+
+    /// The share of the reference's classifications we found, `None` if the reference has none
+    pub fn recall(&self) -> Option<f64> {
+        let expected = self.true_positives + self.false_negatives;
+        if expected == 0 {
+            None
+        } else {
+            Some(self.true_positives as f64 / expected as f64)
+        }
+    }
+}
+
+/// A transaction we classified differently than the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    pub hash: TxHash,
+    /// The successful action type according to the reference
+    pub expected: Option<ActionType>,
+    /// The action types we found, limited to `REFERENCE_ACTION_TYPES`
+    pub found: Vec<ActionType>,
+}
+
+/// The result of comparing our classification with the reference
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Scores per action type
+    pub scores: BTreeMap<ActionType, Score>,
+    /// All transactions where we disagree with the reference
+    pub disagreements: Vec<Disagreement>,
+}
+
+impl ValidationReport {
+    /// Compares the classified transactions against the reference.
+    ///
+    /// Reference transactions that are missing from `txs` (e.g. because they were filtered out)
+    /// are treated as unclassified.
+    pub fn new<'a>(
+        reference: &[ReferenceTx],
+        txs: impl IntoIterator<Item = &'a TransactionData>,
+    ) -> Self {
+        let found: HashMap<TxHash, HashSet<ActionType>> = txs
+            .into_iter()
+            .map(|tx| {
+                let types = tx
+                    .actions()
+                    .filter_map(|action| ActionType::of(&action.inner))
+                    .collect();
+                (tx.hash, types)
+            })
+            .collect();
+
+        let mut report = ValidationReport::default();
+        for reference_tx in reference {
+            let mut agrees = true;
+            let mut found_types = Vec::new();
+            for action in REFERENCE_ACTION_TYPES.iter().copied() {
+                let expected = reference_tx.is_positive(action);
+                let is_found = found
+                    .get(&reference_tx.hash)
+                    .map(|types| types.contains(&action))
+                    .unwrap_or_default();
+                if is_found {
+                    found_types.push(action);
+                }
+
+                let score = report.scores.entry(action).or_default();
+                match (expected, is_found) {
+                    (true, true) => score.true_positives += 1,
+                    (false, true) => score.false_positives += 1,
+                    (true, false) => score.false_negatives += 1,
+                    (false, false) => {}
+                }
+                agrees &= expected == is_found;
+            }
+
+            if !agrees {
+                report.disagreements.push(Disagreement {
+                    hash: reference_tx.hash,
+                    expected: reference_tx
+                        .action
+                        .filter(|action| reference_tx.is_positive(*action)),
+                    found: found_types,
+                });
+            }
+        }
+        report
+    }
+
+    /// Returns the score of the action type
+    pub fn score(&self, action: ActionType) -> Score {
+        self.scores.get(&action).copied().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |value: Option<f64>| {
+            value
+                .map(|v| format!("{:.2}%", v * 100.))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        for (action, score) in &self.scores {
+            writeln!(
+                f,
+                "{}: precision {}, recall {} (tp: {}, fp: {}, fn: {})",
+                action,
+                percent(score.precision()),
+                percent(score.recall()),
+                score.true_positives,
+                score.false_positives,
+                score.false_negatives
+            )?;
+        }
+        for disagreement in &self.disagreements {
+            writeln!(
+                f,
+                "{:?}: expected {:?}, found {:?}",
+                disagreement.hash, disagreement.expected, disagreement.found
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    const REFERENCE: &str = include_str!("../res/11017338.csv");
+
+    #[test]
+    fn parse_reference() {
+        let reference = read_reference(REFERENCE).unwrap();
+        assert_eq!(reference.len(), 124);
+
+        let liquidation = reference
+            .iter()
+            .find(|tx| tx.is_positive(ActionType::Liquidation))
+            .unwrap();
+        assert_eq!(
+            liquidation.hash,
+            "93690c02fc4d58734225d898ea4091df104040450c0f204b6bf6f6850ac4602f"
+                .parse::<TxHash>()
+                .unwrap()
+        );
+        assert_eq!(liquidation.providers, vec!["AAVE", "UNISWAP(7)"]);
+    }
+
+    #[test]
+    fn validate_block_11017338() {
+        let reference = read_reference(REFERENCE).unwrap();
+        let inspector = test_inspector();
+        let txs = block_txs()
+            .into_iter()
+            .map(|mut tx| {
+                inspector.inspect_tx(&mut tx);
+                inspector.reduce_tx(&mut tx);
+                tx
+            })
+            .collect::<Vec<_>>();
+
+        let report = ValidationReport::new(&reference, &txs);
+        assert_eq!(report.scores.len(), REFERENCE_ACTION_TYPES.len());
+        // one line per score and per disagreement
+        assert_eq!(
+            report.to_string().lines().count(),
+            report.scores.len() + report.disagreements.len()
+        );
+        assert!(report
+            .disagreements
+            .iter()
+            .all(|d| reference.iter().any(|tx| tx.hash == d.hash)));
+
+        // the block's only liquidation is found, and checks are not counted as liquidations
+        assert_eq!(report.score(ActionType::Liquidation).recall(), Some(1.));
+        assert_eq!(report.score(ActionType::Liquidation).precision(), Some(1.));
+        // these are also covered by the `BatchInspector` tests
+        for hash in &[
+            "46f4a4d409b44d85e64b1722b8b0f70e9713eb16d2c89da13cffd91486442627",
+            "1d9a2c8bfcd9f6e133c490d892fe3869bada484160a81966e645616cfc21652a",
+        ] {
+            let hash = hash.parse::<TxHash>().unwrap();
+            assert!(report.disagreements.iter().all(|d| d.hash != hash));
+        }
+    }
+}